@@ -0,0 +1,40 @@
+#version 330 core
+
+// Integrates the split sum approximation's BRDF term: scale (r) and bias (g) applied to F0,
+// by n·v along x and roughness along y
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform uint SampleCount = 512u;
+
+#include "shaders/common/pbr.glsl"
+
+vec2 integrate_brdf(float n_dot_v, float roughness)
+{
+    vec3 v = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    vec3 normal = vec3(0.0, 0.0, 1.0);
+    float k = roughness * roughness / 2.0;
+
+    vec2 scale_bias = vec2(0.0);
+    for (uint i = 0u; i < SampleCount; i++) {
+        vec3 h = importance_sample_ggx(hammersley(i, SampleCount), normal, roughness);
+        vec3 l = normalize(2.0 * dot(v, h) * h - v);
+        float n_dot_l = max(l.z, 0.0);
+        float n_dot_h = max(h.z, 0.0);
+        float v_dot_h = max(dot(v, h), 0.0);
+        if (n_dot_l > 0.0) {
+            float g = geometry_smith(n_dot_v, n_dot_l, k);
+            float g_visible = g * v_dot_h / (n_dot_h * n_dot_v);
+            float fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale_bias += vec2((1.0 - fresnel) * g_visible, fresnel * g_visible);
+        }
+    }
+    return scale_bias / float(SampleCount);
+}
+
+void main()
+{
+    out_color = vec4(integrate_brdf(max(frag_uv.x, 1e-3), frag_uv.y), 0.0, 1.0);
+}
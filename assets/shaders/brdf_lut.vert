@@ -0,0 +1,12 @@
+#version 330 core
+
+// A triangle covering the whole screen, without any vertex buffers
+
+out vec2 frag_uv;
+
+void main()
+{
+    vec2 position = vec2(gl_VertexID % 2, gl_VertexID / 2) * 4.0 - 1.0;
+    frag_uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
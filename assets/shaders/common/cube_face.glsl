@@ -0,0 +1,17 @@
+#ifndef CUBE_FACE_GLSL
+#define CUBE_FACE_GLSL
+
+// Direction through `uv` in [-1, 1] on `face`, following the GL cube map face orientations
+vec3 face_direction(int face, vec2 uv)
+{
+    switch (face) {
+        case 0: return vec3(1.0, -uv.y, -uv.x);
+        case 1: return vec3(-1.0, -uv.y, uv.x);
+        case 2: return vec3(uv.x, 1.0, uv.y);
+        case 3: return vec3(uv.x, -1.0, -uv.y);
+        case 4: return vec3(uv.x, -uv.y, 1.0);
+        default: return vec3(-uv.x, -uv.y, -1.0);
+    }
+}
+
+#endif
@@ -0,0 +1,78 @@
+#ifndef LIGHTING_GLSL
+#define LIGHTING_GLSL
+
+// Must match MAX_LIGHTS in src/renderer/lighting.rs
+#define MAX_LIGHTS 16
+
+#include "shaders/common/shadow.glsl"
+
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+struct Light {
+    // xyz position, w type
+    vec4 position;
+    // xyz direction the light travels in, w range
+    vec4 direction;
+    // rgb color, a intensity
+    vec4 color;
+    // x cosine of the inner angle, y cosine of the outer angle
+    vec4 cone;
+};
+
+layout (std140) uniform Lights {
+    Light lights[MAX_LIGHTS];
+    vec4 ambient;
+    int light_count;
+};
+
+// Smoothly fades a point or spot light out towards its range
+float range_attenuation(float distance, float range)
+{
+    float ratio = clamp(distance / range, 0.0, 1.0);
+    float window = 1.0 - ratio * ratio * ratio * ratio;
+    return window * window / (distance * distance + 1.0);
+}
+
+// Diffuse and specular light reaching a surface at `position` with unit `normal`, seen from unit `view_dir`.
+// Blinn-Phong by default, define PHONG to reflect around the normal instead of using the half vector.
+vec3 shade(vec3 position, vec3 normal, vec3 view_dir, vec3 albedo, float shininess)
+{
+    vec3 color = ambient.rgb * albedo;
+    for (int i = 0; i < min(light_count, MAX_LIGHTS); i++) {
+        Light light = lights[i];
+        int type = int(light.position.w);
+
+        vec3 light_dir;
+        float attenuation = 1.0;
+        if (type == LIGHT_DIRECTIONAL) {
+            light_dir = -light.direction.xyz;
+        } else {
+            vec3 to_light = light.position.xyz - position;
+            float distance = length(to_light);
+            light_dir = to_light / distance;
+            attenuation = range_attenuation(distance, light.direction.w);
+            if (type == LIGHT_SPOT) {
+                float cos_angle = dot(-light_dir, light.direction.xyz);
+                attenuation *= smoothstep(light.cone.y, light.cone.x, cos_angle);
+            }
+        }
+
+        float diffuse = max(dot(normal, light_dir), 0.0);
+#ifdef PHONG
+        float specular = pow(max(dot(reflect(-light_dir, normal), view_dir), 0.0), shininess);
+#else
+        float specular = pow(max(dot(normal, normalize(light_dir + view_dir)), 0.0), shininess);
+#endif
+        if (diffuse <= 0.0) {
+            specular = 0.0;
+        }
+
+        vec3 radiance = light.color.rgb * light.color.a * attenuation * shadow_factor(i, position, normal);
+        color += (albedo * diffuse + vec3(specular)) * radiance;
+    }
+    return color;
+}
+
+#endif
@@ -0,0 +1,87 @@
+#ifndef SHADOW_GLSL
+#define SHADOW_GLSL
+
+// Shadow maps of the lights in the `Lights` block, rendered by src/renderer/shadow.rs.
+// Expects MAX_LIGHTS to be defined.
+
+// Must match MAX_SHADOW_VIEWS in src/renderer/shadow.rs
+#define MAX_SHADOW_VIEWS 8
+
+layout (std140) uniform Shadows {
+    // World space to shadow map coordinates of each view, xy within its tile and z depth in [0, 1]
+    mat4 shadow_matrices[MAX_SHADOW_VIEWS];
+    // xy offset and zw size of each view's tile in the atlas, in texture coordinates
+    vec4 shadow_tiles[MAX_SHADOW_VIEWS];
+    // x view depth up to which a cascade is used,
+    // y world size of a texel, which spot lights scale by the distance along their axis
+    vec4 shadow_splits[MAX_SHADOW_VIEWS];
+    // Per light, x first view and y number of views, one per cascade, 0 without a shadow
+    ivec4 light_shadows[MAX_LIGHTS];
+    // x depth bias, y normal offset in texels, z PCF kernel radius in texels
+    vec4 shadow_params;
+};
+
+// Depth atlas holding all shadow views
+uniform sampler2D ShadowMap;
+// Whether ShadowMap and the `Shadows` block are bound
+uniform int UseShadows = 0;
+uniform mat4 View;
+
+// The view of `light` covering `position`, the cascade for directional lights, or -1
+int shadow_view(int light, vec3 position)
+{
+    int first = light_shadows[light].x;
+    int count = light_shadows[light].y;
+    if (count <= 1) {
+        return count == 1 ? first : -1;
+    }
+    float depth = -(View * vec4(position, 1.0)).z;
+    for (int cascade = 0; cascade < count; cascade++) {
+        if (depth <= shadow_splits[first + cascade].x) {
+            return first + cascade;
+        }
+    }
+    return -1;
+}
+
+// Fraction of light `light` reaching `position`, filtered over the PCF kernel
+float shadow_factor(int light, vec3 position, vec3 normal)
+{
+    if (UseShadows == 0) {
+        return 1.0;
+    }
+    int view = shadow_view(light, position);
+    if (view < 0) {
+        return 1.0;
+    }
+
+    // Offsetting along the normal by a few texels moves surfaces out of their own shadow,
+    // w is 1 for directional lights and the distance along the axis for spot lights
+    float texel_size = shadow_splits[view].y * (shadow_matrices[view] * vec4(position, 1.0)).w;
+    vec4 projected = shadow_matrices[view] * vec4(position + normal * shadow_params.y * texel_size, 1.0);
+    vec3 coords = projected.xyz / projected.w;
+    if (any(lessThan(coords, vec3(0.0))) || any(greaterThan(coords, vec3(1.0)))) {
+        return 1.0;
+    }
+
+    vec4 tile = shadow_tiles[view];
+    vec2 texel = 1.0 / vec2(textureSize(ShadowMap, 0));
+    // Keeps the kernel from sampling neighbouring tiles
+    vec2 tile_min = tile.xy + texel * 0.5;
+    vec2 tile_max = tile.xy + tile.zw - texel * 0.5;
+    vec2 center = tile.xy + coords.xy * tile.zw;
+    float depth = coords.z - shadow_params.x;
+
+    int radius = int(shadow_params.z);
+    float lit = 0.0;
+    for (int x = -radius; x <= radius; x++) {
+        for (int y = -radius; y <= radius; y++) {
+            vec2 uv = clamp(center + vec2(x, y) * texel, tile_min, tile_max);
+            lit += depth <= texture(ShadowMap, uv).r ? 1.0 : 0.0;
+        }
+    }
+    float samples = float((2 * radius + 1) * (2 * radius + 1));
+    return lit / samples;
+}
+
+#endif
@@ -0,0 +1,21 @@
+#version 330 core
+
+#include "shaders/common/lighting.glsl"
+
+in vec3 world_position;
+in vec3 world_normal;
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Texture;
+uniform vec3 CameraPosition;
+uniform float Shininess = 32.0;
+
+void main()
+{
+    vec3 albedo = texture(Texture, vec2(frag_uv.x, 1 - frag_uv.y)).rgb;
+    vec3 normal = normalize(world_normal);
+    vec3 view_dir = normalize(CameraPosition - world_position);
+    out_color = vec4(shade(world_position, normal, view_dir, albedo, Shininess), 1.0);
+}
@@ -0,0 +1,169 @@
+#version 330 core
+
+// Metallic-roughness material lit by the shadowed lights of the `Lights` block and by image
+// based lighting.
+// The result is tone mapped and gamma corrected unless HDR_OUTPUT is defined.
+
+#include "shaders/common/lighting.glsl"
+#include "shaders/common/pbr.glsl"
+
+// Bits of MaterialMaps, must match the MAP_* constants in src/renderer/pbr.rs
+#define MAP_BASE_COLOR 1
+#define MAP_METALLIC_ROUGHNESS 2
+#define MAP_NORMAL 4
+#define MAP_OCCLUSION 8
+#define MAP_EMISSIVE 16
+
+in vec3 world_position;
+in vec3 world_normal;
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform vec3 CameraPosition;
+
+uniform vec4 BaseColorFactor = vec4(1.0);
+uniform float MetallicFactor = 1.0;
+uniform float RoughnessFactor = 1.0;
+uniform vec3 EmissiveFactor = vec3(0.0);
+uniform float NormalScale = 1.0;
+uniform float OcclusionStrength = 1.0;
+// Which of the maps below are bound
+uniform int MaterialMaps = 0;
+uniform sampler2D BaseColorMap;
+// Roughness in green, metallic in blue
+uniform sampler2D MetallicRoughnessMap;
+uniform sampler2D NormalMap;
+uniform sampler2D OcclusionMap;
+uniform sampler2D EmissiveMap;
+
+// Whether IrradianceMap, PrefilteredMap and BrdfLut are bound
+uniform int UseEnvironment = 0;
+uniform samplerCube IrradianceMap;
+uniform samplerCube PrefilteredMap;
+uniform sampler2D BrdfLut;
+// Mipmap level of PrefilteredMap holding roughness 1
+uniform float PrefilteredMaxLod;
+uniform float EnvironmentIntensity = 1.0;
+
+bool has_map(int map)
+{
+    return (MaterialMaps & map) != 0;
+}
+
+// Tangent space normal map lookup without vertex tangents, from screen space derivatives
+vec3 perturb_normal(vec3 normal, vec2 uv)
+{
+    vec3 dp1 = dFdx(world_position);
+    vec3 dp2 = dFdy(world_position);
+    vec2 duv1 = dFdx(uv);
+    vec2 duv2 = dFdy(uv);
+    vec3 dp2_perp = cross(dp2, normal);
+    vec3 dp1_perp = cross(normal, dp1);
+    vec3 tangent = dp2_perp * duv1.x + dp1_perp * duv2.x;
+    vec3 bitangent = dp2_perp * duv1.y + dp1_perp * duv2.y;
+    float inverse_max = inversesqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+    mat3 tbn = mat3(tangent * inverse_max, bitangent * inverse_max, normal);
+
+    vec3 mapped = texture(NormalMap, uv).xyz * 2.0 - 1.0;
+    mapped.xy *= NormalScale;
+    return normalize(tbn * mapped);
+}
+
+// Radiance reflected towards `v` from light number `index` by the Cook-Torrance BRDF
+vec3 direct_light(int index, vec3 n, vec3 v, vec3 albedo, float metallic, float roughness, vec3 f0)
+{
+    Light light = lights[index];
+    int type = int(light.position.w);
+    vec3 l;
+    float attenuation = 1.0;
+    if (type == LIGHT_DIRECTIONAL) {
+        l = -light.direction.xyz;
+    } else {
+        vec3 to_light = light.position.xyz - world_position;
+        float distance = length(to_light);
+        l = to_light / distance;
+        attenuation = range_attenuation(distance, light.direction.w);
+        if (type == LIGHT_SPOT) {
+            attenuation *= smoothstep(light.cone.y, light.cone.x, dot(-l, light.direction.xyz));
+        }
+    }
+
+    float n_dot_l = max(dot(n, l), 0.0);
+    if (n_dot_l <= 0.0) {
+        return vec3(0.0);
+    }
+    vec3 h = normalize(v + l);
+    float n_dot_v = max(dot(n, v), 1e-4);
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+
+    vec3 fresnel = fresnel_schlick(max(dot(h, v), 0.0), f0);
+    float d = distribution_ggx(max(dot(n, h), 0.0), roughness);
+    float g = geometry_smith(n_dot_v, n_dot_l, k);
+    vec3 specular = d * g * fresnel / (4.0 * n_dot_v * n_dot_l + 1e-4);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;
+
+    vec3 radiance = light.color.rgb * light.color.a * attenuation * shadow_factor(index, world_position, n);
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+void main()
+{
+    vec2 uv = vec2(frag_uv.x, 1 - frag_uv.y);
+
+    vec4 base_color = BaseColorFactor;
+    if (has_map(MAP_BASE_COLOR)) {
+        base_color *= texture(BaseColorMap, uv);
+    }
+    float metallic = MetallicFactor;
+    float roughness = RoughnessFactor;
+    if (has_map(MAP_METALLIC_ROUGHNESS)) {
+        vec4 metallic_roughness = texture(MetallicRoughnessMap, uv);
+        metallic *= metallic_roughness.b;
+        roughness *= metallic_roughness.g;
+    }
+    // Perfectly smooth surfaces make the GGX distribution degenerate
+    roughness = clamp(roughness, 0.04, 1.0);
+    metallic = clamp(metallic, 0.0, 1.0);
+    float occlusion = 1.0;
+    if (has_map(MAP_OCCLUSION)) {
+        occlusion = mix(1.0, texture(OcclusionMap, uv).r, OcclusionStrength);
+    }
+    vec3 emissive = EmissiveFactor;
+    if (has_map(MAP_EMISSIVE)) {
+        emissive *= texture(EmissiveMap, uv).rgb;
+    }
+
+    vec3 n = normalize(world_normal);
+    if (has_map(MAP_NORMAL)) {
+        n = perturb_normal(n, uv);
+    }
+    vec3 v = normalize(CameraPosition - world_position);
+    vec3 albedo = base_color.rgb;
+    vec3 f0 = mix(DIELECTRIC_F0, albedo, metallic);
+
+    vec3 color = vec3(0.0);
+    for (int i = 0; i < min(light_count, MAX_LIGHTS); i++) {
+        color += direct_light(i, n, v, albedo, metallic, roughness, f0);
+    }
+
+    if (UseEnvironment != 0) {
+        float n_dot_v = max(dot(n, v), 0.0);
+        vec3 fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+        vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * texture(IrradianceMap, n).rgb * albedo;
+        vec3 prefiltered = textureLod(PrefilteredMap, reflect(-v, n), roughness * PrefilteredMaxLod).rgb;
+        vec2 scale_bias = texture(BrdfLut, vec2(n_dot_v, roughness)).rg;
+        vec3 specular = prefiltered * (fresnel * scale_bias.x + scale_bias.y);
+        color += (diffuse + specular) * occlusion * EnvironmentIntensity;
+    } else {
+        color += ambient.rgb * albedo * occlusion;
+    }
+    color += emissive;
+
+#ifndef HDR_OUTPUT
+    // Reinhard tone mapping followed by gamma correction
+    color = color / (color + 1.0);
+    color = pow(color, vec3(1.0 / 2.2));
+#endif
+    out_color = vec4(color, base_color.a);
+}
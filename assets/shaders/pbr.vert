@@ -0,0 +1,22 @@
+#version 330 core
+
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 normal;
+layout (location = 2) in vec2 uv;
+
+out vec3 world_position;
+out vec3 world_normal;
+out vec2 frag_uv;
+
+uniform mat4 Perspective;
+uniform mat4 View;
+uniform mat4 Model;
+
+void main()
+{
+    vec4 world = Model * vec4(position, 1.0);
+    world_position = world.xyz;
+    world_normal = mat3(transpose(inverse(Model))) * normal;
+    frag_uv = uv;
+    gl_Position = Perspective * View * world;
+}
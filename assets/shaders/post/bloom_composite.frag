@@ -0,0 +1,15 @@
+#version 330 core
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Input;
+uniform sampler2D Bloom;
+uniform float Intensity;
+
+void main()
+{
+    vec4 color = texture(Input, frag_uv);
+    out_color = vec4(color.rgb + texture(Bloom, frag_uv).rgb * Intensity, color.a);
+}
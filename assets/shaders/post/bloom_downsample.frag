@@ -0,0 +1,43 @@
+#version 330 core
+
+// Halves the resolution with a 13 tap filter. The first level also keeps only the light above
+// Threshold, with a soft knee of width Knee below it.
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Input;
+uniform vec2 TexelSize;
+uniform int Prefilter;
+uniform float Threshold;
+uniform float Knee;
+
+vec3 sample_at(float x, float y)
+{
+    return texture(Input, frag_uv + vec2(x, y) * TexelSize).rgb;
+}
+
+vec3 prefilter(vec3 color)
+{
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - Threshold + Knee, 0.0, 2.0 * Knee);
+    soft = soft * soft / (4.0 * Knee + 1e-5);
+    float contribution = max(soft, brightness - Threshold) / max(brightness, 1e-5);
+    return color * contribution;
+}
+
+void main()
+{
+    vec3 a = sample_at(-2.0, 2.0), b = sample_at(0.0, 2.0), c = sample_at(2.0, 2.0);
+    vec3 d = sample_at(-2.0, 0.0), e = sample_at(0.0, 0.0), f = sample_at(2.0, 0.0);
+    vec3 g = sample_at(-2.0, -2.0), h = sample_at(0.0, -2.0), i = sample_at(2.0, -2.0);
+    vec3 j = sample_at(-1.0, 1.0), k = sample_at(1.0, 1.0);
+    vec3 l = sample_at(-1.0, -1.0), m = sample_at(1.0, -1.0);
+
+    vec3 color = e * 0.125 + (a + c + g + i) * 0.03125 + (b + d + f + h) * 0.0625 + (j + k + l + m) * 0.125;
+    if (Prefilter != 0) {
+        color = prefilter(color);
+    }
+    out_color = vec4(max(color, 0.0), 1.0);
+}
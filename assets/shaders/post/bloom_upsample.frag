@@ -0,0 +1,23 @@
+#version 330 core
+
+// Upsamples a smaller bloom level with a 3x3 tent filter, added onto the next larger level
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Input;
+uniform vec2 TexelSize;
+
+vec3 sample_at(float x, float y)
+{
+    return texture(Input, frag_uv + vec2(x, y) * TexelSize).rgb;
+}
+
+void main()
+{
+    vec3 color = sample_at(0.0, 0.0) * 4.0;
+    color += (sample_at(-1.0, 0.0) + sample_at(1.0, 0.0) + sample_at(0.0, -1.0) + sample_at(0.0, 1.0)) * 2.0;
+    color += sample_at(-1.0, -1.0) + sample_at(1.0, -1.0) + sample_at(-1.0, 1.0) + sample_at(1.0, 1.0);
+    out_color = vec4(color / 16.0, 1.0);
+}
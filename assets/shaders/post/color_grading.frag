@@ -0,0 +1,20 @@
+#version 330 core
+
+// Looks colors up in a 3D table, sampling the centers of its outer texels for 0 and 1
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Input;
+uniform sampler3D Lut;
+uniform float LutSize;
+uniform float Strength;
+
+void main()
+{
+    vec4 color = texture(Input, frag_uv);
+    vec3 coordinates = clamp(color.rgb, 0.0, 1.0) * ((LutSize - 1.0) / LutSize) + 0.5 / LutSize;
+    vec3 graded = texture(Lut, coordinates).rgb;
+    out_color = vec4(mix(color.rgb, graded, Strength), color.a);
+}
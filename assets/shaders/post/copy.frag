@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Input;
+
+void main()
+{
+    out_color = texture(Input, frag_uv);
+}
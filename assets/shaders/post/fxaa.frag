@@ -0,0 +1,47 @@
+#version 330 core
+
+// Fast approximate anti-aliasing after Timothy Lottes, blurring along edges found by luma contrast.
+// Expects gamma corrected colors.
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Input;
+uniform vec2 TexelSize;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+
+void main()
+{
+    float luma_nw = dot(texture(Input, frag_uv + vec2(-1.0, -1.0) * TexelSize).rgb, LUMA);
+    float luma_ne = dot(texture(Input, frag_uv + vec2(1.0, -1.0) * TexelSize).rgb, LUMA);
+    float luma_sw = dot(texture(Input, frag_uv + vec2(-1.0, 1.0) * TexelSize).rgb, LUMA);
+    float luma_se = dot(texture(Input, frag_uv + vec2(1.0, 1.0) * TexelSize).rgb, LUMA);
+    vec4 center = texture(Input, frag_uv);
+    float luma_m = dot(center.rgb, LUMA);
+
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Perpendicular to the luma gradient, which runs along the edge
+    vec2 direction = vec2(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, -SPAN_MAX, SPAN_MAX) * TexelSize;
+
+    vec3 near = 0.5 * (
+        texture(Input, frag_uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture(Input, frag_uv + direction * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 far = near * 0.5 + 0.25 * (
+        texture(Input, frag_uv + direction * -0.5).rgb +
+        texture(Input, frag_uv + direction * 0.5).rgb);
+
+    // The wider blur may cross over into other edges, fall back if it leaves the local range
+    float luma_far = dot(far, LUMA);
+    vec3 color = luma_far < luma_min || luma_far > luma_max ? near : far;
+    out_color = vec4(color, center.a);
+}
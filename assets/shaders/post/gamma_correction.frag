@@ -0,0 +1,14 @@
+#version 330 core
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Input;
+uniform float Gamma;
+
+void main()
+{
+    vec4 color = texture(Input, frag_uv);
+    out_color = vec4(pow(max(color.rgb, 0.0), vec3(1.0 / Gamma)), color.a);
+}
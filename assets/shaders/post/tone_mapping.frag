@@ -0,0 +1,29 @@
+#version 330 core
+
+// Maps high dynamic range colors to [0, 1], Operator 0 is Reinhard, 1 is the ACES filmic fit
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Input;
+uniform float Exposure;
+uniform int Operator;
+
+vec3 reinhard(vec3 color)
+{
+    return color / (color + 1.0);
+}
+
+// Krzysztof Narkowicz's fit of the ACES reference rendering transform
+vec3 aces(vec3 color)
+{
+    return clamp(color * (2.51 * color + 0.03) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main()
+{
+    vec4 color = texture(Input, frag_uv);
+    vec3 exposed = max(color.rgb * Exposure, 0.0);
+    out_color = vec4(Operator == 1 ? aces(exposed) : reinhard(exposed), color.a);
+}
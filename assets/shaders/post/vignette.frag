@@ -0,0 +1,21 @@
+#version 330 core
+
+// Darkens the corners, starting at Radius from the center (0.5 reaches the middle of the edges)
+// and fading over Softness
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+uniform sampler2D Input;
+uniform float Intensity;
+uniform float Radius;
+uniform float Softness;
+
+void main()
+{
+    vec4 color = texture(Input, frag_uv);
+    float distance = length(frag_uv - 0.5);
+    float darkening = smoothstep(Radius, Radius + Softness, distance) * Intensity;
+    out_color = vec4(color.rgb * (1.0 - darkening), color.a);
+}
@@ -0,0 +1,7 @@
+#version 330 core
+
+// Only the depth is written
+
+void main()
+{
+}
@@ -0,0 +1,13 @@
+#version 330 core
+
+// Depth only pass rendering shadow casters from a light
+
+layout (location = 0) in vec3 position;
+
+uniform mat4 LightViewProjection;
+uniform mat4 Model;
+
+void main()
+{
+    gl_Position = LightViewProjection * Model * vec4(position, 1.0);
+}
@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec3 direction;
+
+out vec4 out_color;
+
+uniform samplerCube Sky;
+
+void main()
+{
+    out_color = vec4(texture(Sky, direction).rgb, 1.0);
+}
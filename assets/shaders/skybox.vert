@@ -0,0 +1,15 @@
+#version 330 core
+
+// A triangle covering the whole screen on the far plane, without any vertex buffers
+
+uniform mat4 InverseViewProjection;
+
+out vec3 direction;
+
+void main()
+{
+    vec2 position = vec2(gl_VertexID % 2, gl_VertexID / 2) * 4.0 - 1.0;
+    vec4 world = InverseViewProjection * vec4(position, 1.0, 1.0);
+    direction = world.xyz / world.w;
+    gl_Position = vec4(position, 1.0, 1.0);
+}
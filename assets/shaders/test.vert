@@ -8,10 +8,11 @@ out vec2 frag_uv;
 
 uniform mat4 Perspective;
 uniform mat4 View;
+uniform mat4 Model;
 
 void main()
 {
-    mat4 MVP = Perspective * View;
+    mat4 MVP = Perspective * View * Model;
     frag_uv = uv;
     gl_Position = MVP * vec4(position, 1.0f);
 }
\ No newline at end of file
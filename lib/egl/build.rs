@@ -0,0 +1,23 @@
+extern crate gl_generator;
+
+use gl_generator::{Registry, Fallbacks, StructGenerator, Api, Profile};
+use std::env;
+use std::fs::File;
+use std::path::Path;
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let mut file_egl = File::create(&Path::new(&out_dir).join("bindings.rs")).unwrap();
+
+    let registry = Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [
+        "EGL_KHR_create_context",
+        "EGL_KHR_no_config_context",
+        "EGL_KHR_surfaceless_context",
+        "EGL_MESA_platform_surfaceless",
+    ]);
+
+    registry.write_bindings(
+        StructGenerator,
+        &mut file_egl
+    ).unwrap();
+}
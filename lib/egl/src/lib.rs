@@ -0,0 +1,87 @@
+// Bindings for EGL, loaded at runtime from the system libEGL
+
+mod bindings {
+    #![allow(non_camel_case_types, clippy::all)]
+
+    use std::os::raw;
+
+    pub type khronos_utime_nanoseconds_t = khronos_uint64_t;
+    pub type khronos_uint64_t = u64;
+    pub type khronos_ssize_t = raw::c_long;
+    pub type EGLint = i32;
+    pub type EGLNativeDisplayType = *const raw::c_void;
+    pub type EGLNativePixmapType = *const raw::c_void;
+    pub type EGLNativeWindowType = *const raw::c_void;
+    pub type NativeDisplayType = EGLNativeDisplayType;
+    pub type NativePixmapType = EGLNativePixmapType;
+    pub type NativeWindowType = EGLNativeWindowType;
+
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+use std::ffi::CString;
+use std::io;
+use std::ops::Deref;
+use std::os::raw::{c_char, c_void};
+
+use libloading::{Library, Symbol};
+
+pub use crate::bindings::Egl as InnerEgl;
+pub use crate::bindings::*;
+
+#[cfg(windows)]
+const LIBRARY_NAMES: [&str; 2] = ["libEGL.dll", "EGL.dll"];
+#[cfg(not(windows))]
+const LIBRARY_NAMES: [&str; 2] = ["libEGL.so.1", "libEGL.so"];
+
+pub struct Egl {
+    inner: bindings::Egl,
+    _library: Library,
+}
+
+impl Egl {
+    /**
+    Opens the system EGL library and loads all entry points from it
+
+    Core entry points are looked up in the library directly, since eglGetProcAddress
+    is only guaranteed to return extension functions prior to EGL 1.5.
+    */
+    pub fn load() -> Result<Egl, io::Error> {
+        let mut library = Library::new(LIBRARY_NAMES[0]);
+        for name in &LIBRARY_NAMES[1..] {
+            library = library.or_else(|_| Library::new(name));
+        }
+        let library = library?;
+
+        let inner = {
+            let get_proc_address: Option<Symbol<unsafe extern "C" fn(*const c_char) -> *const c_void>> =
+                unsafe { library.get(b"eglGetProcAddress\0").ok() };
+
+            bindings::Egl::load_with(|symbol| {
+                let name = CString::new(symbol).expect("Expected symbol name to have no null bytes");
+                unsafe {
+                    match library.get::<*const c_void>(name.as_bytes_with_nul()) {
+                        Ok(address) => *address,
+                        Err(_) => match get_proc_address {
+                            Some(ref get_proc_address) => get_proc_address(name.as_ptr()),
+                            None => std::ptr::null(),
+                        },
+                    }
+                }
+            })
+        };
+
+        Ok(Egl {
+            inner,
+            _library: library,
+        })
+    }
+}
+
+impl Deref for Egl {
+    type Target = bindings::Egl;
+
+    fn deref(&self) -> &bindings::Egl {
+        &self.inner
+    }
+}
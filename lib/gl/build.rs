@@ -11,6 +11,7 @@ fn main() {
 
     let registry = Registry::new(Api::Gl, (4, 5), Profile::Core, Fallbacks::All, [
         "GL_NV_command_list",
+        "GL_ARB_texture_filter_anisotropic",
     ]);
 
     if env::var("CARGO_FEATURE_DEBUG").is_ok() {
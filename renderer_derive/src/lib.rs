@@ -20,9 +20,9 @@ fn impl_vertex_attrib_pointers(syntax_tree: &syn::DeriveInput) -> proc_macro::To
     let vertex_attrib_calls = build_vertex_attrib_pointer_calls(&syntax_tree.data);
 
     let stream = quote! {
-        impl #identifier #generics #where_clause {
+        impl #generics crate::renderer::data::VertexAttribPointers for #identifier #generics #where_clause {
             fn vertex_attrib_pointers(gl: &gl::Gl) {
-                let stride = size_of::<#identifier>();
+                let stride = ::std::mem::size_of::<#identifier>();
                 let mut offset = 0;
 
                 #(#vertex_attrib_calls)*
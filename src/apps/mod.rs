@@ -0,0 +1,7 @@
+/*
+* Experiments runnable by renderer::Runner, main.rs picks one
+*/
+
+mod playground;
+
+pub use playground::Playground;
@@ -0,0 +1,243 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use glutin::dpi::PhysicalPosition;
+use glutin::event::WindowEvent;
+use nalgebra::{Point3, Unit, Vector2, Vector3, Vector4};
+
+use crate::ecs::{Schedule, World};
+use crate::failure_to_string;
+use crate::renderer::*;
+
+/**
+The playground world: a spinning cube with a physically based material on a ground slab, lit by
+a shadow casting sun, a point light and the sky, and a camera as entities under that sky,
+rendered into a high dynamic range `FrameBuffer`, which is post processed into the presented one.
+*/
+pub struct Playground {
+    world: World,
+    /// Systems run once per fixed time step
+    fixed_systems: Schedule,
+    /// Systems run once per frame before rendering
+    update_systems: Schedule,
+    /// Systems run while the frame buffer is bound
+    render_systems: Schedule,
+    /// Shared by the materials drawn with shaders/pbr
+    pbr_program: Rc<RefCell<ShaderProgram>>,
+    /// Linear colors of the rendered scene, before tone mapping
+    scene: FrameBuffer,
+    post_process: PostProcess,
+    /// The post processed frame, shown in the window and saved by screenshots
+    frame_buffer: FrameBuffer,
+    /// Last cursor position while dragging with the left mouse button
+    drag_position: Option<PhysicalPosition<f64>>,
+}
+
+impl Application for Playground {
+    fn init(context: &mut AppContext) -> Result<Self, failure::Error> {
+        let gl = &context.gl;
+        let resource = &context.resources;
+        let (width, height) = context.size();
+        unsafe {
+            gl.Enable(gl::BLEND);
+            gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl.Enable(gl::DEPTH_TEST);
+        }
+
+        // Tone mapping and gamma correction are left to the post processing chain
+        let pbr_program = ShaderProgram::from_resource_with_defines(Rc::clone(gl), resource, "shaders/pbr", &[("HDR_OUTPUT", "1")])?;
+        let pbr_program = Rc::new(RefCell::new(pbr_program));
+
+        let mut world = World::new();
+        let mut graph = SceneGraph::new();
+
+        // Camera entity
+        let camera = world.spawn();
+        world.insert(camera, Camera::new(*Vector3::z_axis() * 10., Vector3::zeros(), width as f32 / height as f32, 45.0, 0.01, 1000.0, true));
+        world.insert(camera, CameraController { speed: 6.0, sensitivity: 0.01 });
+
+        // Smiley textured cube entity
+        let cube_mesh = resource.load_obj("models/cube.obj")?;
+        let smiley = Texture::from_resource_with_descriptor(Rc::clone(gl), resource, "smiley.png",
+            &TextureDescriptor { format: TextureFormat::Srgb8Alpha8, ..Default::default() })?;
+        let cube_mesh: Rc<dyn Drawable> = Rc::new(Mesh::new(Rc::clone(gl), &cube_mesh.vertices, &cube_mesh.indices, Topology::Triangles));
+        let cube_material = PbrMaterial { base_color_map: Some(Rc::new(smiley)), roughness: 0.4, ..Default::default() };
+        let cube = world.spawn();
+        world.insert(cube, SceneNode(graph.add_node("cube", None)));
+        world.insert(cube, MeshRenderer {
+            mesh: Rc::clone(&cube_mesh),
+            material: Rc::new(cube_material.to_material(Rc::clone(&pbr_program))?),
+        });
+        world.insert(cube, Spin::new(Vector3::y_axis(), 0.5));
+
+        // Ground entity, a flattened cube below the spinning one
+        let ground_material = PbrMaterial { base_color: Vector4::new(0.6, 0.6, 0.6, 1.0), roughness: 0.8, ..Default::default() };
+        let ground = world.spawn();
+        let ground_node = graph.add_node("ground", None);
+        graph.node_mut(ground_node).set_translation(Vector3::new(0.0, -1.5, 0.0));
+        graph.node_mut(ground_node).set_scale(Vector3::new(6.0, 0.1, 6.0));
+        world.insert(ground, SceneNode(ground_node));
+        world.insert(ground, MeshRenderer {
+            mesh: cube_mesh,
+            material: Rc::new(ground_material.to_material(Rc::clone(&pbr_program))?),
+        });
+
+        // Light entities, the sun roughly matches the one in the sky texture
+        let sun = world.spawn();
+        world.insert(sun, Light::Directional(DirectionalLight {
+            direction: Unit::new_normalize(Vector3::new(-1.0, -1.0, -1.0)),
+            color: Vector3::new(1.0, 0.95, 0.85),
+            intensity: 2.0,
+        }));
+        world.insert(sun, ShadowCaster);
+        let lamp = world.spawn();
+        let lamp_node = graph.add_node("lamp", None);
+        graph.node_mut(lamp_node).set_translation(Vector3::new(-2.0, 1.0, 2.0));
+        world.insert(lamp, SceneNode(lamp_node));
+        world.insert(lamp, Light::Point(PointLight {
+            position: Point3::origin(),
+            color: Vector3::new(0.4, 0.6, 1.0),
+            intensity: 4.0,
+            range: 8.0,
+        }));
+
+        world.insert_resource(EventState::new());
+        world.insert_resource(DeltaTime(0.0));
+        world.insert_resource(FixedStepAlpha(0.0));
+        world.insert_resource(MouseDrag::default());
+        world.insert_resource(ActiveCamera(camera));
+        world.insert_resource(graph);
+        world.insert_resource(LightBuffer::new(Rc::clone(gl), Vector3::new(0.15, 0.15, 0.2)));
+        world.insert_resource(ShadowMaps::new(Rc::clone(gl), resource, ShadowSettings::default())?);
+
+        // Mipmaps let the specular prefiltering sample the sky at a matching resolution
+        let sky_descriptor = TextureDescriptor {
+            format: TextureFormat::Rgba16F,
+            wrap: (WrapMode::ClampToEdge, WrapMode::ClampToEdge),
+            ..Default::default()
+        };
+        let sky = TextureCube::from_equirectangular(Rc::clone(gl), resource, "textures/sky.hdr", 256, &sky_descriptor)?;
+        world.insert_resource(ImageBasedLighting::new(Rc::clone(gl), resource, &sky)?);
+        world.insert_resource(Skybox::new(Rc::clone(gl), resource, Rc::new(sky))?);
+
+        let mut fixed_systems = Schedule::new();
+        fixed_systems.add_system("spin", spin_system);
+        let mut update_systems = Schedule::new();
+        update_systems.add_system("camera_controller", camera_controller_system);
+        update_systems.add_system("spin_interpolation", spin_interpolation_system);
+        let mut render_systems = Schedule::new();
+        render_systems.add_system("lighting", lighting_system);
+        render_systems.add_system("shadow", shadow_system);
+        render_systems.add_system("mesh_renderer", mesh_renderer_system);
+        render_systems.add_system("skybox", skybox_system);
+
+        // Frame buffers + post processing setup
+        let scene = FrameBuffer::builder(Rc::clone(gl), width, height)
+            .color_attachments(&[ColorFormat::Rgba16F])
+            .depth_renderbuffer(DepthFormat::Depth24)
+            .build()?;
+        let frame_buffer = FrameBuffer::builder(Rc::clone(gl), width, height).build()?;
+        // A slightly warm grade with lifted shadows
+        let grade = ColorLut::from_fn(Rc::clone(gl), 16, |color| {
+            let lifted = color * 0.95 + Vector3::repeat(0.03);
+            lifted.component_mul(&Vector3::new(1.04, 1.0, 0.94)).map(|channel| channel.min(1.0))
+        });
+        let mut post_process = PostProcess::new(Rc::clone(gl), resource, width, height)?;
+        post_process.add(resource, "bloom", Effect::Bloom(Bloom::default()))?;
+        post_process.add(resource, "tone_mapping", Effect::ToneMapping(ToneMapping::default()))?;
+        post_process.add(resource, "gamma", Effect::GammaCorrection(2.2))?;
+        post_process.add(resource, "color_grading", Effect::ColorGrading(ColorGrading::new(Rc::new(grade))))?;
+        post_process.add(resource, "vignette", Effect::Vignette(Vignette::default()))?;
+        post_process.add(resource, "fxaa", Effect::Fxaa)?;
+
+        Ok(Playground {
+            world,
+            fixed_systems,
+            update_systems,
+            render_systems,
+            pbr_program,
+            scene,
+            post_process,
+            frame_buffer,
+            drag_position: None,
+        })
+    }
+
+    fn fixed_update(&mut self, _context: &mut AppContext, dt: f32) {
+        self.world.resource_mut::<DeltaTime>().0 = dt;
+        self.fixed_systems.run(&mut self.world);
+    }
+
+    fn update(&mut self, context: &mut AppContext, dt: f32) {
+        if context.hot_reload {
+            let res = &context.resources;
+            let reloads: Vec<(&str, Result<bool, failure::Error>)> = vec![
+                ("pbr", self.pbr_program.borrow_mut().reload_if_changed(res).map_err(Into::into)),
+                ("skybox", self.world.resource_mut::<Skybox>().reload_if_changed(res).map_err(Into::into)),
+                ("shadow", self.world.resource_mut::<ShadowMaps>().reload_if_changed(res).map_err(Into::into)),
+                ("post processing", self.post_process.reload_if_changed(res).map_err(Into::into)),
+            ];
+            for (name, reload) in reloads {
+                match reload {
+                    Ok(true) => println!("Reloaded {} shaders", name),
+                    Ok(false) => {},
+                    // The error ends with a line break
+                    Err(e) => eprint!("Failed to reload {} shaders: {}", name, failure_to_string(e)),
+                }
+            }
+        }
+
+        *self.world.resource_mut::<EventState>() = context.events.clone();
+        self.world.resource_mut::<DeltaTime>().0 = dt;
+        self.world.resource_mut::<FixedStepAlpha>().0 = context.clock.alpha();
+        self.update_systems.run(&mut self.world);
+    }
+
+    /**
+    Renders the world into the scene frame buffer, post processes it into the frame buffer and
+    presents that in the window
+    */
+    fn render(&mut self, context: &mut AppContext) {
+        self.scene.bind();
+        self.scene.clear([0.8, 0.8, 0.8, 1.0]);
+        self.render_systems.run(&mut self.world);
+        self.scene.unbind();
+
+        let scene = self.scene.get_texture().expect("Expected the scene frame buffer to have a color texture");
+        if let Err(e) = self.post_process.render(scene, &self.frame_buffer) {
+            eprintln!("Failed to post process the frame: {}", e);
+        }
+
+        if !context.headless {
+            let (width, height) = context.size();
+            self.frame_buffer.blit_to_screen(width, height);
+        }
+    }
+
+    fn on_event(&mut self, context: &mut AppContext, event: &WindowEvent) {
+        if let WindowEvent::CursorMoved { position, .. } = event {
+            if !context.events.is_pressed(&KeyCode::MouseLeft) {
+                self.drag_position = None;
+                return;
+            }
+            if let Some(previous) = self.drag_position {
+                let delta = Vector2::new((position.x - previous.x) as f32, (position.y - previous.y) as f32);
+                self.world.resource_mut::<MouseDrag>().0 += delta;
+            }
+            self.drag_position = Some(*position);
+        }
+    }
+
+    fn on_resize(&mut self, _context: &mut AppContext, width: u32, height: u32) {
+        for (_, camera) in self.world.storage_mut::<Camera>().iter_mut() {
+            camera.set_aspect_ratio(width, height);
+        }
+        self.scene.resize(width, height);
+        self.frame_buffer.resize(width, height);
+        self.post_process.resize(width, height);
+    }
+
+    fn frame_buffer(&self) -> Option<&FrameBuffer> {
+        Some(&self.frame_buffer)
+    }
+}
@@ -0,0 +1,73 @@
+/*
+* Entity-component system
+*
+* A `World` owns entities, their components (one `Storage` per component type) and
+* resources. A `Schedule` runs `System`s on the world in a fixed order.
+*/
+
+mod storage;
+mod world;
+mod schedule;
+
+pub use storage::Storage;
+pub use world::{Entity, World};
+pub use schedule::{Schedule, System};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(f32);
+    #[derive(Debug, PartialEq)]
+    struct Velocity(f32);
+
+    #[test]
+    fn despawned_entities_lose_components_and_indices_are_reused() {
+        let mut world = World::new();
+        let first = world.spawn();
+        world.insert(first, Position(1.0));
+        assert!(world.despawn(first));
+        assert!(!world.despawn(first));
+
+        let second = world.spawn();
+        assert_eq!(second.index, first.index);
+        assert!(!world.is_alive(first));
+        assert!(world.get::<Position>(second).is_none());
+        world.insert(second, Position(2.0));
+        assert!(world.get::<Position>(first).is_none());
+        assert_eq!(*world.get::<Position>(second).unwrap(), Position(2.0));
+    }
+
+    #[test]
+    fn systems_run_in_order_on_joined_components() {
+        let mut world = World::new();
+        let moving = world.spawn();
+        world.insert(moving, Position(0.0));
+        world.insert(moving, Velocity(2.0));
+        let fixed = world.spawn();
+        world.insert(fixed, Position(5.0));
+        world.insert_resource(Vec::<&str>::new());
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system("log", |world: &mut World| world.resource_mut::<Vec<&str>>().push("log"))
+            .add_system_before("movement", "log", |world: &mut World| {
+                let velocities = world.storage::<Velocity>();
+                for (entity, position) in world.storage_mut::<Position>().iter_mut() {
+                    if let Some(velocity) = velocities.get(entity) {
+                        position.0 += velocity.0;
+                    }
+                }
+                world.resource_mut::<Vec<&str>>().push("movement");
+            })
+            .add_system_after("last", "log", |world: &mut World| world.resource_mut::<Vec<&str>>().push("last"));
+        assert_eq!(schedule.system_names().collect::<Vec<_>>(), vec!["movement", "log", "last"]);
+
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+        assert_eq!(*world.get::<Position>(moving).unwrap(), Position(4.0));
+        assert_eq!(*world.get::<Position>(fixed).unwrap(), Position(5.0));
+        assert_eq!(*world.resource::<Vec<&str>>(), vec!["movement", "log", "last", "movement", "log", "last"]);
+    }
+}
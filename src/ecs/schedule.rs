@@ -0,0 +1,70 @@
+use super::World;
+
+/// Logic operating on the components and resources of a `World`
+pub trait System {
+    fn run(&mut self, world: &mut World);
+}
+
+impl<F> System for F
+where F: FnMut(&mut World), {
+    fn run(&mut self, world: &mut World) {
+        self(world)
+    }
+}
+
+/**
+Named systems run one after another in a fixed order
+
+Systems are appended by `add_system`, or placed relative to an already added system
+with `add_system_before`/`add_system_after`.
+*/
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<(String, Box<dyn System>)>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Schedule::default()
+    }
+
+    pub fn add_system<S>(&mut self, name: &str, system: S) -> &mut Self
+    where S: System + 'static, {
+        self.systems.push((name.into(), Box::new(system)));
+        self
+    }
+
+    /// Inserts the system right before `before`, panics if there is no such system
+    pub fn add_system_before<S>(&mut self, name: &str, before: &str, system: S) -> &mut Self
+    where S: System + 'static, {
+        let index = self.position(before);
+        self.systems.insert(index, (name.into(), Box::new(system)));
+        self
+    }
+
+    /// Inserts the system right after `after`, panics if there is no such system
+    pub fn add_system_after<S>(&mut self, name: &str, after: &str, system: S) -> &mut Self
+    where S: System + 'static, {
+        let index = self.position(after);
+        self.systems.insert(index + 1, (name.into(), Box::new(system)));
+        self
+    }
+
+    /// Names of the systems in the order they run
+    pub fn system_names(&self) -> impl Iterator<Item = &str> {
+        self.systems.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn run(&mut self, world: &mut World) {
+        for (_, system) in self.systems.iter_mut() {
+            system.run(world);
+        }
+    }
+
+    fn position(&self, name: &str) -> usize {
+        self.systems
+            .iter()
+            .position(|(system_name, _)| system_name == name)
+            .unwrap_or_else(|| panic!("Expected system {} to be scheduled", name))
+    }
+}
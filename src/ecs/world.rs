@@ -0,0 +1,171 @@
+use std::any::{type_name, Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+
+use super::storage::{AnyStorage, Storage};
+
+/// Handle to an entity, only valid until the entity is despawned
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Entity {
+    pub(super) index: u32,
+    pub(super) generation: u32,
+}
+
+/**
+Entities with their components, plus resources: singletons shared by all systems
+
+Storages and resources are borrowed individually at runtime, so a system can read one
+component type while mutating another. Borrowing the same type mutably twice panics.
+*/
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    storages: HashMap<TypeId, RefCell<Box<dyn AnyStorage>>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        World::default()
+    }
+
+    /// Creates an entity without components, reusing indices of despawned entities
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Entity { index, generation: self.generations[index as usize] }
+            },
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity { index: self.generations.len() as u32 - 1, generation: 0 }
+            },
+        }
+    }
+
+    /// Removes the entity and all its components, returns false if it was not alive
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] += 1;
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        index < self.alive.len() && self.alive[index] && self.generations[index] == entity.generation
+    }
+
+    /// Creates the storage for `T` if it does not exist yet
+    pub fn register<T>(&mut self)
+    where T: 'static, {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(Storage::<T>::new())));
+    }
+
+    /**
+    Adds `component` to `entity`, replacing and returning a previous component of the same type
+
+    Panics if the entity is not alive.
+    */
+    pub fn insert<T>(&mut self, entity: Entity, component: T) -> Option<T>
+    where T: 'static, {
+        assert!(self.is_alive(entity), "Expected {:?} to be alive", entity);
+        self.register::<T>();
+        self.storage_mut::<T>().insert(entity, component)
+    }
+
+    pub fn remove<T>(&mut self, entity: Entity) -> Option<T>
+    where T: 'static, {
+        self.storages.get(&TypeId::of::<T>())?;
+        self.storage_mut::<T>().remove(entity)
+    }
+
+    /// Borrows the component `T` of `entity`
+    pub fn get<T>(&self, entity: Entity) -> Option<Ref<'_, T>>
+    where T: 'static, {
+        self.storages.get(&TypeId::of::<T>())?;
+        Ref::filter_map(self.storage::<T>(), |storage| storage.get(entity)).ok()
+    }
+
+    /// Mutably borrows the component `T` of `entity`
+    pub fn get_mut<T>(&self, entity: Entity) -> Option<RefMut<'_, T>>
+    where T: 'static, {
+        self.storages.get(&TypeId::of::<T>())?;
+        RefMut::filter_map(self.storage_mut::<T>(), |storage| storage.get_mut(entity)).ok()
+    }
+
+    /**
+    Borrows all components of type `T`
+
+    Panics if `T` was never registered or inserted, or if the storage is mutably borrowed.
+    */
+    pub fn storage<T>(&self) -> Ref<'_, Storage<T>>
+    where T: 'static, {
+        let cell = self.storages.get(&TypeId::of::<T>()).unwrap_or_else(|| unregistered::<T>());
+        Ref::map(cell.borrow(), |storage| storage.as_any().downcast_ref().unwrap())
+    }
+
+    /**
+    Mutably borrows all components of type `T`
+
+    Panics if `T` was never registered or inserted, or if the storage is already borrowed.
+    */
+    pub fn storage_mut<T>(&self) -> RefMut<'_, Storage<T>>
+    where T: 'static, {
+        let cell = self.storages.get(&TypeId::of::<T>()).unwrap_or_else(|| unregistered::<T>());
+        RefMut::map(cell.borrow_mut(), |storage| storage.as_any_mut().downcast_mut().unwrap())
+    }
+
+    /// Adds or replaces the resource of type `T`
+    pub fn insert_resource<T>(&mut self, resource: T)
+    where T: 'static, {
+        self.resources.insert(TypeId::of::<T>(), RefCell::new(Box::new(resource)));
+    }
+
+    pub fn remove_resource<T>(&mut self) -> Option<T>
+    where T: 'static, {
+        self.resources
+            .remove(&TypeId::of::<T>())
+            .map(|resource| *resource.into_inner().downcast::<T>().unwrap())
+    }
+
+    pub fn has_resource<T>(&self) -> bool
+    where T: 'static, {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Borrows the resource `T`, panics if there is none
+    pub fn resource<T>(&self) -> Ref<'_, T>
+    where T: 'static, {
+        let cell = self.resources.get(&TypeId::of::<T>()).unwrap_or_else(|| missing_resource::<T>());
+        Ref::map(cell.borrow(), |resource| resource.downcast_ref().unwrap())
+    }
+
+    /// Mutably borrows the resource `T`, panics if there is none
+    pub fn resource_mut<T>(&self) -> RefMut<'_, T>
+    where T: 'static, {
+        let cell = self.resources.get(&TypeId::of::<T>()).unwrap_or_else(|| missing_resource::<T>());
+        RefMut::map(cell.borrow_mut(), |resource| resource.downcast_mut().unwrap())
+    }
+}
+
+fn unregistered<T>() -> ! {
+    panic!("Expected component type {} to be registered", type_name::<T>())
+}
+
+fn missing_resource<T>() -> ! {
+    panic!("Expected resource {} to be inserted", type_name::<T>())
+}
@@ -1,16 +1,38 @@
 #[macro_use] extern crate failure;
 #[macro_use] extern crate renderer_derive;
 extern crate gl;
+extern crate egl;
 extern crate nalgebra;
 extern crate glutin;
 extern crate image;
 
+use std::path::PathBuf;
+
+use apps::Playground;
+use renderer::{Runner, run_headless};
+
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+
 pub mod resources;
 pub mod renderer;
+pub mod ecs;
+pub mod apps;
 
 #[allow(arithmetic_overflow)]
 fn main() {
-    if let Err(e) = renderer::run() {
+    let mut args = std::env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("--headless") => {
+            let frames = args.next().and_then(|frames| frames.parse().ok()).unwrap_or(1);
+            let output = args.next().map(PathBuf::from);
+            run_headless::<Playground>(WIDTH, HEIGHT, frames, output.as_deref())
+        },
+        Some("--hot-reload") => Runner::new(WIDTH, HEIGHT, true).and_then(|runner| runner.run::<Playground>()),
+        _ => Runner::new(WIDTH, HEIGHT, false).and_then(|runner| runner.run::<Playground>()),
+    };
+
+    if let Err(e) = result {
         println!("{}", failure_to_string(e));
     }
 }
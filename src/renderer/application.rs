@@ -0,0 +1,307 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use glutin::event::{ElementState, Event, WindowEvent};
+use glutin::event_loop::{ControlFlow, EventLoop};
+
+use crate::resources::Resources;
+
+use super::{EventState, FrameBuffer, FrameClock, HeadlessContext, KeyCode, Window};
+
+/// Time step of `Application::fixed_update`
+const FIXED_STEP: f32 = 1.0 / 60.0;
+
+/// Frame time of headless runs, which are timed independently of the wall clock
+const HEADLESS_FRAME_TIME: f32 = 1.0 / 60.0;
+
+/**
+State shared between a runner and its application
+*/
+pub struct AppContext {
+    pub gl: Rc<gl::Gl>,
+    pub resources: Resources,
+    /// Keys and mouse buttons currently held down
+    pub events: EventState,
+    /// Assets are read from the source tree, applications should reload changed shaders
+    pub hot_reload: bool,
+    /// There is no window, only offscreen frame buffers can be rendered to
+    pub headless: bool,
+    /// Frame timing, `FrameClock::alpha` interpolates between fixed updates while rendering
+    pub clock: FrameClock,
+    width: u32,
+    height: u32,
+    exit_requested: bool,
+}
+
+impl AppContext {
+    /// Size of the window, or of the headless viewport, in pixels
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Stops the runner after the current frame
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+}
+
+/**
+An experiment driven by `Runner` (or `run_headless`)
+
+Each frame the runner calls `fixed_update` once per elapsed fixed time step, then `update`
+with the real frame time followed by `render`, and then swaps the window buffers.
+Input state in `AppContext::events` is updated before `on_event` is called.
+*/
+pub trait Application: Sized {
+    /// Creates the application once the GL context exists
+    fn init(context: &mut AppContext) -> Result<Self, failure::Error>;
+
+    /// Advances a simulation by exactly `dt` seconds, the same every call
+    fn fixed_update(&mut self, _context: &mut AppContext, _dt: f32) {}
+
+    /// Advances the application by the `dt` seconds since the last frame
+    fn update(&mut self, context: &mut AppContext, dt: f32);
+
+    fn render(&mut self, context: &mut AppContext);
+
+    fn on_event(&mut self, _context: &mut AppContext, _event: &WindowEvent) {}
+
+    /// Called after the window and viewport were resized
+    fn on_resize(&mut self, _context: &mut AppContext, _width: u32, _height: u32) {}
+
+    /// Called once before the runner exits
+    fn shutdown(&mut self, _context: &mut AppContext) {}
+
+    /// The rendered image, saved by F12 screenshots and headless runs
+    fn frame_buffer(&self) -> Option<&FrameBuffer> {
+        None
+    }
+}
+
+/**
+Owns the window, its event loop and the input state, and drives an `Application`
+*/
+pub struct Runner {
+    event_loop: EventLoop<()>,
+    window: Window,
+    context: AppContext,
+}
+
+impl Runner {
+    /**
+    Opens a window of `width` x `height` pixels
+
+    With `hot_reload`, assets are read from the source tree instead of the copy next to the
+    executable.
+    */
+    pub fn new(width: u32, height: u32, hot_reload: bool) -> Result<Runner, failure::Error> {
+        let event_loop = EventLoop::new();
+        let window = Window::from_size(width, height, &event_loop)?;
+        let resources = if hot_reload {
+            Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"))
+        } else {
+            Resources::from_relative_exe_path(Path::new("assets"))?
+        };
+
+        let context = AppContext {
+            gl: window.get_gl_handle(),
+            resources,
+            events: EventState::new(),
+            hot_reload,
+            headless: false,
+            clock: FrameClock::new(FIXED_STEP),
+            width,
+            height,
+            exit_requested: false,
+        };
+
+        Ok(Runner { event_loop, window, context })
+    }
+
+    /**
+    Initializes `A` and runs it until the window is closed or the application exits
+
+    Only returns if initialization fails. F12 saves a screenshot of `Application::frame_buffer`.
+    */
+    pub fn run<A>(self) -> Result<(), failure::Error>
+    where A: Application + 'static, {
+        let Runner { event_loop, window, mut context } = self;
+        let mut app = A::init(&mut context)?;
+        // Do not count initialization as the first frame
+        context.clock = FrameClock::new(FIXED_STEP);
+
+        event_loop.run(move |event, _, control_flow| {
+            match event {
+                Event::LoopDestroyed => app.shutdown(&mut context),
+                Event::WindowEvent { event, .. } => {
+                    match &event {
+                        WindowEvent::Resized(physical_size) => {
+                            let (width, height) = (physical_size.width, physical_size.height);
+                            context.width = width;
+                            context.height = height;
+                            window.resize(width, height);
+                            app.on_resize(&mut context, width, height);
+                        },
+                        WindowEvent::CloseRequested => context.exit(),
+                        WindowEvent::KeyboardInput { input, .. } => {
+                            if let Some(virtual_keycode) = input.virtual_keycode {
+                                let code: KeyCode = virtual_keycode.into();
+                                if code == KeyCode::F12 && input.state == ElementState::Pressed && context.events.is_released(&code) {
+                                    if let Some(frame_buffer) = app.frame_buffer() {
+                                        save_screenshot(frame_buffer);
+                                    }
+                                }
+                                context.events.process_event(code, input.state);
+                            }
+                        },
+                        WindowEvent::MouseInput { button, state, .. } => {
+                            context.events.process_event((*button).into(), *state);
+                        },
+                        _ => (),
+                    }
+                    app.on_event(&mut context, &event);
+                },
+                Event::RedrawRequested(_) => {
+                    let dt = context.clock.tick();
+                    run_frame(&mut app, &mut context, dt);
+                    window.swap_buffers();
+                    window.request_redraw();
+                },
+                _ => (),
+            }
+
+            if context.exit_requested {
+                *control_flow = ControlFlow::Exit;
+            }
+        });
+    }
+}
+
+/**
+Runs `frames` frames of `A` without creating a window, optionally saving the last
+`Application::frame_buffer` as a PNG image at `output`
+
+Intended for machines without a display server, e.g. CI running Mesa llvmpipe.
+*/
+pub fn run_headless<A>(width: u32, height: u32, frames: u32, output: Option<&Path>) -> Result<(), failure::Error>
+where A: Application, {
+    let headless_context = HeadlessContext::from_size(width, height)?;
+    let mut context = AppContext {
+        gl: headless_context.get_gl_handle(),
+        resources: Resources::from_relative_exe_path(Path::new("assets"))?,
+        events: EventState::new(),
+        hot_reload: false,
+        headless: true,
+        clock: FrameClock::new(FIXED_STEP),
+        width,
+        height,
+        exit_requested: false,
+    };
+
+    let mut app = A::init(&mut context)?;
+    for _ in 0..frames {
+        let dt = context.clock.advance(HEADLESS_FRAME_TIME);
+        run_frame(&mut app, &mut context, dt);
+        if context.exit_requested {
+            break;
+        }
+    }
+    headless_context.finish();
+
+    if let (Some(path), Some(frame_buffer)) = (output, app.frame_buffer()) {
+        frame_buffer.save_png(path)?;
+    }
+    app.shutdown(&mut context);
+
+    Ok(())
+}
+
+/// Runs the fixed updates due this frame, then updates and renders the application
+fn run_frame<A>(app: &mut A, context: &mut AppContext, dt: f32)
+where A: Application, {
+    let fixed_step = context.clock.fixed_step();
+    for _ in 0..context.clock.fixed_steps() {
+        app.fixed_update(context, fixed_step);
+    }
+    app.update(context, dt);
+    app.render(context);
+}
+
+/**
+Saves the contents of `frame_buffer` to screenshot_<unix time>.png in the working directory
+*/
+fn save_screenshot(frame_buffer: &FrameBuffer) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let path = PathBuf::from(format!("screenshot_{}.png", timestamp));
+    match frame_buffer.save_png(&path) {
+        Ok(()) => println!("Saved screenshot to {}", path.display()),
+        Err(e) => println!("Failed to save screenshot: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clears its frame buffer to red and exits after three frames
+    struct ClearApp {
+        frame_buffer: FrameBuffer,
+        updates: u32,
+        fixed_updates: u32,
+    }
+
+    impl Application for ClearApp {
+        fn init(context: &mut AppContext) -> Result<Self, failure::Error> {
+            let (width, height) = context.size();
+            Ok(ClearApp { frame_buffer: FrameBuffer::new(Rc::clone(&context.gl), width, height), updates: 0, fixed_updates: 0 })
+        }
+
+        fn fixed_update(&mut self, _context: &mut AppContext, dt: f32) {
+            assert_eq!(dt, FIXED_STEP);
+            self.fixed_updates += 1;
+        }
+
+        fn update(&mut self, context: &mut AppContext, dt: f32) {
+            assert!(dt > 0.0);
+            self.updates += 1;
+            if self.updates == 3 {
+                context.exit();
+            }
+        }
+
+        fn render(&mut self, context: &mut AppContext) {
+            assert!(context.headless);
+            self.frame_buffer.bind();
+            unsafe {
+                context.gl.ClearColor(1.0, 0.0, 0.0, 1.0);
+                context.gl.Clear(gl::COLOR_BUFFER_BIT);
+            }
+            self.frame_buffer.unbind();
+        }
+
+        fn shutdown(&mut self, _context: &mut AppContext) {
+            assert_eq!(self.updates, 3);
+            // Headless frames take exactly one fixed step
+            assert_eq!(self.fixed_updates, 3);
+        }
+
+        fn frame_buffer(&self) -> Option<&FrameBuffer> {
+            Some(&self.frame_buffer)
+        }
+    }
+
+    #[test]
+    fn headless_runner_saves_frame_buffer_of_last_frame() {
+        let temp = crate::renderer::test_support::TempResources::new("headless_runner");
+        let output = temp.path().join("frame.png");
+        run_headless::<ClearApp>(4, 4, 10, Some(&output)).unwrap();
+
+        let image = image::open(&output).unwrap().into_rgb8();
+        assert_eq!(image.dimensions(), (4, 4));
+        assert_eq!(image.get_pixel(2, 2).0, [255, 0, 0]);
+    }
+}
@@ -17,11 +17,65 @@ impl Camera {
         }
     }
 
+    /**
+    Creates a camera placed and oriented by `transform`, looking down its local -Z axis
+    */
+    pub fn from_isometry(transform: &Isometry3<f32>, aspect: f32, fov: f32, near_plane: f32, far_plane: f32, perspective: bool) -> Self {
+        // Inverse of rotation_matrix(), which is Ry(-yaw) * Rx(-pitch) * Rz(-roll)
+        let m = transform.rotation.to_rotation_matrix();
+        let yaw = -m[(0, 2)].atan2(m[(2, 2)]);
+        let pitch = m[(1, 2)].clamp(-1., 1.).asin();
+        let roll = -m[(1, 0)].atan2(m[(1, 1)]);
+        Camera::new(transform.translation.vector, Vector3::new(yaw, pitch, roll), aspect, fov, near_plane, far_plane, perspective)
+    }
+
     pub fn get_view_matrix(&self) -> Matrix4<f32> {
         let translation = Translation3::from(self.position);
         (translation * self.rotation_matrix()).inverse().to_homogeneous()
     }
 
+    pub fn get_position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn near_plane(&self) -> f32 {
+        self.near_plane
+    }
+
+    pub fn far_plane(&self) -> f32 {
+        self.far_plane
+    }
+
+    /**
+    World space corners of the view frustum between the view depths `near` and `far`,
+    the four corners at `near` first, e.g. to fit shadow cascades to slices of the frustum
+    */
+    pub fn frustum_corners(&self, near: f32, far: f32) -> [Point3<f32>; 8] {
+        let inverse_projection = self
+            .get_projection_matrix()
+            .try_inverse()
+            .expect("Expected the projection matrix to be invertible");
+        let to_world = Translation3::from(self.position) * self.rotation_matrix();
+        let mut corners = [Point3::origin(); 8];
+        for (i, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter().enumerate() {
+            let near_corner = inverse_projection.transform_point(&Point3::new(*x, *y, -1.0));
+            let far_corner = inverse_projection.transform_point(&Point3::new(*x, *y, 1.0));
+            // Frustum edges are straight lines in view space, for orthographic cameras as well
+            let at_depth = |depth: f32| {
+                let t = (depth - self.near_plane) / (self.far_plane - self.near_plane);
+                to_world * (near_corner + (far_corner - near_corner) * t)
+            };
+            corners[i] = at_depth(near);
+            corners[i + 4] = at_depth(far);
+        }
+        corners
+    }
+
+    /// The view matrix without its translation, e.g. for geometry infinitely far away
+    pub fn get_view_rotation_matrix(&self) -> Matrix4<f32> {
+        self.rotation_matrix().inverse().to_homogeneous()
+    }
+
     pub fn set_aspect_ratio(&mut self, width: u32, height: u32) {
         self.aspect = width as f32 / height as f32;
     }
@@ -32,6 +86,7 @@ impl Camera {
         self.position += rotation * move_dir * dx;
     }
 
+    #[allow(clippy::assign_op_pattern, clippy::neg_multiply)]
     pub fn rotate_camera(&mut self, delta_rotation: Vector3<f32>) {
         self.rotation += delta_rotation;
         if self.rotation[1] > RealField::frac_pi_2() {
@@ -42,6 +97,7 @@ impl Camera {
         }
     }
 
+    #[allow(clippy::needless_return)]
     fn rotation_matrix(&self) -> Rotation3<f32> {
         let yaw = Rotation3::from_axis_angle(&Vector3::y_axis(), self.rotation[0]).inverse();
         let pitch = Rotation3::from_axis_angle(&Vector3::x_axis(), self.rotation[1]).inverse();
@@ -56,4 +112,36 @@ impl Camera {
             Orthographic3::new(-10., 10., -10., 10., self.near_plane, self.far_plane).to_homogeneous()
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_isometry_matches_yaw_pitch_roll() {
+        let camera = Camera::new(Vector3::new(1., 2., 3.), Vector3::new(0.3, -0.4, 0.2), 1., 1., 0.1, 100., true);
+        let rotation = UnitQuaternion::from_rotation_matrix(&camera.rotation_matrix());
+        let isometry = Isometry3::from_parts(Translation3::new(1., 2., 3.), rotation);
+        let from_isometry = Camera::from_isometry(&isometry, 1., 1., 0.1, 100., true);
+
+        assert!((from_isometry.rotation - camera.rotation).norm() < 1e-5);
+        assert!((from_isometry.get_view_matrix() - camera.get_view_matrix()).norm() < 1e-5);
+    }
+
+    #[test]
+    fn frustum_corners_lie_on_the_frustum_edges() {
+        let camera = Camera::new(Vector3::new(1., 2., 3.), Vector3::new(0.3, -0.4, 0.), 1.5, 1., 0.1, 100., true);
+        let view = camera.get_view_matrix();
+        let view_projection = camera.get_projection_matrix() * view;
+        let corners = camera.frustum_corners(2., 10.);
+
+        for (i, corner) in corners.iter().enumerate() {
+            let depth = if i < 4 { 2. } else { 10. };
+            assert!((view.transform_point(corner).z + depth).abs() < 1e-3);
+            let ndc = view_projection.transform_point(corner);
+            assert!((ndc.x.abs() - 1.).abs() < 1e-4 && (ndc.y.abs() - 1.).abs() < 1e-4, "{:?}", ndc);
+        }
+        assert!((corners[0] - corners[4]).norm() > (corners[0] - corners[1]).norm());
+    }
+}
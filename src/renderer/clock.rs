@@ -0,0 +1,129 @@
+use std::time::Instant;
+
+/// Longest frame time fed into the fixed step accumulator, avoids endless catching up after stalls
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Weight of the newest frame in the smoothed frames per second
+const FPS_SMOOTHING: f32 = 0.1;
+
+/**
+Measures frame times and accumulates them into fixed simulation steps
+
+Call `tick` once per frame, then run one simulation update for every step returned by
+`fixed_steps`. Rendering can interpolate between the last two simulation states by `alpha`.
+*/
+pub struct FrameClock {
+    last_tick: Instant,
+    delta: f32,
+    fps: f32,
+    frame_count: u64,
+    fixed_step: f32,
+    accumulator: f32,
+}
+
+impl FrameClock {
+    /// A clock running simulation steps of `fixed_step` seconds, panics unless it is positive
+    pub fn new(fixed_step: f32) -> Self {
+        assert!(fixed_step > 0.0, "Fixed step must be positive, got {}", fixed_step);
+        FrameClock {
+            last_tick: Instant::now(),
+            delta: 0.0,
+            fps: 0.0,
+            frame_count: 0,
+            fixed_step,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Starts a new frame, returning the seconds since the previous `tick`
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        let delta = now.duration_since(self.last_tick).as_secs_f32();
+        self.last_tick = now;
+        self.advance(delta)
+    }
+
+    /// Starts a new frame that took `delta` seconds, e.g. for deterministic offline rendering
+    pub fn advance(&mut self, delta: f32) -> f32 {
+        self.delta = delta;
+        self.frame_count += 1;
+        self.accumulator += delta.min(MAX_FRAME_TIME);
+        if delta > 0.0 {
+            let fps = 1.0 / delta;
+            self.fps = if self.frame_count == 1 { fps } else { self.fps + (fps - self.fps) * FPS_SMOOTHING };
+        }
+        delta
+    }
+
+    /// Removes all whole fixed steps from the accumulator and returns how many there were
+    pub fn fixed_steps(&mut self) -> u32 {
+        let steps = (self.accumulator / self.fixed_step).floor();
+        self.accumulator -= steps * self.fixed_step;
+        steps as u32
+    }
+
+    /// Fraction of a fixed step accumulated since the last simulation update, in [0, 1)
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.fixed_step
+    }
+
+    /// Seconds between the last two ticks
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn fixed_step(&self) -> f32 {
+        self.fixed_step
+    }
+
+    /// Exponentially smoothed frames per second
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Number of ticks so far
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulates_fixed_steps_with_alpha() {
+        let mut clock = FrameClock::new(0.25);
+        assert_eq!(clock.advance(0.1), 0.1);
+        assert_eq!(clock.fixed_steps(), 0);
+        assert!((clock.alpha() - 0.4).abs() < 1e-5);
+
+        clock.advance(0.2);
+        assert_eq!(clock.fixed_steps(), 1);
+        assert!((clock.alpha() - 0.2).abs() < 1e-5);
+        assert_eq!(clock.frame_count(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_non_positive_fixed_steps() {
+        FrameClock::new(0.0);
+    }
+
+    #[test]
+    fn clamps_long_frames() {
+        let mut clock = FrameClock::new(0.125);
+        clock.advance(10.0);
+        assert_eq!(clock.fixed_steps(), 2);
+        assert_eq!(clock.delta(), 10.0);
+    }
+
+    #[test]
+    fn smooths_fps() {
+        let mut clock = FrameClock::new(0.01);
+        clock.advance(0.01);
+        assert!((clock.fps() - 100.0).abs() < 1e-3);
+        clock.advance(0.02);
+        assert!((clock.fps() - 95.0).abs() < 1e-3);
+    }
+}
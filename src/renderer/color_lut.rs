@@ -0,0 +1,194 @@
+#![allow(non_local_definitions)]
+
+use std::{ffi::c_void, rc::Rc};
+use gl::types::*;
+use image::RgbaImage;
+use nalgebra::Vector3;
+
+use crate::resources::{self, Resources};
+
+use super::texture::{self, FilterMode, SampledTexture, TextureDescriptor, TextureFormat, WrapMode};
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Failed to load color lookup table {}", name)]
+    ResourceLoad { name: String, #[cause] inner: resources::Error },
+    #[fail(display = "Color lookup table is {}x{}, expected a row of square slices, e.g. 256x16", width, height)]
+    InvalidLayout { width: u32, height: u32 },
+}
+
+/**
+A 3D color lookup table for color grading, sampled with a `sampler3D` at the input color
+
+Texel (r, g, b) of a `size`³ table holds the graded color of (r, g, b) / (size - 1), colors in
+between are interpolated linearly. Stored as half floats, so that tables may leave [0, 1].
+*/
+pub struct ColorLut {
+    gl: Rc<gl::Gl>,
+    id: GLuint,
+    size: u32,
+}
+
+impl ColorLut {
+    /// A table leaving colors unchanged
+    pub fn identity(gl: Rc<gl::Gl>, size: u32) -> Self {
+        Self::from_fn(gl, size, |color| color)
+    }
+
+    /**
+    Samples `grade` at every texel, e.g. to build a table from a color transform in code
+
+    `grade` is called with and returns linear [0, 1] colors.
+    */
+    pub fn from_fn<F>(gl: Rc<gl::Gl>, size: u32, grade: F) -> Self
+    where F: Fn(Vector3<f32>) -> Vector3<f32>, {
+        let size = size.max(2);
+        let step = 1.0 / (size - 1) as f32;
+        let mut pixels = Vec::with_capacity((size * size * size * 3) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let graded = grade(Vector3::new(r as f32, g as f32, b as f32) * step);
+                    pixels.extend_from_slice(graded.as_slice());
+                }
+            }
+        }
+
+        let lut = Self::new(gl, size);
+        lut.upload(gl::RGB, gl::FLOAT, pixels.as_ptr() as *const c_void);
+        lut
+    }
+
+    /**
+    Converts a table laid out as a row of `size` square slices, e.g. a 256x16 image for a
+    16³ table, from an identity image graded in an image editor
+
+    Red increases to the right within each slice, green downwards and blue from slice to slice.
+    */
+    pub fn from_image(gl: Rc<gl::Gl>, image: &RgbaImage) -> Result<Self, Error> {
+        let (width, height) = image.dimensions();
+        if height < 2 || width != height * height {
+            return Err(Error::InvalidLayout { width, height });
+        }
+
+        let size = height;
+        let mut pixels = Vec::with_capacity((size * size * size * 4) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    pixels.extend_from_slice(&image.get_pixel(b * size + r, g).0);
+                }
+            }
+        }
+
+        let lut = Self::new(gl, size);
+        lut.upload(gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr() as *const c_void);
+        Ok(lut)
+    }
+
+    /// Loads an image laid out as for `from_image` from `res`
+    pub fn from_resource(gl: Rc<gl::Gl>, res: &Resources, name: &str) -> Result<Self, Error> {
+        let image = res
+            .load_rgba_image(name)
+            .map_err(|inner| Error::ResourceLoad { name: name.into(), inner })?;
+        Self::from_image(gl, &image)
+    }
+
+    /// Allocates an uninitialized `size`³ table with linear filtering clamped to its edges
+    fn new(gl: Rc<gl::Gl>, size: u32) -> Self {
+        let descriptor = TextureDescriptor::render_target(TextureFormat::Rgba16F, FilterMode::Linear);
+        let (internal_format, _, _) = descriptor.format.gl_formats();
+        let mut id: GLuint = 0;
+        unsafe {
+            gl.CreateTextures(gl::TEXTURE_3D, 1, &mut id);
+            gl.TextureStorage3D(id, 1, internal_format, size as GLsizei, size as GLsizei, size as GLsizei);
+        }
+        texture::apply_sampling(&gl, id, &descriptor);
+        unsafe {
+            gl.TextureParameteri(id, gl::TEXTURE_WRAP_R, WrapMode::ClampToEdge.gl_enum() as GLint);
+        }
+
+        ColorLut { gl, id, size }
+    }
+
+    fn upload(&self, format: GLenum, pixel_type: GLenum, data: *const c_void) {
+        let size = self.size as GLsizei;
+        unsafe {
+            self.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            self.gl.TextureSubImage3D(self.id, 0, 0, 0, 0, size, size, size, format, pixel_type, data);
+            self.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+    }
+
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Number of texels along each axis
+    pub fn get_size(&self) -> u32 {
+        self.size
+    }
+}
+
+impl SampledTexture for ColorLut {
+    fn bind_to_unit(&self, unit: u32) {
+        unsafe {
+            self.gl.BindTextureUnit(unit, self.id);
+        }
+    }
+
+    fn target(&self) -> GLenum {
+        gl::TEXTURE_3D
+    }
+}
+
+impl Drop for ColorLut {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+    use crate::renderer::HeadlessContext;
+
+    fn read_texels(lut: &ColorLut) -> Vec<f32> {
+        let size = lut.get_size() as usize;
+        let mut texels = vec![0.0f32; size * size * size * 4];
+        unsafe {
+            lut.gl.GetTextureImage(lut.get_id(), 0, gl::RGBA, gl::FLOAT,
+                (texels.len() * std::mem::size_of::<f32>()) as GLsizei, texels.as_mut_ptr() as *mut c_void);
+        }
+        texels
+    }
+
+    #[test]
+    fn strip_images_match_tables_built_in_code() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let size = 4;
+        let invert = |channel: u32| 255 - (channel * 255 / (size - 1)) as u8;
+        let strip = RgbaImage::from_fn(size * size, size, |x, y| Rgba([invert(x % size), invert(y), invert(x / size), 255]));
+
+        let from_image = ColorLut::from_image(Rc::clone(&gl), &strip).unwrap();
+        let from_fn = ColorLut::from_fn(Rc::clone(&gl), size, |color| Vector3::repeat(1.0) - color);
+        assert_eq!(from_image.get_size(), size);
+        for (a, b) in read_texels(&from_image).iter().zip(read_texels(&from_fn).iter()) {
+            assert!((a - b).abs() < 1e-2, "{} differs from {}", a, b);
+        }
+
+        let identity = read_texels(&ColorLut::identity(Rc::clone(&gl), size));
+        // Texel (1, 2, 3) is the 1 + 2 * 4 + 3 * 16 = 57th, stored as half floats
+        for (texel, expected) in identity[57 * 4..57 * 4 + 3].iter().zip([1.0 / 3.0, 2.0 / 3.0, 1.0].iter()) {
+            assert!((texel - expected).abs() < 1e-3, "{} differs from {}", texel, expected);
+        }
+
+        assert!(matches!(ColorLut::from_image(Rc::clone(&gl), &RgbaImage::new(16, 16)),
+            Err(Error::InvalidLayout { width: 16, height: 16 })));
+    }
+}
@@ -0,0 +1,287 @@
+#![allow(non_local_definitions)]
+
+use std::rc::Rc;
+use gl::types::*;
+
+use crate::resources::{self, Resources};
+
+use super::shader::{self, ComputeProgram, MemoryBarrier};
+use super::texture::{self, FilterMode, LoadedImage, SampledTexture, Texture, TextureDescriptor, TextureFormat, WrapMode};
+
+/// Compute shader resampling an equirectangular image onto the faces of a cube map
+const EQUIRECTANGULAR_TO_CUBE_SHADER: &str = "shaders/equirectangular_to_cube";
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Failed to load cube map face {}", name)]
+    ResourceLoad { name: String, #[cause] inner: resources::Error },
+    #[fail(display = "Cube map face {} is {}x{}, expected {}x{}", name, width, height, size, size)]
+    InvalidFaceSize { name: String, width: u32, height: u32, size: u32 },
+    #[fail(display = "Equirectangular images can not be converted to {:?} cube maps", format)]
+    UnsupportedFormat { format: TextureFormat },
+    #[fail(display = "Failed to build the equirectangular conversion shader")]
+    ConversionShader { #[cause] inner: shader::Error },
+}
+
+/**
+A cube map texture, sampled by direction with a `samplerCube`
+
+Faces are in GL order: +X, -X, +Y, -Y, +Z, -Z, each stored with its top row first.
+*/
+pub struct TextureCube {
+    gl: Rc<gl::Gl>,
+    id: GLuint,
+    size: u32,
+    descriptor: TextureDescriptor,
+}
+
+impl TextureCube {
+    /**
+    Allocates six uninitialized `size` x `size` faces with all mipmap levels the descriptor needs
+
+    see: glTextureStorage2D
+    */
+    pub fn new(gl: Rc<gl::Gl>, size: u32, descriptor: &TextureDescriptor) -> Self {
+        let levels = Self::level_count(size, descriptor);
+        let (internal_format, _, _) = descriptor.format.gl_formats();
+        let mut id: GLuint = 0;
+        unsafe {
+            gl.CreateTextures(gl::TEXTURE_CUBE_MAP, 1, &mut id);
+            gl.TextureStorage2D(id, levels as GLsizei, internal_format, size as GLsizei, size as GLsizei);
+        }
+        texture::apply_sampling(&gl, id, descriptor);
+        unsafe {
+            gl.TextureParameteri(id, gl::TEXTURE_WRAP_R, descriptor.wrap.0.gl_enum() as GLint);
+        }
+
+        TextureCube { gl, id, size, descriptor: *descriptor }
+    }
+
+    /**
+    Loads six square face images of equal size, in the order +X, -X, +Y, -Y, +Z, -Z,
+    with the loader `Texture::from_resource_with_descriptor` uses for `descriptor.format`
+    */
+    pub fn from_faces(gl: Rc<gl::Gl>, res: &Resources, names: &[&str; 6], descriptor: &TextureDescriptor) -> Result<Self, Error> {
+        let faces = names
+            .iter()
+            .map(|&name| LoadedImage::load(res, name, descriptor.format)
+                .map_err(|inner| Error::ResourceLoad { name: name.into(), inner }))
+            .collect::<Result<Vec<LoadedImage>, Error>>()?;
+
+        let size = faces[0].dimensions().0;
+        for (face, name) in faces.iter().zip(names.iter()) {
+            let (width, height) = face.dimensions();
+            if width != size || height != size {
+                return Err(Error::InvalidFaceSize { name: (*name).into(), width, height, size });
+            }
+        }
+
+        let cube = Self::new(gl, size, descriptor);
+        unsafe {
+            cube.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            for (index, face) in faces.iter().enumerate() {
+                let (format, pixel_type, data) = face.pixels();
+                cube.gl.TextureSubImage3D(cube.id, 0, 0, 0, index as GLint, size as GLsizei, size as GLsizei, 1, format, pixel_type, data);
+            }
+            cube.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+        cube.generate_mipmaps();
+        Ok(cube)
+    }
+
+    /**
+    Loads an equirectangular (latitude-longitude) image, e.g. an .hdr environment map, and
+    resamples it to a cube map with `face_size` x `face_size` faces on the GPU
+
+    The compute shader is read from `res` as well. `descriptor.format` must be one of
+    `Rgba8`, `Rgba16F`, `Rgba32F` or `R11fG11fB10f`.
+    */
+    pub fn from_equirectangular(gl: Rc<gl::Gl>, res: &Resources, name: &str, face_size: u32, descriptor: &TextureDescriptor) -> Result<Self, Error> {
+        let source_descriptor = TextureDescriptor {
+            format: descriptor.format,
+            min_filter: FilterMode::Linear,
+            mag_filter: FilterMode::Linear,
+            mipmap_filter: None,
+            wrap: (WrapMode::Repeat, WrapMode::ClampToEdge),
+            ..Default::default()
+        };
+        let equirectangular = Texture::from_resource_with_descriptor(Rc::clone(&gl), res, name, &source_descriptor)
+            .map_err(|inner| Error::ResourceLoad { name: name.into(), inner })?;
+        Self::from_equirectangular_texture(gl, res, &equirectangular, face_size, descriptor)
+    }
+
+    /**
+    Like `from_equirectangular`, for an image that was already uploaded to a texture
+    */
+    pub fn from_equirectangular_texture(gl: Rc<gl::Gl>, res: &Resources, equirectangular: &Texture, face_size: u32, descriptor: &TextureDescriptor) -> Result<Self, Error> {
+        let image_format = match descriptor.format {
+            TextureFormat::Rgba8 => "rgba8",
+            TextureFormat::Rgba16F => "rgba16f",
+            TextureFormat::Rgba32F => "rgba32f",
+            TextureFormat::R11fG11fB10f => "r11f_g11f_b10f",
+            format => return Err(Error::UnsupportedFormat { format }),
+        };
+        let program = ComputeProgram::from_resource_with_defines(
+            Rc::clone(&gl), res, EQUIRECTANGULAR_TO_CUBE_SHADER, &[("CUBE_FORMAT", image_format)])
+            .map_err(|inner| Error::ConversionShader { inner })?;
+
+        let cube = Self::new(gl, face_size, descriptor);
+        program
+            .program()
+            .bind_texture("Equirectangular", equirectangular)
+            .map_err(|inner| Error::ConversionShader { inner })?;
+        let (internal_format, _, _) = descriptor.format.gl_formats();
+        unsafe {
+            cube.gl.BindImageTexture(0, cube.id, 0, gl::TRUE, 0, gl::WRITE_ONLY, internal_format);
+        }
+
+        let [local_x, local_y, _] = program.local_size();
+        program.dispatch(face_size.div_ceil(local_x), face_size.div_ceil(local_y), 6);
+        shader::memory_barrier(&cube.gl, MemoryBarrier::TEXTURE_FETCH | MemoryBarrier::TEXTURE_UPDATE);
+        unsafe {
+            cube.gl.BindImageTexture(0, 0, 0, gl::FALSE, 0, gl::WRITE_ONLY, internal_format);
+        }
+
+        cube.generate_mipmaps();
+        Ok(cube)
+    }
+
+    fn level_count(size: u32, descriptor: &TextureDescriptor) -> u32 {
+        match descriptor.mipmap_filter {
+            Some(_) => 32 - size.max(1).leading_zeros(),
+            None => 1,
+        }
+    }
+
+    fn generate_mipmaps(&self) {
+        if self.descriptor.mipmap_filter.is_some() {
+            unsafe { self.gl.GenerateTextureMipmap(self.id) }
+        }
+    }
+
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Width and height of every face
+    pub fn get_size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn descriptor(&self) -> &TextureDescriptor {
+        &self.descriptor
+    }
+
+    /// Number of mipmap levels, down to 1x1 faces for mipmapped cube maps
+    pub fn levels(&self) -> u32 {
+        Self::level_count(self.size, &self.descriptor)
+    }
+}
+
+impl SampledTexture for TextureCube {
+    fn bind_to_unit(&self, unit: u32) {
+        unsafe {
+            self.gl.BindTextureUnit(unit, self.id);
+        }
+    }
+
+    fn target(&self) -> GLenum {
+        gl::TEXTURE_CUBE_MAP
+    }
+}
+
+impl Drop for TextureCube {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use std::path::Path;
+
+    use image::{Rgb, Rgba, RgbaImage};
+    use nalgebra::Vector3;
+
+    use super::*;
+    use crate::renderer::test_support::TempResources;
+    use crate::renderer::{Camera, FrameBuffer, HeadlessContext, Skybox};
+    use crate::resources::HdrImage;
+
+    const FACE_COLORS: [[u8; 3]; 6] = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 0], [0, 255, 255], [255, 0, 255]];
+
+    fn read_face(cube: &TextureCube, face: i32) -> Vec<f32> {
+        let size = cube.get_size() as i32;
+        let mut pixels = vec![0.0f32; (size * size * 4) as usize];
+        unsafe {
+            cube.gl.GetTextureSubImage(cube.id, 0, 0, 0, face, size, size, 1, gl::RGBA, gl::FLOAT,
+                (pixels.len() * std::mem::size_of::<f32>()) as GLsizei, pixels.as_mut_ptr() as *mut GLvoid);
+        }
+        pixels
+    }
+
+    #[test]
+    fn skybox_shows_the_face_the_camera_looks_at() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("cubemap_faces");
+        let names = ["px.png", "nx.png", "py.png", "ny.png", "pz.png", "nz.png"];
+        for (name, [r, g, b]) in names.iter().zip(FACE_COLORS.iter()) {
+            RgbaImage::from_pixel(2, 2, Rgba([*r, *g, *b, 255])).save(temp.path().join(name)).unwrap();
+        }
+        let descriptor = TextureDescriptor::render_target(TextureFormat::Rgba8, FilterMode::Nearest);
+        let cube = TextureCube::from_faces(Rc::clone(&gl), &temp.resources(), &names, &descriptor).unwrap();
+
+        let assets = Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        let skybox = Skybox::new(Rc::clone(&gl), &assets, Rc::new(cube)).unwrap();
+        let frame_buffer = FrameBuffer::new(Rc::clone(&gl), 1, 1);
+        let render = |yaw: f32, depth: f32| {
+            let camera = Camera::new(Vector3::new(5.0, 0.0, 0.0), Vector3::new(yaw, 0.0, 0.0), 1.0, 1.0, 0.1, 100.0, true);
+            frame_buffer.bind();
+            frame_buffer.clear([0.0, 0.0, 0.0, 1.0]);
+            unsafe {
+                gl.Enable(gl::DEPTH_TEST);
+                gl.Viewport(0, 0, 1, 1);
+                gl.ClearDepth(depth as f64);
+                gl.Clear(gl::DEPTH_BUFFER_BIT);
+            }
+            skybox.render(&camera).unwrap();
+            frame_buffer.unbind();
+            frame_buffer.read_rgb_image().get_pixel(0, 0).0
+        };
+
+        assert_eq!(render(0.0, 1.0), FACE_COLORS[5]);
+        assert_eq!(render(-PI / 2.0, 1.0), FACE_COLORS[1]);
+        assert_eq!(render(PI / 2.0, 1.0), FACE_COLORS[0]);
+        assert_eq!(render(PI, 1.0), FACE_COLORS[4]);
+        // Anything already drawn is in front of the sky
+        assert_eq!(render(0.0, 0.5), [0, 0, 0]);
+    }
+
+    #[test]
+    fn converts_equirectangular_images_on_the_gpu() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let assets = Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        // Upper half sky, lower half ground
+        let sky = HdrImage::from_fn(8, 4, |_, y| if y < 2 { Rgb([0.5, 1.0, 4.0]) } else { Rgb([0.25, 0.125, 0.0]) });
+        let source = Texture::from_image_with_descriptor(Rc::clone(&gl), &sky,
+            &TextureDescriptor::render_target(TextureFormat::Rgba32F, FilterMode::Nearest));
+
+        let descriptor = TextureDescriptor::render_target(TextureFormat::Rgba32F, FilterMode::Linear);
+        let cube = TextureCube::from_equirectangular_texture(Rc::clone(&gl), &assets, &source, 4, &descriptor).unwrap();
+        assert_eq!(cube.get_size(), 4);
+        let up = read_face(&cube, 2);
+        assert!(up.chunks(4).all(|pixel| pixel == [0.5, 1.0, 4.0, 1.0]));
+        let down = read_face(&cube, 3);
+        assert!(down.chunks(4).all(|pixel| pixel == [0.25, 0.125, 0.0, 1.0]));
+
+        let depth = TextureDescriptor::render_target(TextureFormat::Depth24, FilterMode::Nearest);
+        assert!(matches!(
+            TextureCube::from_equirectangular_texture(Rc::clone(&gl), &assets, &source, 4, &depth),
+            Err(Error::UnsupportedFormat { format: TextureFormat::Depth24 })));
+    }
+}
@@ -1,6 +1,19 @@
 
 use gl::types::*;
 
+/**
+Describes how the fields of a vertex type map to shader attribute locations
+
+Implement with `#[derive(VertexAttribPointers)]` and a `#[location = n]` attribute per field.
+*/
+pub trait VertexAttribPointers {
+    /**
+    Enables and describes every attribute of the vertex type for the bound vertex array
+    and array buffer
+    */
+    fn vertex_attrib_pointers(gl: &gl::Gl);
+}
+
 #[derive(Copy, Clone, Debug)]
 #[repr(C, packed)]
 pub struct Float32_32_32 {
@@ -14,6 +27,7 @@ impl Float32_32_32 {
         Self{ x, y, z }
     }
 
+    #[allow(clippy::missing_safety_doc)]
     pub unsafe fn vertex_attrib_pointer(gl: &gl::Gl, location: usize, stride: usize, offset: usize) {
         gl.EnableVertexAttribArray(location as gl::types::GLuint);
         gl.VertexAttribPointer(
@@ -47,6 +61,7 @@ impl Float32_32_32_32 {
         Self{ x, y, z, w }
     }
 
+    #[allow(clippy::missing_safety_doc)]
     pub unsafe fn vertex_attrib_pointer(gl: &gl::Gl, location: usize, stride: usize, offset: usize) {
         gl.EnableVertexAttribArray(location as gl::types::GLuint);
         gl.VertexAttribPointer(
@@ -78,6 +93,7 @@ impl Float32_32 {
         Self{ x, y }
     }
 
+    #[allow(clippy::missing_safety_doc)]
     pub unsafe fn vertex_attrib_pointer(gl: &gl::Gl, location: usize, stride: usize, offset: usize) {
         gl.EnableVertexAttribArray(location as gl::types::GLuint);
         gl.VertexAttribPointer(
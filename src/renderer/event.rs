@@ -8,11 +8,13 @@ use glutin::event::{VirtualKeyCode, MouseButton, ElementState};
 */
 
 /// Keeps track of which keys have been pressed.
+#[derive(Clone)]
 pub struct EventState {
     state: HashMap<KeyCode, ElementState>,
 }
 impl EventState {
     /// Constructs a new KeyboardState with all the keys released.
+    #[allow(clippy::new_without_default)]
     pub fn new() -> EventState {
         EventState {
             state: HashMap::new(),
@@ -41,7 +43,7 @@ impl EventState {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum KeyCode {
     MouseLeft,
     MouseRight,
@@ -77,6 +79,7 @@ pub enum KeyCode {
     LShift,
     RControl,
     RShift,
+    F12,
     Other(u8),
     Error,
 }
@@ -126,6 +129,7 @@ impl From<VirtualKeyCode> for KeyCode {
             VirtualKeyCode::LShift      => KeyCode::LShift,  
             VirtualKeyCode::RControl    => KeyCode::RControl,      
             VirtualKeyCode::RShift      => KeyCode::RShift, 
+            VirtualKeyCode::F12         => KeyCode::F12,
             _ => KeyCode::Error
         }
     }
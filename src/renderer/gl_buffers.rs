@@ -1,7 +1,11 @@
+#![allow(non_local_definitions)]
+
+use std::path::Path;
 use std::rc::Rc;
 use gl::types::*;
+use image::{ImageBuffer, Pixel, RgbImage, RgbaImage};
 
-use super::Texture;
+use super::{FilterMode, Texture, TextureDescriptor, TextureFormat};
 
 pub struct VertexBuffer<B>
 where B: BufferType, {
@@ -54,12 +58,57 @@ where B: BufferType, {
         unsafe {
             self.gl.BufferData(
                 B::BUFFER_TYPE,
-                (data.len() * ::std::mem::size_of::<T>()) as GLsizeiptr,
+                ::std::mem::size_of_val(data) as GLsizeiptr,
                 data.as_ptr() as *const GLvoid,
                 gl::STATIC_DRAW,
             );
         }
     }
+
+    /**
+    Submits data that is replaced often, e.g. every frame, to the currently bound VertexBuffer
+
+    see: glBufferData
+    */
+    pub fn buffer_dynamic_draw<T>(&self, data: &[T]) {
+        unsafe {
+            self.gl.BufferData(
+                B::BUFFER_TYPE,
+                ::std::mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+                gl::DYNAMIC_DRAW,
+            );
+        }
+    }
+
+    /**
+    Binds the buffer to binding point `index` of an indexed target,
+    e.g. `layout(std430, binding = index)` for shader storage buffers
+
+    see: glBindBufferBase
+    */
+    pub fn bind_base(&self, index: u32) {
+        unsafe {
+            self.gl.BindBufferBase(B::BUFFER_TYPE, index, self.id);
+        }
+    }
+
+    /**
+    Reads back the first `data.len()` elements of the buffer into `data`
+
+    see: glGetNamedBufferSubData
+    */
+    pub fn read_data<T>(&self, data: &mut [T])
+    where T: Copy, {
+        unsafe {
+            self.gl.GetNamedBufferSubData(
+                self.id,
+                0,
+                ::std::mem::size_of_val(data) as GLsizeiptr,
+                data.as_mut_ptr() as *mut GLvoid,
+            );
+        }
+    }
 }
 
 impl<B> Drop for VertexBuffer<B> 
@@ -85,8 +134,20 @@ impl BufferType for ElementArrayBufferType {
     const BUFFER_TYPE: GLuint = gl::ELEMENT_ARRAY_BUFFER; 
 }
 
+pub struct ShaderStorageBufferType;
+impl BufferType for ShaderStorageBufferType {
+    const BUFFER_TYPE: GLuint = gl::SHADER_STORAGE_BUFFER;
+}
+
+pub struct UniformBufferType;
+impl BufferType for UniformBufferType {
+    const BUFFER_TYPE: GLuint = gl::UNIFORM_BUFFER;
+}
+
 pub type ElementArrayBuffer = VertexBuffer<ElementArrayBufferType>;
 pub type ArrayBuffer = VertexBuffer<ArrayBufferType>;
+pub type ShaderStorageBuffer = VertexBuffer<ShaderStorageBufferType>;
+pub type UniformBuffer = VertexBuffer<UniformBufferType>;
 
 pub struct VertexArray {
     gl: Rc<gl::Gl>,
@@ -127,35 +188,272 @@ impl Drop for VertexArray {
     }
 }
 
-pub struct FrameBuffer {
+/// Storage format of a depth or depth-stencil attachment
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DepthFormat {
+    Depth16,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+    Depth32FStencil8,
+}
+
+impl From<DepthFormat> for TextureFormat {
+    fn from(format: DepthFormat) -> Self {
+        match format {
+            DepthFormat::Depth16 => TextureFormat::Depth16,
+            DepthFormat::Depth24 => TextureFormat::Depth24,
+            DepthFormat::Depth32F => TextureFormat::Depth32F,
+            DepthFormat::Depth24Stencil8 => TextureFormat::Depth24Stencil8,
+            DepthFormat::Depth32FStencil8 => TextureFormat::Depth32FStencil8,
+        }
+    }
+}
+
+impl DepthFormat {
+    pub fn has_stencil(self) -> bool {
+        matches!(self, DepthFormat::Depth24Stencil8 | DepthFormat::Depth32FStencil8)
+    }
+
+    fn attachment_point(self) -> GLenum {
+        if self.has_stencil() { gl::DEPTH_STENCIL_ATTACHMENT } else { gl::DEPTH_ATTACHMENT }
+    }
+}
+
+/**
+Internal formats of frame buffer color attachments
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorFormat {
+    R8,
+    Rg8,
+    Rgb8,
+    Rgba8,
+    R16F,
+    Rg16F,
+    Rgba16F,
+    R32F,
+    Rg32F,
+    Rgba32F,
+    R11fG11fB10f,
+}
+
+impl From<ColorFormat> for TextureFormat {
+    fn from(format: ColorFormat) -> Self {
+        match format {
+            ColorFormat::R8 => TextureFormat::R8,
+            ColorFormat::Rg8 => TextureFormat::Rg8,
+            ColorFormat::Rgb8 => TextureFormat::Rgb8,
+            ColorFormat::Rgba8 => TextureFormat::Rgba8,
+            ColorFormat::R16F => TextureFormat::R16F,
+            ColorFormat::Rg16F => TextureFormat::Rg16F,
+            ColorFormat::Rgba16F => TextureFormat::Rgba16F,
+            ColorFormat::R32F => TextureFormat::R32F,
+            ColorFormat::Rg32F => TextureFormat::Rg32F,
+            ColorFormat::Rgba32F => TextureFormat::Rgba32F,
+            ColorFormat::R11fG11fB10f => TextureFormat::R11fG11fB10f,
+        }
+    }
+}
+
+/**
+Render target storage that can not be sampled, e.g. for depth buffers only used for depth testing
+*/
+pub struct RenderBuffer {
     gl: Rc<gl::Gl>,
     id: GLuint,
-    texture: Texture,
+    internal_format: GLenum,
 }
 
-impl FrameBuffer {
-    pub fn new(gl: Rc<gl::Gl>, width: u32, height: u32) -> Self {
+impl RenderBuffer {
+    pub fn new(gl: Rc<gl::Gl>, width: u32, height: u32, internal_format: GLenum) -> Self {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl.CreateRenderbuffers(1, &mut id);
+        }
+
+        let render_buffer = RenderBuffer { gl, id, internal_format };
+        render_buffer.resize(width, height);
+        render_buffer
+    }
+
+    /**
+    Reallocates the storage, discarding the contents
+
+    see: glNamedRenderbufferStorage
+    */
+    pub fn resize(&self, width: u32, height: u32) {
+        unsafe {
+            self.gl.NamedRenderbufferStorage(self.id, self.internal_format, width as GLsizei, height as GLsizei);
+        }
+    }
+
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for RenderBuffer {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteRenderbuffers(1, &self.id) }
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum FrameBufferError {
+    #[fail(display = "Frame buffer is not complete, status 0x{:x}", status)]
+    Incomplete { status: GLenum },
+    #[fail(display = "{} color attachments requested, at most {} are supported", count, max)]
+    TooManyColorAttachments { count: usize, max: usize },
+}
+
+enum DepthAttachment {
+    RenderBuffer(RenderBuffer),
+    Texture(Texture),
+}
+
+/**
+Configures the attachments of a `FrameBuffer`, see `FrameBuffer::builder`
+*/
+pub struct FrameBufferBuilder {
+    gl: Rc<gl::Gl>,
+    width: u32,
+    height: u32,
+    color: Vec<ColorFormat>,
+    /// Format and whether the depth attachment is a sampleable texture
+    depth: Option<(DepthFormat, bool)>,
+}
+
+impl FrameBufferBuilder {
+    /// Leaves out the RGB8 color texture, e.g. for depth only passes
+    pub fn without_color(mut self) -> Self {
+        self.color.clear();
+        self
+    }
+
+    /**
+    Replaces the RGB8 color texture by one texture per format, attached to `COLOR_ATTACHMENT0`
+    onwards in order, e.g. position, normal and albedo targets of a G-buffer
+
+    All attachments are draw buffers, fragment shader output `location = i` writes to `formats[i]`.
+    */
+    pub fn color_attachments(mut self, formats: &[ColorFormat]) -> Self {
+        self.color = formats.to_vec();
+        self
+    }
+
+    /// Adds a depth (or depth-stencil) attachment only used for depth testing
+    pub fn depth_renderbuffer(mut self, format: DepthFormat) -> Self {
+        self.depth = Some((format, false));
+        self
+    }
+
+    /// Adds a depth (or depth-stencil) attachment that can be sampled after rendering
+    pub fn depth_texture(mut self, format: DepthFormat) -> Self {
+        self.depth = Some((format, true));
+        self
+    }
+
+    pub fn build(self) -> Result<FrameBuffer, FrameBufferError> {
+        let FrameBufferBuilder { gl, width, height, color, depth } = self;
         let mut id: GLuint = 0;
         unsafe {
-            gl.GenFramebuffers(1, &mut id);
-            gl.BindFramebuffer(gl::FRAMEBUFFER, id);
+            gl.CreateFramebuffers(1, &mut id);
+        }
+
+        let mut max_attachments: GLint = 0;
+        unsafe {
+            gl.GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, &mut max_attachments);
+        }
+        if color.len() > max_attachments as usize {
+            unsafe { gl.DeleteFramebuffers(1, &id) }
+            return Err(FrameBufferError::TooManyColorAttachments { count: color.len(), max: max_attachments as usize });
         }
 
-        let texture: Texture = Texture::empty(Rc::clone(&gl), width, height);
+        let textures: Vec<Texture> = color
+            .iter()
+            .enumerate()
+            .map(|(i, &format)| {
+                let descriptor = TextureDescriptor::render_target(format.into(), FilterMode::Linear);
+                let texture = Texture::new(Rc::clone(&gl), width, height, &descriptor);
+                unsafe { gl.NamedFramebufferTexture(id, gl::COLOR_ATTACHMENT0 + i as GLenum, texture.get_id(), 0) }
+                texture
+            })
+            .collect();
 
+        let draw_buffers: Vec<GLenum> = (0..textures.len() as GLenum).map(|i| gl::COLOR_ATTACHMENT0 + i).collect();
         unsafe {
-            gl.FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, texture.get_id(), 0);
-            if gl.CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
-                println!("ERROR::FRAMEBUFFER:: Framebuffer is not complete!");
+            if draw_buffers.is_empty() {
+                gl.NamedFramebufferDrawBuffer(id, gl::NONE);
+                gl.NamedFramebufferReadBuffer(id, gl::NONE);
+            } else {
+                gl.NamedFramebufferDrawBuffers(id, draw_buffers.len() as GLsizei, draw_buffers.as_ptr());
             }
-            gl.BindFramebuffer(gl::FRAMEBUFFER, 0); 
         }
 
-        FrameBuffer {
-            gl, 
+        let depth = depth.map(|(format, sampled)| {
+            if sampled {
+                let descriptor = TextureDescriptor::render_target(format.into(), FilterMode::Nearest);
+                let texture = Texture::new(Rc::clone(&gl), width, height, &descriptor);
+                unsafe {
+                    gl.NamedFramebufferTexture(id, format.attachment_point(), texture.get_id(), 0);
+                }
+                (format, DepthAttachment::Texture(texture))
+            } else {
+                let (internal_format, _, _) = TextureFormat::from(format).gl_formats();
+                let render_buffer = RenderBuffer::new(Rc::clone(&gl), width, height, internal_format);
+                unsafe {
+                    gl.NamedFramebufferRenderbuffer(id, format.attachment_point(), gl::RENDERBUFFER, render_buffer.get_id());
+                }
+                (format, DepthAttachment::RenderBuffer(render_buffer))
+            }
+        });
+
+        let frame_buffer = FrameBuffer {
+            gl,
             id,
-            texture,
+            width,
+            height,
+            textures,
+            color,
+            depth,
+        };
+
+        let status = unsafe { frame_buffer.gl.CheckNamedFramebufferStatus(id, gl::FRAMEBUFFER) };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(FrameBufferError::Incomplete { status });
         }
+
+        Ok(frame_buffer)
+    }
+}
+
+pub struct FrameBuffer {
+    gl: Rc<gl::Gl>,
+    id: GLuint,
+    width: u32,
+    height: u32,
+    textures: Vec<Texture>,
+    color: Vec<ColorFormat>,
+    depth: Option<(DepthFormat, DepthAttachment)>,
+}
+
+impl FrameBuffer {
+    /**
+    Creates a frame buffer with an RGB8 color texture and a 24 bit depth renderbuffer
+    */
+    pub fn new(gl: Rc<gl::Gl>, width: u32, height: u32) -> Self {
+        Self::builder(gl, width, height)
+            .depth_renderbuffer(DepthFormat::Depth24)
+            .build()
+            .expect("Expected RGB8 color with 24 bit depth to be a complete frame buffer")
+    }
+
+    /**
+    Starts configuring a frame buffer with an RGB8 color texture and no depth attachment
+    */
+    pub fn builder(gl: Rc<gl::Gl>, width: u32, height: u32) -> FrameBufferBuilder {
+        FrameBufferBuilder { gl, width, height, color: vec![ColorFormat::Rgb8], depth: None }
     }
 
     pub fn bind(&self) {
@@ -170,17 +468,310 @@ impl FrameBuffer {
         }
     }
 
-    pub fn resize(&self, width: u32, height: u32) {
-        self.texture.resize(width, height);
+    /**
+    Reallocates all attachments at the new size, discarding their contents
+
+    Resizing to a width or height of 0, e.g. for a minimized window, is ignored since such
+    a frame buffer could not be complete.
+    */
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        self.width = width;
+        self.height = height;
+        for texture in &mut self.textures {
+            texture.resize(width, height);
+        }
+        match &mut self.depth {
+            Some((_, DepthAttachment::RenderBuffer(render_buffer))) => render_buffer.resize(width, height),
+            Some((_, DepthAttachment::Texture(texture))) => texture.resize(width, height),
+            None => (),
+        }
     }
 
+    /// Binds the first color texture, panics if the frame buffer was built without one
     pub fn bind_texture(&self) {
-        self.texture.bind();
+        self.get_texture()
+            .expect("Expected frame buffer to have a color texture")
+            .bind();
+    }
+
+    /// The texture at `COLOR_ATTACHMENT0`
+    pub fn get_texture(&self) -> Option<&Texture> {
+        self.textures.first()
+    }
+
+    /// The texture at `COLOR_ATTACHMENT0 + index`
+    pub fn get_color_texture(&self, index: usize) -> Option<&Texture> {
+        self.textures.get(index)
+    }
+
+    pub fn color_textures(&self) -> &[Texture] {
+        &self.textures
+    }
+
+    pub fn color_formats(&self) -> &[ColorFormat] {
+        &self.color
+    }
+
+    /**
+    Restricts rendering to the given color attachments, fragment shader output `location = i`
+    writes to `COLOR_ATTACHMENT0 + attachments[i]`. Panics on attachments that do not exist.
+
+    see: glDrawBuffers
+    */
+    pub fn set_draw_buffers(&self, attachments: &[usize]) {
+        let draw_buffers: Vec<GLenum> = attachments
+            .iter()
+            .map(|&index| {
+                assert!(index < self.textures.len(), "Expected color attachment {} to exist", index);
+                gl::COLOR_ATTACHMENT0 + index as GLenum
+            })
+            .collect();
+        unsafe {
+            self.gl.NamedFramebufferDrawBuffers(self.id, draw_buffers.len() as GLsizei, draw_buffers.as_ptr());
+        }
+    }
+
+    /// The depth attachment if it was built with `depth_texture`
+    pub fn get_depth_texture(&self) -> Option<&Texture> {
+        match &self.depth {
+            Some((_, DepthAttachment::Texture(texture))) => Some(texture),
+            _ => None,
+        }
+    }
+
+    /**
+    Clears the color attachments to `color` and the depth (and stencil) attachment to 1 (and 0)
+    */
+    pub fn clear(&self, color: [f32; 4]) {
+        for index in 0..self.textures.len() {
+            self.clear_color_attachment(index, color);
+        }
+        unsafe {
+            match self.depth {
+                Some((format, _)) if format.has_stencil() => self.gl.ClearNamedFramebufferfi(self.id, gl::DEPTH_STENCIL, 0, 1.0, 0),
+                Some(_) => self.gl.ClearNamedFramebufferfv(self.id, gl::DEPTH, 0, &1.0),
+                None => (),
+            }
+        }
+    }
+
+    /**
+    Clears the color texture at `COLOR_ATTACHMENT0 + index` to `color`
+
+    see: glClearTexImage
+    */
+    pub fn clear_color_attachment(&self, index: usize, color: [f32; 4]) {
+        let texture = self.get_color_texture(index).expect("Expected color attachment to exist");
+        unsafe {
+            self.gl.ClearTexImage(texture.get_id(), 0, gl::RGBA, gl::FLOAT, color.as_ptr() as *const GLvoid);
+        }
+    }
+
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /**
+    Reads back the first color attachment as an RGB image with the origin in the top left corner
+
+    see: glReadPixels
+    */
+    pub fn read_rgb_image(&self) -> RgbImage {
+        self.read_pixels(gl::RGB)
+    }
+
+    /**
+    Reads back the first color attachment as an RGBA image with the origin in the top left corner
+
+    see: glReadPixels
+    */
+    pub fn read_rgba_image(&self) -> RgbaImage {
+        self.read_pixels(gl::RGBA)
+    }
+
+    /**
+    Saves the first color attachment as a PNG image at `path`
+    */
+    pub fn save_png(&self, path: &Path) -> Result<(), image::ImageError> {
+        self.read_rgba_image().save_with_format(path, image::ImageFormat::Png)
+    }
+
+    /**
+    Copies the first color attachment into the default frame buffer, scaled to `width` x `height`
+
+    see: glBlitNamedFramebuffer
+    */
+    pub fn blit_to_screen(&self, width: u32, height: u32) {
+        unsafe {
+            self.gl.BlitNamedFramebuffer(self.id, 0, 0, 0, self.width as GLint, self.height as GLint,
+                0, 0, width as GLint, height as GLint, gl::COLOR_BUFFER_BIT, gl::LINEAR);
+        }
+    }
+
+    fn read_pixels<P>(&self, format: GLenum) -> ImageBuffer<P, Vec<u8>>
+    where P: Pixel<Subpixel = u8> + 'static, {
+        let row_size = self.width as usize * P::CHANNEL_COUNT as usize;
+        let mut pixels: Vec<u8> = vec![0; row_size * self.height as usize];
+        self.bind();
+        unsafe {
+            self.gl.ReadBuffer(gl::COLOR_ATTACHMENT0);
+            self.gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            self.gl.ReadPixels(
+                0, 0,
+                self.width as i32,
+                self.height as i32,
+                format,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut GLvoid,
+            );
+        }
+        self.unbind();
+
+        // OpenGL stores rows bottom up, images are stored top down
+        let flipped: Vec<u8> = pixels
+            .chunks_exact(row_size.max(1))
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        ImageBuffer::from_raw(self.width, self.height, flipped)
+            .expect("Expected pixel buffer to match frame buffer size")
     }
 }
 
 impl Drop for FrameBuffer {
     fn drop(&mut self) {
-        unsafe { self.gl.DeleteFramebuffers(1, &mut self.id) }
+        unsafe { self.gl.DeleteFramebuffers(1, &self.id) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::test_support::{TempResources, FULLSCREEN_VERTEX_SOURCE};
+    use crate::renderer::HeadlessContext;
+
+    fn texture_size(gl: &gl::Gl, texture: &Texture) -> (i32, i32) {
+        let (mut width, mut height) = (0, 0);
+        unsafe {
+            gl.GetTextureLevelParameteriv(texture.get_id(), 0, gl::TEXTURE_WIDTH, &mut width);
+            gl.GetTextureLevelParameteriv(texture.get_id(), 0, gl::TEXTURE_HEIGHT, &mut height);
+        }
+        (width, height)
+    }
+
+    #[test]
+    fn resize_reallocates_all_attachments() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let mut frame_buffer = FrameBuffer::builder(Rc::clone(&gl), 8, 4)
+            .depth_texture(DepthFormat::Depth24Stencil8)
+            .build()
+            .unwrap();
+
+        frame_buffer.resize(16, 12);
+        frame_buffer.resize(0, 12);
+        assert_eq!(frame_buffer.get_size(), (16, 12));
+        assert_eq!(frame_buffer.get_texture().unwrap().get_size(), (16, 12));
+        assert_eq!(texture_size(&gl, frame_buffer.get_texture().unwrap()), (16, 12));
+        assert_eq!(texture_size(&gl, frame_buffer.get_depth_texture().unwrap()), (16, 12));
+        let status = unsafe { gl.CheckNamedFramebufferStatus(frame_buffer.id, gl::FRAMEBUFFER) };
+        assert_eq!(status, gl::FRAMEBUFFER_COMPLETE);
+    }
+
+    #[test]
+    fn depth_only_frame_buffer_is_complete() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let frame_buffer = FrameBuffer::builder(Rc::clone(&gl), 4, 4)
+            .without_color()
+            .depth_texture(DepthFormat::Depth32F)
+            .build()
+            .unwrap();
+        frame_buffer.clear([0.0; 4]);
+
+        let mut depth = [0.0f32; 16];
+        unsafe {
+            gl.GetTextureImage(
+                frame_buffer.get_depth_texture().unwrap().get_id(), 0, gl::DEPTH_COMPONENT, gl::FLOAT,
+                std::mem::size_of_val(&depth) as GLsizei, depth.as_mut_ptr() as *mut GLvoid);
+        }
+        assert_eq!(depth, [1.0; 16]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn writes_fragment_outputs_to_color_attachments() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("gbuffer");
+        temp.write("gbuffer.vert", FULLSCREEN_VERTEX_SOURCE);
+        temp.write("gbuffer.frag", "#version 330 core\n\
+            layout(location = 0) out vec4 albedo;\nlayout(location = 1) out float depth;\n\
+            void main() { albedo = vec4(2.5, -1.0, 0.25, 1.0); depth = 42.5; }\n");
+        let program = crate::renderer::ShaderProgram::from_resource(Rc::clone(&gl), &temp.resources(), "gbuffer").unwrap();
+
+        let frame_buffer = FrameBuffer::builder(Rc::clone(&gl), 2, 2)
+            .color_attachments(&[ColorFormat::Rgba16F, ColorFormat::R32F, ColorFormat::Rgba8])
+            .depth_renderbuffer(DepthFormat::Depth24)
+            .build()
+            .unwrap();
+        assert_eq!(frame_buffer.color_textures().len(), 3);
+        frame_buffer.clear([0.0; 4]);
+        frame_buffer.clear_color_attachment(2, [0.0, 1.0, 0.0, 1.0]);
+        // The shader has no output for the third attachment, which would be undefined
+        frame_buffer.set_draw_buffers(&[0, 1]);
+
+        let mut vertex_array: GLuint = 0;
+        unsafe {
+            gl.GenVertexArrays(1, &mut vertex_array);
+            gl.BindVertexArray(vertex_array);
+            gl.Viewport(0, 0, 2, 2);
+        }
+        frame_buffer.bind();
+        program.bind();
+        unsafe {
+            gl.DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        frame_buffer.unbind();
+
+        let read_floats = |index: usize, format: GLenum, values: &mut [f32]| unsafe {
+            gl.GetTextureImage(
+                frame_buffer.get_color_texture(index).unwrap().get_id(), 0, format, gl::FLOAT,
+                std::mem::size_of_val(values) as GLsizei, values.as_mut_ptr() as *mut GLvoid);
+        };
+        let mut albedo = [0.0f32; 16];
+        read_floats(0, gl::RGBA, &mut albedo);
+        assert_eq!(albedo[..4], [2.5, -1.0, 0.25, 1.0]);
+        let mut depth = [0.0f32; 4];
+        read_floats(1, gl::RED, &mut depth);
+        assert_eq!(depth, [42.5; 4]);
+        // Not a draw buffer, keeps its clear color
+        let mut unused = [0.0f32; 16];
+        read_floats(2, gl::RGBA, &mut unused);
+        assert_eq!(unused[..4], [0.0, 1.0, 0.0, 1.0]);
+
+        frame_buffer.bind();
+        let mut draw_buffers: [GLint; 3] = [0; 3];
+        unsafe {
+            for (i, draw_buffer) in draw_buffers.iter_mut().enumerate() {
+                gl.GetIntegerv(gl::DRAW_BUFFER0 + i as GLenum, draw_buffer);
+            }
+            gl.DeleteVertexArrays(1, &vertex_array);
+        }
+        frame_buffer.unbind();
+        assert_eq!(draw_buffers, [gl::COLOR_ATTACHMENT0 as GLint, gl::COLOR_ATTACHMENT1 as GLint, gl::NONE as GLint]);
+    }
+
+    #[test]
+    fn rejects_more_color_attachments_than_supported() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let result = FrameBuffer::builder(context.get_gl_handle(), 1, 1)
+            .color_attachments(&[ColorFormat::R8; 64])
+            .build();
+        assert!(matches!(result, Err(FrameBufferError::TooManyColorAttachments { count: 64, .. })));
+    }
+}
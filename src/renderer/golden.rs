@@ -0,0 +1,345 @@
+/*
+* Golden image regression tests
+*
+* Each test renders a small scene offscreen through a `HeadlessContext`, reads back the
+* `FrameBuffer` and compares it to a reference image in tests/golden/<name>.png.
+* On mismatch the rendered image and a diff image are written to target/golden/.
+*
+* Run with UPDATE_GOLDEN=1 to (re)generate the reference images.
+*/
+
+use std::env;
+use std::f32::consts::FRAC_PI_4;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use image::{Rgba, RgbaImage};
+use nalgebra::{Matrix4, Point3, Unit, Vector3};
+
+use super::*;
+use super::data::VertexAttribPointers;
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 128;
+
+/// Default allowed absolute difference per color channel
+const TOLERANCE: u8 = 2;
+
+/// A scene to render for a golden image test
+struct GoldenScene<'a, V, I> {
+    /// Shader program name relative to the assets directory, e.g. "shaders/test"
+    shader: &'a str,
+    /// Texture bound as `Texture` while drawing, relative to the assets directory
+    texture: Option<&'a str>,
+    vertices: &'a [V],
+    indices: &'a [I],
+    camera: Camera,
+    /// Uploaded as the `Lights` block, unless empty
+    lights: &'a [Light],
+}
+
+fn assets_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")
+}
+
+fn render<V, I>(scene: &GoldenScene<V, I>) -> RgbaImage
+where V: VertexAttribPointers, I: IndexType, {
+    render_with(scene, |_, _, _| ())
+}
+
+/**
+Like `render`, calling `prepare` to set up further state of the bound program right before
+drawing, whatever it returns is kept alive until the frame is finished
+*/
+fn render_with<V, I, F, R>(scene: &GoldenScene<V, I>, prepare: F) -> RgbaImage
+where V: VertexAttribPointers, I: IndexType, F: FnOnce(&Rc<gl::Gl>, &Resources, &ShaderProgram) -> R, {
+    let context = HeadlessContext::from_size(WIDTH, HEIGHT)
+        .unwrap_or_else(|e| panic!("Failed to create headless context: {}", e));
+    let gl = context.get_gl_handle();
+    let resource = Resources::from_path(&assets_path());
+
+    unsafe {
+        gl.Enable(gl::BLEND);
+        gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl.Enable(gl::DEPTH_TEST);
+    }
+
+    let program = ShaderProgram::from_resource(Rc::clone(&gl), &resource, scene.shader)
+        .unwrap_or_else(|e| panic!("Failed to load shader {}: {}", scene.shader, e));
+    let texture = scene.texture.map(|name| {
+        Texture::from_resource(Rc::clone(&gl), &resource, name)
+            .unwrap_or_else(|e| panic!("Failed to load texture {}: {}", name, e))
+    });
+
+    let mesh = Mesh::new(Rc::clone(&gl), scene.vertices, scene.indices, Topology::Triangles);
+    let frame_buffer = FrameBuffer::new(Rc::clone(&gl), WIDTH, HEIGHT);
+
+    if let Some(texture) = &texture {
+        program.bind_texture("Texture", texture)
+            .unwrap_or_else(|e| panic!("Failed to bind the texture of {}: {}", scene.shader, e));
+    }
+    program.bind();
+    if let Some(location) = program.get_uniform_location("View") {
+        program.bind_uniform_mat4(location, &scene.camera.get_view_matrix());
+    }
+    if let Some(location) = program.get_uniform_location("Perspective") {
+        program.bind_uniform_mat4(location, &scene.camera.get_projection_matrix());
+    }
+    if let Some(location) = program.get_uniform_location("Model") {
+        program.bind_uniform_mat4(location, &Matrix4::identity());
+    }
+    program.set_uniform("CameraPosition", &scene.camera.get_position()).ok();
+    let light_buffer = LightBuffer::new(Rc::clone(&gl), Vector3::new(0.1, 0.1, 0.1));
+    if !scene.lights.is_empty() {
+        light_buffer.upload(scene.lights);
+        light_buffer.bind_base(LIGHTS_BINDING);
+        program.bind_uniform_block("Lights", LIGHTS_BINDING)
+            .unwrap_or_else(|e| panic!("Failed to bind lights of {}: {}", scene.shader, e));
+    }
+    let _prepared = prepare(&gl, &resource, &program);
+    // `prepare` may have used other programs
+    program.bind();
+    frame_buffer.bind();
+    frame_buffer.clear([0.8, 0.8, 0.8, 1.0]);
+    mesh.draw();
+    frame_buffer.unbind();
+    context.finish();
+
+    frame_buffer.read_rgba_image()
+}
+
+/**
+Compares `actual` to `expected`, returning the number of pixels where any channel differs
+by more than `tolerance` together with an image highlighting those pixels in red
+*/
+fn compare(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> (usize, RgbaImage) {
+    let mut mismatches = 0;
+    let diff = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let e = expected.get_pixel(x, y);
+        let differs = x >= actual.width() || y >= actual.height() || {
+            let a = actual.get_pixel(x, y);
+            a.0.iter().zip(e.0.iter()).any(|(a, e)| (*a as i16 - *e as i16).abs() > tolerance as i16)
+        };
+
+        if differs {
+            mismatches += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            // Faded grayscale of the expected image for context
+            let luma = (e[0] as u32 + e[1] as u32 + e[2] as u32) / 3;
+            let faded = (luma / 4 + 160) as u8;
+            Rgba([faded, faded, faded, 255])
+        }
+    });
+
+    if actual.dimensions() != expected.dimensions() {
+        mismatches = mismatches.max(1);
+    }
+
+    (mismatches, diff)
+}
+
+fn assert_matches_golden(name: &str, actual: &RgbaImage, tolerance: u8) {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let reference_path = manifest_dir.join("tests").join("golden").join(format!("{}.png", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        actual.save(&reference_path).unwrap();
+        return;
+    }
+
+    let expected = image::open(&reference_path)
+        .unwrap_or_else(|e| panic!(
+            "Failed to open reference image {}: {}. Run with UPDATE_GOLDEN=1 to create it",
+            reference_path.display(), e))
+        .into_rgba8();
+
+    let (mismatches, diff) = compare(actual, &expected, tolerance);
+    if mismatches > 0 {
+        let output_dir = manifest_dir.join("target").join("golden");
+        fs::create_dir_all(&output_dir).unwrap();
+        let actual_path = output_dir.join(format!("{}.actual.png", name));
+        let diff_path = output_dir.join(format!("{}.diff.png", name));
+        actual.save(&actual_path).unwrap();
+        diff.save(&diff_path).unwrap();
+        panic!(
+            "{} pixels differ from {} by more than {} (actual: {}, diff: {})",
+            mismatches, reference_path.display(), tolerance, actual_path.display(), diff_path.display());
+    }
+}
+
+fn textured_quad() -> [PosColorUV; 4] {
+    [
+        PosColorUV { position: (-0.5, -0.5, 0.0).into(), color: (1.0, 0.0, 0.0, 1.0).into(), uv: (0.0, 0.0).into()},
+        PosColorUV { position: (0.5, -0.5, 0.0).into(), color: (1.0, 1.0, 0.0, 1.0).into(), uv: (1.0, 0.0).into()},
+        PosColorUV { position: (-0.5, 0.5, 0.0).into(), color: (0.0, 1.0, 0.0, 1.0).into(), uv: (0.0, 1.0).into()},
+        PosColorUV { position: (0.5, 0.5, 0.0).into(), color: (0.0, 1.0, 1.0, 1.0).into(), uv: (1.0, 1.0).into()},
+    ]
+}
+
+const QUAD_INDICES: [u8; 6] = [1, 0, 2, 2, 3, 1];
+
+#[test]
+fn compare_accepts_differences_within_tolerance() {
+    let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+    let actual = RgbaImage::from_pixel(4, 4, Rgba([102, 98, 100, 255]));
+    let (mismatches, _) = compare(&actual, &expected, 2);
+    assert_eq!(mismatches, 0);
+}
+
+#[test]
+fn compare_reports_differences_above_tolerance() {
+    let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(1, 2, Rgba([100, 103, 100, 255]));
+    let (mismatches, diff) = compare(&actual, &expected, 2);
+    assert_eq!(mismatches, 1);
+    assert_eq!(*diff.get_pixel(1, 2), Rgba([255, 0, 0, 255]));
+}
+
+#[test]
+fn compare_reports_size_mismatch() {
+    let expected = RgbaImage::new(4, 4);
+    let actual = RgbaImage::new(4, 2);
+    let (mismatches, _) = compare(&actual, &expected, 0);
+    assert!(mismatches > 0);
+}
+
+#[test]
+fn golden_textured_quad() {
+    let vertices = textured_quad();
+    let scene = GoldenScene {
+        shader: "shaders/test",
+        texture: Some("smiley.png"),
+        vertices: &vertices,
+        indices: &QUAD_INDICES,
+        camera: Camera::new(*Vector3::z_axis() * 2., Vector3::zeros(), 1.0, FRAC_PI_4, 0.01, 1000.0, true),
+        lights: &[],
+    };
+    assert_matches_golden("textured_quad", &render(&scene), TOLERANCE);
+}
+
+#[test]
+fn golden_textured_quad_rotated_camera() {
+    let vertices = textured_quad();
+    let scene = GoldenScene {
+        shader: "shaders/test",
+        texture: Some("smiley.png"),
+        vertices: &vertices,
+        indices: &QUAD_INDICES,
+        camera: Camera::new(Vector3::new(0.5, 0.0, 2.0), Vector3::new(-0.3, 0.2, 0.0), 1.0, FRAC_PI_4, 0.01, 1000.0, true),
+        lights: &[],
+    };
+    assert_matches_golden("textured_quad_rotated_camera", &render(&scene), TOLERANCE);
+}
+
+#[test]
+fn golden_obj_cube() {
+    let mesh = Resources::from_path(&assets_path()).load_obj("models/cube.obj").unwrap();
+    let scene = GoldenScene {
+        shader: "shaders/test",
+        texture: Some("smiley.png"),
+        vertices: &mesh.vertices,
+        indices: &mesh.indices,
+        camera: Camera::new(Vector3::new(1.5, 1.5, 2.5), Vector3::new(-0.54, 0.475, 0.0), 1.0, FRAC_PI_4, 0.01, 1000.0, true),
+        lights: &[],
+    };
+    assert_matches_golden("obj_cube", &render(&scene), TOLERANCE);
+}
+
+#[test]
+fn golden_lit_cube() {
+    let mesh = Resources::from_path(&assets_path()).load_obj("models/cube.obj").unwrap();
+    let lights = [
+        Light::Directional(DirectionalLight {
+            direction: Unit::new_normalize(Vector3::new(-1.0, -2.0, -0.5)),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            intensity: 0.6,
+        }),
+        Light::Point(PointLight {
+            position: Point3::new(2.0, 0.5, 2.0),
+            color: Vector3::new(1.0, 0.3, 0.2),
+            intensity: 6.0,
+            range: 6.0,
+        }),
+        Light::Spot(SpotLight {
+            position: Point3::new(0.0, 3.0, 0.0),
+            direction: -Vector3::y_axis(),
+            color: Vector3::new(0.2, 0.4, 1.0),
+            intensity: 8.0,
+            range: 6.0,
+            inner_angle: 0.2,
+            outer_angle: 0.4,
+        }),
+    ];
+    let scene = GoldenScene {
+        shader: "shaders/lit",
+        texture: Some("smiley.png"),
+        vertices: &mesh.vertices,
+        indices: &mesh.indices,
+        camera: Camera::new(Vector3::new(1.5, 1.5, 2.5), Vector3::new(-0.54, 0.475, 0.0), 1.0, FRAC_PI_4, 0.01, 1000.0, true),
+        lights: &lights,
+    };
+    assert_matches_golden("lit_cube", &render(&scene), TOLERANCE);
+}
+
+#[test]
+fn golden_pbr_cube() {
+    let mesh = Resources::from_path(&assets_path()).load_obj("models/cube.obj").unwrap();
+    let lights = [Light::Directional(DirectionalLight {
+        direction: Unit::new_normalize(Vector3::new(-1.0, -2.0, -0.5)),
+        color: Vector3::new(1.0, 0.95, 0.85),
+        intensity: 2.0,
+    })];
+    let scene = GoldenScene {
+        shader: "shaders/pbr",
+        texture: None,
+        vertices: &mesh.vertices,
+        indices: &mesh.indices,
+        camera: Camera::new(Vector3::new(1.5, 1.5, 2.5), Vector3::new(-0.54, 0.475, 0.0), 1.0, FRAC_PI_4, 0.01, 1000.0, true),
+        lights: &lights,
+    };
+    let image = render_with(&scene, |gl, resource, program| {
+        // Precomputing the lighting uses texture units too, so it comes before binding anything
+        let sky_descriptor = TextureDescriptor::render_target(TextureFormat::Rgba16F, FilterMode::Linear);
+        let sky = TextureCube::from_equirectangular(Rc::clone(gl), resource, "textures/sky.hdr", 64, &sky_descriptor).unwrap();
+        let ibl = ImageBasedLighting::with_sizes(Rc::clone(gl), resource, &sky, 16, 32).unwrap();
+        ibl.bind(program).unwrap();
+
+        let base_color = Texture::from_resource_with_descriptor(Rc::clone(gl), resource, "smiley.png",
+            &TextureDescriptor { format: TextureFormat::Srgb8Alpha8, ..Default::default() }).unwrap();
+        let material = PbrMaterial { base_color_map: Some(Rc::new(base_color)), roughness: 0.4, ..Default::default() };
+        material.bind(program).unwrap();
+        (material, ibl)
+    });
+    assert_matches_golden("pbr_cube", &image, TOLERANCE);
+}
+
+#[test]
+fn golden_depth_tested_quads() {
+    // Both quads sample a single texel: the near one the smiley's center, the far one its corner
+    let quad = |offset: f32, z: f32, uv: f32| {
+        [(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (0.5, 0.5)].map(|(x, y)| PosColorUV {
+            position: (x + offset, y + offset, z).into(),
+            color: (1.0, 1.0, 1.0, 1.0).into(),
+            uv: (uv, uv).into(),
+        })
+    };
+    let near = quad(-0.2, 0.0, 0.5);
+    let far = quad(0.2, -0.5, 0.02);
+    let vertices: Vec<PosColorUV> = near.iter().chain(far.iter()).copied().collect();
+    // The far quad is drawn last and must only be visible outside of the near one
+    let indices: [u8; 12] = [1, 0, 2, 2, 3, 1, 5, 4, 6, 6, 7, 5];
+
+    let scene = GoldenScene {
+        shader: "shaders/test",
+        texture: Some("smiley.png"),
+        vertices: &vertices,
+        indices: &indices,
+        camera: Camera::new(*Vector3::z_axis() * 2., Vector3::zeros(), 1.0, FRAC_PI_4, 0.01, 1000.0, true),
+        lights: &[],
+    };
+    assert_matches_golden("depth_tested_quads", &render(&scene), TOLERANCE);
+}
@@ -0,0 +1,119 @@
+#![allow(non_local_definitions)]
+
+use std::{io, ptr, rc::Rc};
+
+use egl::types::*;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Failed to load the EGL library")]
+    LibraryLoad(#[cause] io::Error),
+    #[fail(display = "Failed to get a surfaceless EGL display")]
+    NoDisplay,
+    #[fail(display = "Failed to initialize EGL display, error 0x{:x}", code)]
+    Initialize { code: EGLint },
+    #[fail(display = "Failed to bind the OpenGL API, error 0x{:x}", code)]
+    BindApi { code: EGLint },
+    #[fail(display = "Failed to create OpenGL context, error 0x{:x}", code)]
+    CreateContext { code: EGLint },
+    #[fail(display = "Failed to make OpenGL context current, error 0x{:x}", code)]
+    MakeCurrent { code: EGLint },
+}
+
+/**
+An OpenGL context without any window or default framebuffer
+
+Uses EGL on the Mesa surfaceless platform, so it works without a display server
+and without a GPU (e.g. llvmpipe). All rendering has to go to a `FrameBuffer`.
+*/
+pub struct HeadlessContext {
+    gl: Rc<gl::Gl>,
+    egl: egl::Egl,
+    display: EGLDisplay,
+    context: EGLContext,
+}
+
+impl HeadlessContext {
+    pub fn from_size(width: u32, height: u32) -> Result<HeadlessContext, Error> {
+        let egl = egl::Egl::load().map_err(Error::LibraryLoad)?;
+
+        let display = unsafe {
+            egl.GetPlatformDisplay(egl::PLATFORM_SURFACELESS_MESA, ptr::null_mut(), ptr::null())
+        };
+        if display == egl::NO_DISPLAY {
+            return Err(Error::NoDisplay);
+        }
+
+        let context_attributes = [
+            egl::CONTEXT_MAJOR_VERSION as EGLint, 4,
+            egl::CONTEXT_MINOR_VERSION as EGLint, 5,
+            egl::CONTEXT_OPENGL_PROFILE_MASK as EGLint, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT as EGLint,
+            egl::NONE as EGLint,
+        ];
+
+        let context = unsafe {
+            if egl.Initialize(display, ptr::null_mut(), ptr::null_mut()) == egl::FALSE {
+                return Err(Error::Initialize { code: egl.GetError() });
+            }
+            if egl.BindAPI(egl::OPENGL_API) == egl::FALSE {
+                return Err(Error::BindApi { code: egl.GetError() });
+            }
+            // EGL_KHR_no_config_context, the surfaceless platform exposes no configs
+            let context = egl.CreateContext(display, ptr::null(), egl::NO_CONTEXT, context_attributes.as_ptr());
+            if context == egl::NO_CONTEXT {
+                return Err(Error::CreateContext { code: egl.GetError() });
+            }
+            if egl.MakeCurrent(display, egl::NO_SURFACE, egl::NO_SURFACE, context) == egl::FALSE {
+                let code = egl.GetError();
+                egl.DestroyContext(display, context);
+                return Err(Error::MakeCurrent { code });
+            }
+            context
+        };
+
+        let gl = Rc::new(gl::Gl::load_with(|s| {
+            let name = std::ffi::CString::new(s).unwrap();
+            unsafe { egl.GetProcAddress(name.as_ptr()) as *const _ }
+        }));
+
+        let headless = HeadlessContext {
+            gl,
+            egl,
+            display,
+            context,
+        };
+        headless.resize(width, height);
+        Ok(headless)
+    }
+
+    pub fn get_gl_handle(&self) -> Rc<gl::Gl> {
+        Rc::clone(&self.gl)
+    }
+
+    pub fn resize(&self, width: u32, height: u32) {
+        unsafe {
+            self.gl.Viewport(0, 0, width as i32, height as i32);
+        }
+    }
+
+    /**
+    Blocks until all submitted rendering commands have completed
+
+    see: glFinish
+    */
+    pub fn finish(&self) {
+        unsafe {
+            self.gl.Finish();
+        }
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        // The display is shared by every context in the process, so it is not terminated here
+        unsafe {
+            self.egl.MakeCurrent(self.display, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+            self.egl.DestroyContext(self.display, self.context);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+#![allow(non_local_definitions)]
+
+use std::rc::Rc;
+use gl::types::*;
+
+use crate::resources::Resources;
+
+use super::cubemap::TextureCube;
+use super::gl_buffers::{ColorFormat, FrameBuffer, FrameBufferError, VertexArray};
+use super::shader::{self, ComputeProgram, MemoryBarrier, ShaderProgram};
+use super::texture::{FilterMode, Texture, TextureDescriptor, TextureFormat, WrapMode};
+
+/// Compute shader convolving an environment into diffuse irradiance
+const IRRADIANCE_SHADER: &str = "shaders/ibl_irradiance";
+
+/// Compute shader convolving an environment with the GGX lobe of one roughness
+const PREFILTER_SHADER: &str = "shaders/ibl_prefilter";
+
+/// Full screen pass integrating the BRDF lookup table
+const BRDF_LUT_SHADER: &str = "shaders/brdf_lut";
+
+/// Face size of the irradiance cube map, irradiance varies slowly so it can be tiny
+pub const IRRADIANCE_SIZE: u32 = 32;
+
+/// Face size of the most detailed level of the prefiltered specular cube map
+pub const PREFILTERED_SIZE: u32 = 128;
+
+/// Width and height of the BRDF lookup table
+pub const BRDF_LUT_SIZE: u32 = 256;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Failed to build an image based lighting shader")]
+    Shader { #[cause] inner: shader::Error },
+    #[fail(display = "Failed to create the BRDF lookup table")]
+    BrdfLut { #[cause] inner: FrameBufferError },
+}
+
+/**
+Image based lighting precomputed from an environment cube map for the split sum approximation:
+a diffuse irradiance cube map, a specular cube map prefiltered by roughness along its mipmap
+levels and a BRDF lookup table, as sampled by shaders/pbr.frag
+*/
+pub struct ImageBasedLighting {
+    irradiance: TextureCube,
+    prefiltered: TextureCube,
+    /// Holds the lookup table in its only color attachment
+    brdf_lut: FrameBuffer,
+}
+
+impl ImageBasedLighting {
+    /**
+    Convolves `environment` with the default map sizes, the shaders are read from `res`
+
+    Sampling `environment` with mipmaps reduces speckles in the prefiltered map for high
+    dynamic range environments.
+    */
+    pub fn new(gl: Rc<gl::Gl>, res: &Resources, environment: &TextureCube) -> Result<Self, Error> {
+        Self::with_sizes(gl, res, environment, IRRADIANCE_SIZE, PREFILTERED_SIZE)
+    }
+
+    /// Like `new`, with the face sizes of the irradiance and prefiltered cube maps
+    pub fn with_sizes(gl: Rc<gl::Gl>, res: &Resources, environment: &TextureCube, irradiance_size: u32, prefiltered_size: u32) -> Result<Self, Error> {
+        let format = TextureFormat::Rgba16F;
+        let irradiance = TextureCube::new(Rc::clone(&gl), irradiance_size, &TextureDescriptor::render_target(format, FilterMode::Linear));
+        let prefiltered = TextureCube::new(Rc::clone(&gl), prefiltered_size, &TextureDescriptor {
+            format,
+            mipmap_filter: Some(FilterMode::Linear),
+            wrap: (WrapMode::ClampToEdge, WrapMode::ClampToEdge),
+            ..Default::default()
+        });
+
+        let irradiance_program = load_compute(&gl, res, IRRADIANCE_SHADER)?;
+        bind_environment(&irradiance_program, environment)?;
+        dispatch_cube_level(&gl, &irradiance_program, &irradiance, 0);
+
+        let prefilter_program = load_compute(&gl, res, PREFILTER_SHADER)?;
+        bind_environment(&prefilter_program, environment)?;
+        let max_level = prefiltered.levels() - 1;
+        for level in 0..=max_level {
+            let roughness = if max_level == 0 { 0.0 } else { level as f32 / max_level as f32 };
+            prefilter_program
+                .set_uniform("Roughness", &roughness)
+                .map_err(|inner| Error::Shader { inner })?;
+            dispatch_cube_level(&gl, &prefilter_program, &prefiltered, level);
+        }
+
+        let brdf_lut = integrate_brdf_lut(&gl, res)?;
+
+        Ok(ImageBasedLighting { irradiance, prefiltered, brdf_lut })
+    }
+
+    pub fn irradiance(&self) -> &TextureCube {
+        &self.irradiance
+    }
+
+    pub fn prefiltered(&self) -> &TextureCube {
+        &self.prefiltered
+    }
+
+    /// Scale (red) and bias (green) of F0 by n·v (horizontal) and roughness (vertical)
+    pub fn brdf_lut(&self) -> &Texture {
+        self.brdf_lut
+            .get_texture()
+            .expect("Expected the BRDF lookup table frame buffer to have a color attachment")
+    }
+
+    /**
+    Binds the maps as `IrradianceMap`, `PrefilteredMap` and `BrdfLut` of `program`, sets
+    `PrefilteredMaxLod` and enables them with `UseEnvironment`
+    */
+    pub fn bind(&self, program: &ShaderProgram) -> Result<(), shader::Error> {
+        program.bind_texture("IrradianceMap", &self.irradiance)?;
+        program.bind_texture("PrefilteredMap", &self.prefiltered)?;
+        program.bind_texture("BrdfLut", self.brdf_lut())?;
+        program.set_uniform("PrefilteredMaxLod", &((self.prefiltered.levels() - 1) as f32))?;
+        program.set_uniform("UseEnvironment", &1)
+    }
+}
+
+fn load_compute(gl: &Rc<gl::Gl>, res: &Resources, name: &str) -> Result<ComputeProgram, Error> {
+    ComputeProgram::from_resource_with_defines(Rc::clone(gl), res, name, &[("CUBE_FORMAT", "rgba16f")])
+        .map_err(|inner| Error::Shader { inner })
+}
+
+fn bind_environment(program: &ComputeProgram, environment: &TextureCube) -> Result<(), Error> {
+    program
+        .program()
+        .bind_texture("Environment", environment)
+        .map(|_| ())
+        .map_err(|inner| Error::Shader { inner })
+}
+
+/// Runs `program` once per texel of all faces of `level` of `cube`, bound as image unit 0
+fn dispatch_cube_level(gl: &gl::Gl, program: &ComputeProgram, cube: &TextureCube, level: u32) {
+    let (internal_format, _, _) = cube.descriptor().format.gl_formats();
+    let size = (cube.get_size() >> level).max(1);
+    unsafe {
+        gl.BindImageTexture(0, cube.get_id(), level as GLint, gl::TRUE, 0, gl::WRITE_ONLY, internal_format);
+    }
+    let [local_x, local_y, _] = program.local_size();
+    program.dispatch(size.div_ceil(local_x), size.div_ceil(local_y), 6);
+    shader::memory_barrier(gl, MemoryBarrier::TEXTURE_FETCH | MemoryBarrier::TEXTURE_UPDATE);
+    unsafe {
+        gl.BindImageTexture(0, 0, 0, gl::FALSE, 0, gl::WRITE_ONLY, internal_format);
+    }
+}
+
+/// Renders the BRDF lookup table into a two channel float frame buffer
+fn integrate_brdf_lut(gl: &Rc<gl::Gl>, res: &Resources) -> Result<FrameBuffer, Error> {
+    let program = ShaderProgram::from_resource(Rc::clone(gl), res, BRDF_LUT_SHADER)
+        .map_err(|inner| Error::Shader { inner })?;
+    let frame_buffer = FrameBuffer::builder(Rc::clone(gl), BRDF_LUT_SIZE, BRDF_LUT_SIZE)
+        .color_attachments(&[ColorFormat::Rg16F])
+        .build()
+        .map_err(|inner| Error::BrdfLut { inner })?;
+    // Empty, the vertex shader derives its full screen triangle from `gl_VertexID`
+    let vertex_array = VertexArray::new(Rc::clone(gl));
+
+    let mut viewport = [0 as GLint; 4];
+    unsafe {
+        gl.GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        gl.Viewport(0, 0, BRDF_LUT_SIZE as GLsizei, BRDF_LUT_SIZE as GLsizei);
+    }
+    frame_buffer.bind();
+    program.bind();
+    vertex_array.bind();
+    unsafe {
+        gl.DrawArrays(gl::TRIANGLES, 0, 3);
+    }
+    vertex_array.unbind();
+    frame_buffer.unbind();
+    unsafe {
+        gl.Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+    }
+
+    Ok(frame_buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::renderer::test_support::TempResources;
+    use crate::renderer::HeadlessContext;
+
+    fn read_cube_level(gl: &gl::Gl, cube: &TextureCube, level: u32) -> Vec<f32> {
+        let size = (cube.get_size() >> level).max(1) as GLsizei;
+        let mut pixels = vec![0.0f32; (size * size * 4 * 6) as usize];
+        unsafe {
+            gl.GetTextureSubImage(cube.get_id(), level as GLint, 0, 0, 0, size, size, 6, gl::RGBA, gl::FLOAT,
+                (pixels.len() * std::mem::size_of::<f32>()) as GLsizei, pixels.as_mut_ptr() as *mut GLvoid);
+        }
+        pixels
+    }
+
+    #[test]
+    fn constant_environment_keeps_its_color() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("ibl_constant");
+        RgbaImage::from_pixel(4, 4, Rgba([255, 128, 0, 255])).save(temp.path().join("face.png")).unwrap();
+        let descriptor = TextureDescriptor::render_target(TextureFormat::Rgba16F, FilterMode::Linear);
+        let environment = TextureCube::from_faces(Rc::clone(&gl), &temp.resources(), &["face.png"; 6], &descriptor).unwrap();
+
+        let assets = Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        let ibl = ImageBasedLighting::with_sizes(Rc::clone(&gl), &assets, &environment, 4, 8).unwrap();
+        assert_eq!(ibl.prefiltered().levels(), 4);
+
+        let expected = [1.0, 128.0 / 255.0, 0.0];
+        let cubes = [(ibl.irradiance(), 0), (ibl.prefiltered(), 0), (ibl.prefiltered(), 3)];
+        for (cube, level) in cubes.iter() {
+            for pixel in read_cube_level(&gl, cube, *level).chunks(4) {
+                for (channel, expected) in pixel.iter().zip(expected.iter()) {
+                    // The irradiance integral is a sum over samples, stored as half floats
+                    assert!((channel - expected).abs() < 1e-2, "{:?} differs from {:?}", pixel, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integrates_brdf_lookup_table() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let assets = Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        let frame_buffer = integrate_brdf_lut(&gl, &assets).unwrap();
+        let lut = frame_buffer.get_texture().unwrap();
+
+        let size = BRDF_LUT_SIZE as usize;
+        let mut pixels = vec![0.0f32; size * size * 2];
+        unsafe {
+            gl.GetTextureImage(lut.get_id(), 0, gl::RG, gl::FLOAT,
+                (pixels.len() * std::mem::size_of::<f32>()) as GLsizei, pixels.as_mut_ptr() as *mut GLvoid);
+        }
+        let texel = |x: usize, y: usize| (pixels[(y * size + x) * 2], pixels[(y * size + x) * 2 + 1]);
+
+        // Looking straight at a smooth surface reflects F0 unchanged
+        let (scale, bias) = texel(size - 1, 0);
+        assert!(scale > 0.95 && bias < 0.02, "scale {} bias {}", scale, bias);
+        // Grazing angles approach full reflection through the bias
+        let (scale, bias) = texel(0, 0);
+        assert!(bias > scale, "scale {} bias {}", scale, bias);
+        for (scale, bias) in pixels.chunks(2).map(|texel| (texel[0], texel[1])) {
+            assert!(scale >= 0.0 && bias >= 0.0 && scale + bias <= 1.01, "scale {} bias {}", scale, bias);
+        }
+    }
+}
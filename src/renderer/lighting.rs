@@ -0,0 +1,217 @@
+use std::rc::Rc;
+
+use nalgebra::{Matrix4, Point3, Unit, Vector3};
+
+use super::UniformBuffer;
+
+/// Lights the `Lights` uniform block holds, must match `MAX_LIGHTS` in shaders/lighting.glsl
+pub const MAX_LIGHTS: usize = 16;
+
+/// Uniform buffer binding point the lighting system binds the `Lights` block to
+pub const LIGHTS_BINDING: u32 = 0;
+
+/// Light shining in one direction from infinitely far away, like the sun
+#[derive(Copy, Clone, Debug)]
+pub struct DirectionalLight {
+    /// Direction the light travels in
+    pub direction: Unit<Vector3<f32>>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+}
+
+/// Light shining in all directions from a point, fading out towards `range`
+#[derive(Copy, Clone, Debug)]
+pub struct PointLight {
+    pub position: Point3<f32>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    /// Distance at which the light has no effect anymore
+    pub range: f32,
+}
+
+/// Point light restricted to a cone around `direction`
+#[derive(Copy, Clone, Debug)]
+pub struct SpotLight {
+    pub position: Point3<f32>,
+    /// Direction of the cone's axis
+    pub direction: Unit<Vector3<f32>>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    pub range: f32,
+    /// Angle from the axis in radians within which the light is at full intensity
+    pub inner_angle: f32,
+    /// Angle from the axis in radians beyond which there is no light
+    pub outer_angle: f32,
+}
+
+/**
+A light source, usable as a component: the lighting system places it by the entity's
+`SceneNode`, with positions and directions in the node's local space
+*/
+#[derive(Copy, Clone, Debug)]
+pub enum Light {
+    Directional(DirectionalLight),
+    Point(PointLight),
+    Spot(SpotLight),
+}
+
+impl Light {
+    /// The light with its position and direction transformed by `transform`
+    pub fn transformed(&self, transform: &Matrix4<f32>) -> Light {
+        let direction = |direction: &Unit<Vector3<f32>>| Unit::new_normalize(transform.transform_vector(direction));
+        match self {
+            Light::Directional(light) => Light::Directional(DirectionalLight { direction: direction(&light.direction), ..*light }),
+            Light::Point(light) => Light::Point(PointLight { position: transform.transform_point(&light.position), ..*light }),
+            Light::Spot(light) => Light::Spot(SpotLight {
+                position: transform.transform_point(&light.position),
+                direction: direction(&light.direction),
+                ..*light
+            }),
+        }
+    }
+
+    fn to_std140(self) -> GpuLight {
+        match self {
+            Light::Directional(light) => GpuLight {
+                position: [0.0, 0.0, 0.0, 0.0],
+                direction: [light.direction.x, light.direction.y, light.direction.z, 0.0],
+                color: [light.color.x, light.color.y, light.color.z, light.intensity],
+                cone: [0.0; 4],
+            },
+            Light::Point(light) => GpuLight {
+                position: [light.position.x, light.position.y, light.position.z, 1.0],
+                direction: [0.0, 0.0, 0.0, light.range],
+                color: [light.color.x, light.color.y, light.color.z, light.intensity],
+                cone: [0.0; 4],
+            },
+            Light::Spot(light) => GpuLight {
+                position: [light.position.x, light.position.y, light.position.z, 2.0],
+                direction: [light.direction.x, light.direction.y, light.direction.z, light.range],
+                color: [light.color.x, light.color.y, light.color.z, light.intensity],
+                cone: [light.inner_angle.cos(), light.outer_angle.cos(), 0.0, 0.0],
+            },
+        }
+    }
+}
+
+/// One `Light` of the `Lights` uniform block in std140 layout
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+struct GpuLight {
+    /// xyz position, w type: 0 directional, 1 point, 2 spot
+    position: [f32; 4],
+    /// xyz direction, w range
+    direction: [f32; 4],
+    /// rgb color, a intensity
+    color: [f32; 4],
+    /// x cosine of the inner angle, y cosine of the outer angle
+    cone: [f32; 4],
+}
+
+/// The `Lights` uniform block in std140 layout
+#[repr(C)]
+struct LightsBlock {
+    lights: [GpuLight; MAX_LIGHTS],
+    ambient: [f32; 4],
+    count: i32,
+    _padding: [i32; 3],
+}
+
+/**
+Uniform buffer holding the `Lights` block of shaders/lighting.glsl
+*/
+pub struct LightBuffer {
+    buffer: UniformBuffer,
+    /// Color added to every lit surface regardless of the lights
+    pub ambient: Vector3<f32>,
+}
+
+impl LightBuffer {
+    pub fn new(gl: Rc<gl::Gl>, ambient: Vector3<f32>) -> Self {
+        let light_buffer = LightBuffer { buffer: UniformBuffer::new(gl), ambient };
+        light_buffer.upload(&[]);
+        light_buffer
+    }
+
+    /**
+    Replaces the lights in the buffer, lights beyond `MAX_LIGHTS` are dropped
+
+    Returns the number of lights uploaded.
+    */
+    pub fn upload(&self, lights: &[Light]) -> usize {
+        let count = lights.len().min(MAX_LIGHTS);
+        let mut block = LightsBlock {
+            lights: [GpuLight::default(); MAX_LIGHTS],
+            ambient: [self.ambient.x, self.ambient.y, self.ambient.z, 0.0],
+            count: count as i32,
+            _padding: [0; 3],
+        };
+        for (gpu_light, light) in block.lights.iter_mut().zip(lights.iter()) {
+            *gpu_light = light.to_std140();
+        }
+
+        self.buffer.bind();
+        self.buffer.buffer_dynamic_draw(std::slice::from_ref(&block));
+        self.buffer.unbind();
+        count
+    }
+
+    /// Binds the buffer to uniform buffer binding point `binding`, see `ShaderProgram::bind_uniform_block`
+    pub fn bind_base(&self, binding: u32) {
+        self.buffer.bind_base(binding);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::renderer::{HeadlessContext, ShaderProgram};
+    use crate::resources::Resources;
+
+    #[test]
+    fn lights_block_matches_std140_layout() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let assets = Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        let program = ShaderProgram::from_resource(Rc::clone(&gl), &assets, "shaders/lit").unwrap();
+        program.bind_uniform_block("Lights", LIGHTS_BINDING).unwrap();
+        assert!(program.bind_uniform_block("Missing", LIGHTS_BINDING).is_err());
+
+        // ShaderProgram keeps its id private, read it back from the current binding
+        program.bind();
+        let name = std::ffi::CString::new("Lights").unwrap();
+        let mut id: gl::types::GLint = 0;
+        let mut size: gl::types::GLint = 0;
+        unsafe {
+            gl.GetIntegerv(gl::CURRENT_PROGRAM, &mut id);
+            let index = gl.GetUniformBlockIndex(id as gl::types::GLuint, name.as_ptr());
+            gl.GetActiveUniformBlockiv(id as gl::types::GLuint, index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut size);
+        }
+        assert_eq!(size as usize, std::mem::size_of::<LightsBlock>());
+    }
+
+    #[test]
+    fn transforms_positions_and_directions() {
+        let light = Light::Spot(SpotLight {
+            position: Point3::new(1.0, 0.0, 0.0),
+            direction: Vector3::x_axis(),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+            range: 10.0,
+            inner_angle: 0.2,
+            outer_angle: 0.4,
+        });
+        let transform = Matrix4::new_translation(&Vector3::new(0.0, 2.0, 0.0))
+            * Matrix4::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2);
+
+        match light.transformed(&transform) {
+            Light::Spot(spot) => {
+                assert!((spot.position - Point3::new(0.0, 3.0, 0.0)).norm() < 1e-5);
+                assert!((spot.direction.into_inner() - Vector3::y()).norm() < 1e-5);
+            },
+            _ => panic!("Expected the transformed light to still be a spot light"),
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::cell::{Ref, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use nalgebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4};
+
+use super::shader::{Error, ShaderProgram};
+use super::texture::SampledTexture;
+
+/**
+A uniform value stored in a `Material`, one variant per `UniformValue` type
+*/
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MaterialValue {
+    Float(f32),
+    Int(i32),
+    Vec2(Vector2<f32>),
+    Vec3(Vector3<f32>),
+    Vec4(Vector4<f32>),
+    Mat3(Matrix3<f32>),
+    Mat4(Matrix4<f32>),
+}
+
+impl MaterialValue {
+    fn check(&self, program: &ShaderProgram, name: &str) -> Result<(), Error> {
+        match self {
+            MaterialValue::Float(_) => program.check_uniform::<f32>(name),
+            MaterialValue::Int(_) => program.check_uniform::<i32>(name),
+            MaterialValue::Vec2(_) => program.check_uniform::<Vector2<f32>>(name),
+            MaterialValue::Vec3(_) => program.check_uniform::<Vector3<f32>>(name),
+            MaterialValue::Vec4(_) => program.check_uniform::<Vector4<f32>>(name),
+            MaterialValue::Mat3(_) => program.check_uniform::<Matrix3<f32>>(name),
+            MaterialValue::Mat4(_) => program.check_uniform::<Matrix4<f32>>(name),
+        }
+        .map(|_| ())
+    }
+
+    /// Sets the uniform `name` of `program` to the value, see `ShaderProgram::set_uniform`
+    pub fn set(&self, program: &ShaderProgram, name: &str) -> Result<(), Error> {
+        match self {
+            MaterialValue::Float(value) => program.set_uniform(name, value),
+            MaterialValue::Int(value) => program.set_uniform(name, value),
+            MaterialValue::Vec2(value) => program.set_uniform(name, value),
+            MaterialValue::Vec3(value) => program.set_uniform(name, value),
+            MaterialValue::Vec4(value) => program.set_uniform(name, value),
+            MaterialValue::Mat3(value) => program.set_uniform(name, value),
+            MaterialValue::Mat4(value) => program.set_uniform(name, value),
+        }
+    }
+}
+
+macro_rules! impl_from_for_material_value {
+    ($type:ty, $variant:ident) => {
+        impl From<$type> for MaterialValue {
+            fn from(value: $type) -> Self {
+                MaterialValue::$variant(value)
+            }
+        }
+    };
+}
+
+impl_from_for_material_value!(f32, Float);
+impl_from_for_material_value!(i32, Int);
+impl_from_for_material_value!(Vector2<f32>, Vec2);
+impl_from_for_material_value!(Vector3<f32>, Vec3);
+impl_from_for_material_value!(Vector4<f32>, Vec4);
+impl_from_for_material_value!(Matrix3<f32>, Mat3);
+impl_from_for_material_value!(Matrix4<f32>, Mat4);
+
+/**
+A shader program together with the textures and uniform values to draw with it
+
+Texture slots and uniforms are checked against the program's reflection when they are set,
+`apply` binds the program and all of its state before drawing. Programs are shared between
+materials through a `RefCell`, so that they can still be hot reloaded.
+*/
+pub struct Material {
+    program: Rc<RefCell<ShaderProgram>>,
+    textures: BTreeMap<String, Rc<dyn SampledTexture>>,
+    uniforms: BTreeMap<String, MaterialValue>,
+}
+
+impl Material {
+    pub fn new(program: Rc<RefCell<ShaderProgram>>) -> Self {
+        Material { program, textures: BTreeMap::new(), uniforms: BTreeMap::new() }
+    }
+
+    /// Borrows the program, e.g. to set per draw uniforms after `apply`
+    pub fn program(&self) -> Ref<'_, ShaderProgram> {
+        self.program.borrow()
+    }
+
+    /// The shared program, to create more materials drawing with it
+    pub fn shared_program(&self) -> &Rc<RefCell<ShaderProgram>> {
+        &self.program
+    }
+
+    /**
+    Puts `texture` in the slot of the sampler uniform `name`, replacing any previous texture
+
+    Fails like `ShaderProgram::check_texture` if the program can not sample it as `name`.
+    */
+    pub fn set_texture(&mut self, name: &str, texture: Rc<dyn SampledTexture>) -> Result<(), Error> {
+        self.program().check_texture(name, texture.as_ref())?;
+        self.textures.insert(name.into(), texture);
+        Ok(())
+    }
+
+    /// Like `set_texture`, consuming and returning the material
+    pub fn with_texture(mut self, name: &str, texture: Rc<dyn SampledTexture>) -> Result<Self, Error> {
+        self.set_texture(name, texture)?;
+        Ok(self)
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&Rc<dyn SampledTexture>> {
+        self.textures.get(name)
+    }
+
+    /**
+    Stores `value` for the uniform `name`, replacing any previous value
+
+    Fails like `ShaderProgram::check_uniform` if the program has no such uniform of that type.
+    */
+    pub fn set_uniform<T>(&mut self, name: &str, value: T) -> Result<(), Error>
+    where T: Into<MaterialValue>, {
+        let value = value.into();
+        value.check(&self.program(), name)?;
+        self.uniforms.insert(name.into(), value);
+        Ok(())
+    }
+
+    /// Like `set_uniform`, consuming and returning the material
+    pub fn with_uniform<T>(mut self, name: &str, value: T) -> Result<Self, Error>
+    where T: Into<MaterialValue>, {
+        self.set_uniform(name, value)?;
+        Ok(self)
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&MaterialValue> {
+        self.uniforms.get(name)
+    }
+
+    /**
+    Sampler uniforms of the program without a texture slot in this material, which sample
+    whatever is bound to their texture unit, e.g. maps bound per frame like shadow maps
+    */
+    pub fn unassigned_samplers(&self) -> Vec<String> {
+        let program = self.program();
+        let mut names: Vec<String> = program
+            .uniforms()
+            .keys()
+            .filter(|name| !name.ends_with("[0]"))
+            .filter(|name| program.texture_unit(name).is_some() && !self.textures.contains_key(*name))
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    /**
+    Binds the program, sets all uniform values and binds all textures to their units
+
+    Fails if a hot reload removed or changed one of the uniforms, all others are still applied.
+    Uniforms of the wrong type are reported before missing ones.
+    */
+    pub fn apply(&self) -> Result<(), Error> {
+        let program = self.program();
+        program.bind();
+        let uniforms = self.uniforms.iter().map(|(name, value)| value.set(&program, name));
+        let textures = self.textures.iter().map(|(name, texture)| program.bind_texture(name, texture.as_ref()).map(|_| ()));
+        // Collected first so that every uniform and texture is applied
+        let mut errors: Vec<Error> = uniforms.chain(textures).filter_map(Result::err).collect();
+        errors.sort_by_key(|e| matches!(e, Error::UnknownUniform { .. }));
+        errors.into_iter().next().map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gl::types::*;
+    use image::Rgba;
+
+    use super::*;
+    use crate::renderer::test_support::{TempResources, FULLSCREEN_VERTEX_SOURCE};
+    use crate::renderer::{FrameBuffer, HeadlessContext, Texture, TextureCube, TextureDescriptor, TextureFormat, FilterMode, VertexArray};
+
+    #[test]
+    fn validates_slots_and_uniforms_against_the_program() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("material");
+        temp.write("material.vert", FULLSCREEN_VERTEX_SOURCE);
+        temp.write("material.frag", "#version 330 core\nuniform sampler2D Albedo;\n\
+            uniform samplerCube Environment;\nuniform vec4 Tint;\nuniform float Strength;\nout vec4 color;\nvoid main() {\n\
+            color = vec4((texture(Albedo, vec2(0.5)) * Tint * Strength).rgb, texture(Environment, vec3(1.0)).a);\n}\n");
+        let program = ShaderProgram::from_resource(Rc::clone(&gl), &temp.resources(), "material").unwrap();
+        let program = Rc::new(RefCell::new(program));
+
+        let red = image::RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 255]));
+        let red = Rc::new(Texture::from_image_with_descriptor(Rc::clone(&gl), &red,
+            &TextureDescriptor::render_target(TextureFormat::Rgba8, FilterMode::Nearest)));
+        let cube = Rc::new(TextureCube::new(Rc::clone(&gl), 1, &TextureDescriptor::render_target(TextureFormat::Rgba8, FilterMode::Nearest)));
+
+        let mut material = Material::new(Rc::clone(&program));
+        assert!(matches!(material.set_texture("Missing", red.clone()), Err(Error::UnknownUniform { .. })));
+        assert!(matches!(material.set_texture("Albedo", cube.clone()), Err(Error::UniformTypeMismatch { .. })));
+        assert!(matches!(material.set_texture("Tint", red.clone()), Err(Error::UniformTypeMismatch { .. })));
+        assert!(matches!(material.set_uniform("Tint", 1.0f32), Err(Error::UniformTypeMismatch { .. })));
+        assert!(matches!(material.set_uniform("Missing", 1.0f32), Err(Error::UnknownUniform { .. })));
+        assert!(material.uniform("Tint").is_none());
+
+        let material = material
+            .with_texture("Albedo", red)
+            .and_then(|material| material.with_uniform("Tint", Vector4::new(1.0, 1.0, 1.0, 1.0)))
+            .and_then(|material| material.with_uniform("Strength", 0.5f32))
+            .unwrap();
+        assert_eq!(material.uniform("Strength"), Some(&MaterialValue::Float(0.5)));
+        assert_eq!(material.unassigned_samplers(), vec!["Environment".to_owned()]);
+
+        // Creating textures binds them to the active unit, so this comes before applying
+        let frame_buffer = FrameBuffer::new(Rc::clone(&gl), 1, 1);
+        let vertex_array = VertexArray::new(Rc::clone(&gl));
+
+        // Leave the unit of Albedo bound to another texture, apply has to rebind it
+        let unit = program.borrow().texture_unit("Albedo").unwrap();
+        let other = Texture::empty(Rc::clone(&gl), 1, 1);
+        other.bind_to_unit(unit.0);
+        unsafe { gl.UseProgram(0) };
+        material.apply().unwrap();
+
+        frame_buffer.bind();
+        vertex_array.bind();
+        unsafe {
+            gl.Viewport(0, 0, 1, 1);
+            gl.DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        frame_buffer.unbind();
+        let pixel = frame_buffer.read_rgb_image().get_pixel(0, 0).0;
+        assert!((pixel[0] as i32 - 128).abs() <= 1 && pixel[1] == 0, "{:?}", pixel);
+
+        let mut current: GLint = 0;
+        unsafe { gl.GetIntegerv(gl::CURRENT_PROGRAM, &mut current) };
+        assert_ne!(current, 0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::marker::PhantomData;
+use std::rc::Rc;
+use gl::types::*;
+
+use super::data::VertexAttribPointers;
+use super::{ArrayBuffer, ElementArrayBuffer, VertexArray};
+
+/// Integer types usable as element indices
+pub trait IndexType: Copy {
+    const INDEX_TYPE: GLenum;
+}
+
+impl IndexType for u8 {
+    const INDEX_TYPE: GLenum = gl::UNSIGNED_BYTE;
+}
+
+impl IndexType for u16 {
+    const INDEX_TYPE: GLenum = gl::UNSIGNED_SHORT;
+}
+
+impl IndexType for u32 {
+    const INDEX_TYPE: GLenum = gl::UNSIGNED_INT;
+}
+
+/// How the indices of a `Mesh` are assembled into primitives
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Topology {
+    Points,
+    Lines,
+    LineStrip,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Topology {
+    fn gl_mode(self) -> GLenum {
+        match self {
+            Topology::Points => gl::POINTS,
+            Topology::Lines => gl::LINES,
+            Topology::LineStrip => gl::LINE_STRIP,
+            Topology::Triangles => gl::TRIANGLES,
+            Topology::TriangleStrip => gl::TRIANGLE_STRIP,
+            Topology::TriangleFan => gl::TRIANGLE_FAN,
+        }
+    }
+}
+
+/**
+Indexed geometry owning its vertex array, vertex buffer and index buffer
+*/
+pub struct Mesh<V>
+where V: VertexAttribPointers, {
+    gl: Rc<gl::Gl>,
+    vertex_array: VertexArray,
+    _vertex_buffer: ArrayBuffer,
+    _index_buffer: ElementArrayBuffer,
+    index_count: usize,
+    index_type: GLenum,
+    topology: Topology,
+    _marker: PhantomData<V>,
+}
+
+impl<V> Mesh<V>
+where V: VertexAttribPointers, {
+    /**
+    Uploads `vertices` and `indices` for static draw and records the vertex layout of `V`
+    */
+    pub fn new<I>(gl: Rc<gl::Gl>, vertices: &[V], indices: &[I], topology: Topology) -> Self
+    where I: IndexType, {
+        let vertex_buffer = ArrayBuffer::new(Rc::clone(&gl));
+        vertex_buffer.bind();
+        vertex_buffer.buffer_static_draw(vertices);
+        vertex_buffer.unbind();
+
+        let index_buffer = ElementArrayBuffer::new(Rc::clone(&gl));
+        let vertex_array = VertexArray::new(Rc::clone(&gl));
+        vertex_array.bind();
+        vertex_buffer.bind();
+        index_buffer.bind();
+        index_buffer.buffer_static_draw(indices);
+        V::vertex_attrib_pointers(&gl);
+        vertex_array.unbind();
+        vertex_buffer.unbind();
+        index_buffer.unbind();
+
+        Mesh {
+            gl,
+            vertex_array,
+            _vertex_buffer: vertex_buffer,
+            _index_buffer: index_buffer,
+            index_count: indices.len(),
+            index_type: I::INDEX_TYPE,
+            topology,
+            _marker: PhantomData,
+        }
+    }
+
+    /**
+    Draws all indices of the mesh with the currently bound program and textures
+
+    see: glDrawElements
+    */
+    pub fn draw(&self) {
+        self.vertex_array.bind();
+        unsafe {
+            self.gl.DrawElements(self.topology.gl_mode(), self.index_count as GLsizei, self.index_type, std::ptr::null());
+        }
+        self.vertex_array.unbind();
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+}
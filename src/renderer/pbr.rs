@@ -0,0 +1,238 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use nalgebra::{Vector3, Vector4};
+
+use super::material::{Material, MaterialValue};
+use super::shader::{self, ShaderProgram};
+use super::texture::{SampledTexture, Texture};
+
+/// Bits of the `MaterialMaps` uniform of shaders/pbr.frag, one per bound map
+pub const MAP_BASE_COLOR: i32 = 1;
+pub const MAP_METALLIC_ROUGHNESS: i32 = 2;
+pub const MAP_NORMAL: i32 = 4;
+pub const MAP_OCCLUSION: i32 = 8;
+pub const MAP_EMISSIVE: i32 = 16;
+
+/**
+A metallic-roughness material as in glTF 2.0, rendered by shaders/pbr
+
+Every factor is multiplied with its map, if there is one. Base color and emissive maps hold
+sRGB colors and should use an sRGB `TextureFormat`, all other maps hold linear values.
+`to_material` turns it into a `Material` for a `MeshRenderer`.
+*/
+#[derive(Clone)]
+pub struct PbrMaterial {
+    /// Linear RGBA base color, alpha is written to the output for blending
+    pub base_color: Vector4<f32>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Linear emitted radiance, added after lighting
+    pub emissive: Vector3<f32>,
+    /// Scales the x and y components of tangent space normals from `normal_map`
+    pub normal_scale: f32,
+    /// How much `occlusion_map` darkens indirect light, from 0 (not at all) to 1
+    pub occlusion_strength: f32,
+    pub base_color_map: Option<Rc<Texture>>,
+    /// Roughness in the green channel, metallic in the blue channel
+    pub metallic_roughness_map: Option<Rc<Texture>>,
+    /// Tangent space normals
+    pub normal_map: Option<Rc<Texture>>,
+    /// Ambient occlusion in the red channel
+    pub occlusion_map: Option<Rc<Texture>>,
+    pub emissive_map: Option<Rc<Texture>>,
+}
+
+impl Default for PbrMaterial {
+    /// A white, fully rough dielectric without maps
+    fn default() -> Self {
+        PbrMaterial {
+            base_color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            metallic: 0.0,
+            roughness: 1.0,
+            emissive: Vector3::zeros(),
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+            base_color_map: None,
+            metallic_roughness_map: None,
+            normal_map: None,
+            occlusion_map: None,
+            emissive_map: None,
+        }
+    }
+}
+
+impl PbrMaterial {
+    /// The `MaterialMaps` bits of the maps this material has
+    pub fn map_bits(&self) -> i32 {
+        self.maps()
+            .iter()
+            .filter(|(_, _, map)| map.is_some())
+            .fold(0, |bits, (bit, _, _)| bits | bit)
+    }
+
+    /**
+    Sets the factors and binds the maps of the material on `program`, e.g. shaders/pbr
+
+    Fails if `program` lacks one of the uniforms.
+    */
+    pub fn bind(&self, program: &ShaderProgram) -> Result<(), shader::Error> {
+        for (name, value) in self.factors().iter() {
+            value.set(program, name)?;
+        }
+        for (_, name, map) in self.maps().iter() {
+            if let Some(texture) = map {
+                program.bind_texture(name, texture.as_ref())?;
+            }
+        }
+        Ok(())
+    }
+
+    /**
+    A `Material` drawing with `program`, e.g. shaders/pbr, with the factors and maps of this one
+
+    Fails if `program` lacks one of the uniforms.
+    */
+    pub fn to_material(&self, program: Rc<RefCell<ShaderProgram>>) -> Result<Material, shader::Error> {
+        let mut material = Material::new(program);
+        for (name, value) in self.factors().iter() {
+            material.set_uniform(name, *value)?;
+        }
+        for (_, name, map) in self.maps().iter() {
+            if let Some(texture) = map {
+                material.set_texture(name, Rc::clone(texture) as Rc<dyn SampledTexture>)?;
+            }
+        }
+        Ok(material)
+    }
+
+    fn factors(&self) -> [(&'static str, MaterialValue); 7] {
+        [
+            ("BaseColorFactor", self.base_color.into()),
+            ("MetallicFactor", self.metallic.into()),
+            ("RoughnessFactor", self.roughness.into()),
+            ("EmissiveFactor", self.emissive.into()),
+            ("NormalScale", self.normal_scale.into()),
+            ("OcclusionStrength", self.occlusion_strength.into()),
+            ("MaterialMaps", self.map_bits().into()),
+        ]
+    }
+
+    fn maps(&self) -> [(i32, &'static str, &Option<Rc<Texture>>); 5] {
+        [
+            (MAP_BASE_COLOR, "BaseColorMap", &self.base_color_map),
+            (MAP_METALLIC_ROUGHNESS, "MetallicRoughnessMap", &self.metallic_roughness_map),
+            (MAP_NORMAL, "NormalMap", &self.normal_map),
+            (MAP_OCCLUSION, "OcclusionMap", &self.occlusion_map),
+            (MAP_EMISSIVE, "EmissiveMap", &self.emissive_map),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use gl::types::*;
+    use image::Rgba;
+
+    use super::*;
+    use crate::renderer::{FrameBuffer, HeadlessContext, LightBuffer, Mesh, PosNormalUV, Topology, LIGHTS_BINDING};
+    use crate::renderer::{DirectionalLight, Light, TextureDescriptor, TextureFormat};
+    use crate::resources::Resources;
+
+    /// Draws a quad facing +Z under a light from the camera's direction, returning the center pixel
+    fn render_quad(material: &PbrMaterial) -> [u8; 4] {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let assets = Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        let program = ShaderProgram::from_resource(Rc::clone(&gl), &assets, "shaders/pbr").unwrap();
+
+        let vertices = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].map(|(x, y)| PosNormalUV {
+            position: (x, y, 0.0).into(),
+            normal: (0.0, 0.0, 1.0).into(),
+            uv: ((x + 1.0) / 2.0, (y + 1.0) / 2.0).into(),
+        });
+        let quad = Mesh::new(Rc::clone(&gl), &vertices, &[1u8, 0, 2, 2, 3, 1], Topology::Triangles);
+        let lights = LightBuffer::new(Rc::clone(&gl), Vector3::zeros());
+        lights.upload(&[Light::Directional(DirectionalLight {
+            direction: -Vector3::z_axis(),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            intensity: 2.0,
+        })]);
+        lights.bind_base(LIGHTS_BINDING);
+        program.bind_uniform_block("Lights", LIGHTS_BINDING).unwrap();
+
+        // Identity view and projection, looking down -Z at the quad from z = 1
+        program.set_uniform("CameraPosition", &Vector3::new(0.0, 0.0, 1.0)).unwrap();
+        for name in ["Model", "View", "Perspective"].iter() {
+            program.set_uniform(name, &nalgebra::Matrix4::<f32>::identity()).unwrap();
+        }
+        material.bind(&program).unwrap();
+
+        let frame_buffer = FrameBuffer::new(Rc::clone(&gl), 1, 1);
+        frame_buffer.bind();
+        frame_buffer.clear([0.0, 0.0, 0.0, 1.0]);
+        program.bind();
+        quad.draw();
+        frame_buffer.unbind();
+        context.finish();
+        frame_buffer.read_rgba_image().get_pixel(0, 0).0
+    }
+
+    #[test]
+    fn binds_only_the_maps_it_has() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let image = image::RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        let white = Rc::new(Texture::from_image_with_descriptor(Rc::clone(&gl), &image,
+            &TextureDescriptor { format: TextureFormat::Srgb8Alpha8, ..Default::default() }));
+
+        let material = PbrMaterial {
+            base_color_map: Some(Rc::clone(&white)),
+            occlusion_map: Some(white),
+            ..Default::default()
+        };
+        assert_eq!(material.map_bits(), MAP_BASE_COLOR | MAP_OCCLUSION);
+        assert_eq!(PbrMaterial::default().map_bits(), 0);
+
+        let assets = Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        let program = ShaderProgram::from_resource(Rc::clone(&gl), &assets, "shaders/pbr").unwrap();
+        material.bind(&program).unwrap();
+        let unit = program.texture_unit("BaseColorMap").unwrap();
+        let mut bound: GLint = 0;
+        unsafe {
+            gl.ActiveTexture(gl::TEXTURE0 + unit.0);
+            gl.GetIntegerv(gl::TEXTURE_BINDING_2D, &mut bound);
+        }
+        assert_ne!(bound, 0);
+        let lit = ShaderProgram::from_resource(Rc::clone(&gl), &assets, "shaders/lit").unwrap();
+        assert!(material.bind(&lit).is_err());
+        assert!(material.to_material(Rc::new(RefCell::new(lit))).is_err());
+
+        let converted = material.to_material(Rc::new(RefCell::new(program))).unwrap();
+        assert!(converted.texture("BaseColorMap").is_some() && converted.texture("NormalMap").is_none());
+        assert_eq!(converted.uniform("MaterialMaps"), Some(&MaterialValue::Int(MAP_BASE_COLOR | MAP_OCCLUSION)));
+    }
+
+    #[test]
+    fn metals_tint_reflections_and_rough_surfaces_spread_them() {
+        let red = |metallic: f32, roughness: f32| PbrMaterial {
+            base_color: Vector4::new(1.0, 0.0, 0.0, 1.0),
+            metallic,
+            roughness,
+            ..Default::default()
+        };
+
+        // The dielectric reflects a white highlight on top of its red diffuse light
+        let dielectric = render_quad(&red(0.0, 0.3));
+        assert!(dielectric[1] > 0 && dielectric[0] > dielectric[1]);
+        // Metals have no diffuse light and reflect in their own color
+        let metal = render_quad(&red(1.0, 0.3));
+        assert_eq!(metal[1], metal[2]);
+        assert!(metal[0] > dielectric[0] && metal[1] < dielectric[1]);
+        // Rougher surfaces spread the highlight, which makes its center darker
+        let rough_metal = render_quad(&red(1.0, 0.8));
+        assert!(rough_metal[0] < metal[0]);
+    }
+}
@@ -0,0 +1,669 @@
+#![allow(non_local_definitions)]
+
+use std::cell::{Ref, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use gl::types::*;
+use nalgebra::Vector2;
+
+use crate::resources::Resources;
+
+use super::color_lut::ColorLut;
+use super::gl_buffers::{ColorFormat, FrameBuffer, FrameBufferError, VertexArray};
+use super::material::Material;
+use super::shader::{self, ignore_unknown_uniform, ShaderProgram};
+use super::texture::Texture;
+
+/// Vertex shader deriving a full screen triangle from `gl_VertexID`, shared by every pass
+const FULLSCREEN_SHADER: &str = "shaders/post/fullscreen.vert";
+
+/// Passes its input through unchanged, used when no pass is enabled
+const COPY_SHADER: &str = "shaders/post/copy";
+const TONE_MAPPING_SHADER: &str = "shaders/post/tone_mapping";
+const GAMMA_CORRECTION_SHADER: &str = "shaders/post/gamma_correction";
+const FXAA_SHADER: &str = "shaders/post/fxaa";
+const BLOOM_DOWNSAMPLE_SHADER: &str = "shaders/post/bloom_downsample";
+const BLOOM_UPSAMPLE_SHADER: &str = "shaders/post/bloom_upsample";
+const BLOOM_COMPOSITE_SHADER: &str = "shaders/post/bloom_composite";
+const VIGNETTE_SHADER: &str = "shaders/post/vignette";
+const COLOR_GRADING_SHADER: &str = "shaders/post/color_grading";
+
+/// Intermediate results keep high dynamic range colors until a pass maps them to [0, 1]
+const TARGET_FORMAT: ColorFormat = ColorFormat::Rgba16F;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Failed to build a post processing shader")]
+    Shader { #[cause] inner: shader::Error },
+    #[fail(display = "Failed to create the post processing targets")]
+    Targets { #[cause] inner: FrameBufferError },
+    #[fail(display = "There already is a post processing pass named {}", name)]
+    DuplicatePass { name: String },
+    #[fail(display = "There is no post processing pass named {}", name)]
+    UnknownPass { name: String },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    /// c / (1 + c), keeps hues but flattens highlights
+    Reinhard,
+    /// A fit of the ACES filmic curve, with more contrast and saturation
+    Aces,
+}
+
+/// Maps high dynamic range colors to [0, 1], see shaders/post/tone_mapping.frag
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ToneMapping {
+    pub operator: ToneMapOperator,
+    /// Colors are multiplied by it before mapping
+    pub exposure: f32,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping { operator: ToneMapOperator::Aces, exposure: 1.0 }
+    }
+}
+
+/**
+Light bleeding around bright areas, blurred over a chain of `levels` targets of half the size
+of the previous one and added back onto the image
+*/
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bloom {
+    /// Brightness above which colors bloom, so it belongs before tone mapping
+    pub threshold: f32,
+    /// Width of the soft transition below `threshold`
+    pub knee: f32,
+    /// Scale of the blurred light added to the image
+    pub intensity: f32,
+    pub levels: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom { threshold: 1.0, knee: 0.5, intensity: 0.1, levels: 5 }
+    }
+}
+
+/// Darkens the corners of the image, see shaders/post/vignette.frag
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vignette {
+    /// Darkening at full strength, from 0 (none) to 1 (black)
+    pub intensity: f32,
+    /// Distance from the center where darkening starts, 0.5 is the middle of the edges
+    pub radius: f32,
+    /// Distance over which darkening fades in beyond `radius`
+    pub softness: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Vignette { intensity: 0.4, radius: 0.4, softness: 0.4 }
+    }
+}
+
+/// Looks colors up in a 3D table, meant for [0, 1] colors after tone mapping
+#[derive(Clone)]
+pub struct ColorGrading {
+    pub lut: Rc<ColorLut>,
+    /// Blends from the input (0) to the graded colors (1)
+    pub strength: f32,
+}
+
+impl ColorGrading {
+    pub fn new(lut: Rc<ColorLut>) -> Self {
+        ColorGrading { lut, strength: 1.0 }
+    }
+}
+
+/**
+A full screen pass of a `PostProcess` chain
+*/
+pub enum Effect {
+    ToneMapping(ToneMapping),
+    /// Raises colors to the power of 1 / gamma, e.g. 2.2 to display linear colors
+    GammaCorrection(f32),
+    /// Fast approximate anti-aliasing, best run on gamma corrected colors
+    Fxaa,
+    Bloom(Bloom),
+    Vignette(Vignette),
+    ColorGrading(ColorGrading),
+    /**
+    A custom pass drawn with the material's program, which samples the previous result as
+    `Input` and may read the texel size of it from `TexelSize`, see `PostProcess::load_shader`
+    */
+    Shader(Material),
+}
+
+impl Effect {
+    /// Fragment shaders of the built in effects, loaded when a pass is added
+    fn shaders(&self) -> &'static [&'static str] {
+        match self {
+            Effect::ToneMapping(_) => &[TONE_MAPPING_SHADER],
+            Effect::GammaCorrection(_) => &[GAMMA_CORRECTION_SHADER],
+            Effect::Fxaa => &[FXAA_SHADER],
+            Effect::Bloom(_) => &[BLOOM_DOWNSAMPLE_SHADER, BLOOM_UPSAMPLE_SHADER, BLOOM_COMPOSITE_SHADER],
+            Effect::Vignette(_) => &[VIGNETTE_SHADER],
+            Effect::ColorGrading(_) => &[COLOR_GRADING_SHADER],
+            Effect::Shader(_) => &[],
+        }
+    }
+}
+
+struct Pass {
+    name: String,
+    effect: Effect,
+    enabled: bool,
+}
+
+/**
+A chain of named full screen passes applied to a rendered image, e.g. the color texture of
+the `FrameBuffer` a scene was rendered into
+
+Each pass reads the result of the previous one, intermediate results alternate between two
+half float targets of the output size and the last pass writes to the output. Passes run in
+the order they were added and can be disabled without removing them.
+*/
+pub struct PostProcess {
+    gl: Rc<gl::Gl>,
+    /// Programs by fragment shader, shared with custom passes for hot reloading
+    programs: BTreeMap<String, Rc<RefCell<ShaderProgram>>>,
+    passes: Vec<Pass>,
+    targets: [FrameBuffer; 2],
+    /// Bloom mip chain starting at half the output size, grown as bloom passes need it
+    bloom_levels: Vec<FrameBuffer>,
+    /// Empty, the vertex shader derives its full screen triangle from `gl_VertexID`
+    vertex_array: VertexArray,
+}
+
+impl PostProcess {
+    /// An empty chain for outputs of `width` x `height`, the shaders are read from `res`
+    pub fn new(gl: Rc<gl::Gl>, res: &Resources, width: u32, height: u32) -> Result<Self, Error> {
+        let target = || FrameBuffer::builder(Rc::clone(&gl), width.max(1), height.max(1))
+            .color_attachments(&[TARGET_FORMAT])
+            .build()
+            .map_err(|inner| Error::Targets { inner });
+        let targets = [target()?, target()?];
+
+        let mut post_process = PostProcess {
+            gl: Rc::clone(&gl),
+            programs: BTreeMap::new(),
+            passes: Vec::new(),
+            targets,
+            bloom_levels: Vec::new(),
+            vertex_array: VertexArray::new(gl),
+        };
+        post_process.load_shader(res, COPY_SHADER)?;
+        Ok(post_process)
+    }
+
+    /// Appends a pass, fails if there already is one named `name`
+    pub fn add(&mut self, res: &Resources, name: &str, effect: Effect) -> Result<(), Error> {
+        let index = self.passes.len();
+        self.insert(res, index, name, effect)
+    }
+
+    /// Inserts a pass right before the pass `before`
+    pub fn add_before(&mut self, res: &Resources, name: &str, before: &str, effect: Effect) -> Result<(), Error> {
+        let index = self.position(before).ok_or_else(|| Error::UnknownPass { name: before.into() })?;
+        self.insert(res, index, name, effect)
+    }
+
+    /**
+    Appends a custom pass drawn with the fragment shader `shader`.frag, see `load_shader`,
+    returning its material to set textures and uniforms
+    */
+    pub fn add_shader(&mut self, res: &Resources, name: &str, shader: &str) -> Result<&mut Material, Error> {
+        let program = self.load_shader(res, shader)?;
+        self.add(res, name, Effect::Shader(Material::new(program)))?;
+        match self.effect_mut(name) {
+            Some(Effect::Shader(material)) => Ok(material),
+            _ => unreachable!("Expected the pass that was just added to be a custom shader pass"),
+        }
+    }
+
+    /**
+    Builds the fragment shader `shader`.frag, e.g. "shaders/post/sepia", with the full screen
+    vertex shader, for the `Material` of an `Effect::Shader`
+
+    Programs are cached by `shader` and hot reloaded by `reload_if_changed`.
+    */
+    pub fn load_shader(&mut self, res: &Resources, shader: &str) -> Result<Rc<RefCell<ShaderProgram>>, Error> {
+        if let Some(program) = self.programs.get(shader) {
+            return Ok(Rc::clone(program));
+        }
+
+        let fragment = format!("{}.frag", shader);
+        let program = ShaderProgram::from_stage_resources(Rc::clone(&self.gl), res, shader, &[FULLSCREEN_SHADER, &fragment])
+            .map_err(|inner| Error::Shader { inner })?;
+        let program = Rc::new(RefCell::new(program));
+        self.programs.insert(shader.into(), Rc::clone(&program));
+        Ok(program)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Effect> {
+        self.position(name).map(|index| self.passes.remove(index).effect)
+    }
+
+    pub fn effect(&self, name: &str) -> Option<&Effect> {
+        self.passes.iter().find(|pass| pass.name == name).map(|pass| &pass.effect)
+    }
+
+    /// The effect of the pass `name`, e.g. to change its parameters
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut Effect> {
+        self.passes.iter_mut().find(|pass| pass.name == name).map(|pass| &mut pass.effect)
+    }
+
+    /// Enables or disables the pass `name`, returns false if there is no such pass
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.passes.iter_mut().find(|pass| pass.name == name) {
+            Some(pass) => {
+                pass.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.passes.iter().any(|pass| pass.name == name && pass.enabled)
+    }
+
+    /// Names of the passes in the order they run
+    pub fn pass_names(&self) -> impl Iterator<Item = &str> {
+        self.passes.iter().map(|pass| pass.name.as_str())
+    }
+
+    /// Reallocates the intermediate targets, `render` also does this when the output size changes
+    pub fn resize(&mut self, width: u32, height: u32) {
+        for target in self.targets.iter_mut() {
+            target.resize(width, height);
+        }
+        self.bloom_levels.clear();
+    }
+
+    /**
+    Rebuilds the programs whose sources changed, see `ShaderProgram::reload_if_changed`
+
+    Returns true if any program was replaced. Stops at the first program that fails to build,
+    the remaining ones are rebuilt by the next call.
+    */
+    pub fn reload_if_changed(&mut self, res: &Resources) -> Result<bool, Error> {
+        let mut reloaded = false;
+        for program in self.programs.values() {
+            reloaded |= program.borrow_mut().reload_if_changed(res).map_err(|inner| Error::Shader { inner })?;
+        }
+        Ok(reloaded)
+    }
+
+    /**
+    Runs the enabled passes on `input` and writes the result to the first color attachment of
+    `output`, which must not be the frame buffer of `input`
+
+    With no enabled passes `input` is copied. Depth testing and blending are disabled while
+    drawing, the bound frame buffer, viewport, depth test and blend state are kept, also when
+    a pass fails to set its uniforms. Uniforms missing from a shader are skipped.
+    */
+    pub fn render(&mut self, input: &Texture, output: &FrameBuffer) -> Result<(), shader::Error> {
+        let (width, height) = output.get_size();
+        if self.targets[0].get_size() != (width, height) {
+            self.resize(width, height);
+        }
+        let bloom_levels = self
+            .enabled_passes()
+            .filter_map(|pass| match &pass.effect {
+                Effect::Bloom(bloom) => Some(bloom.levels.max(1)),
+                _ => None,
+            })
+            .max();
+        if let Some(levels) = bloom_levels {
+            self.grow_bloom_levels(levels, width, height);
+        }
+
+        let mut frame_buffer: GLint = 0;
+        let mut viewport = [0 as GLint; 4];
+        let mut blend_func = [0 as GLint; 4];
+        let (depth_test, blend) = unsafe {
+            self.gl.GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut frame_buffer);
+            self.gl.GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            self.gl.GetIntegerv(gl::BLEND_SRC_RGB, &mut blend_func[0]);
+            self.gl.GetIntegerv(gl::BLEND_DST_RGB, &mut blend_func[1]);
+            self.gl.GetIntegerv(gl::BLEND_SRC_ALPHA, &mut blend_func[2]);
+            self.gl.GetIntegerv(gl::BLEND_DST_ALPHA, &mut blend_func[3]);
+            (self.gl.IsEnabled(gl::DEPTH_TEST) == gl::TRUE, self.gl.IsEnabled(gl::BLEND) == gl::TRUE)
+        };
+        unsafe {
+            self.gl.Disable(gl::DEPTH_TEST);
+            self.gl.Disable(gl::BLEND);
+        }
+        self.vertex_array.bind();
+
+        let passes: Vec<&Pass> = self.enabled_passes().collect();
+        let mut drawn = Ok(());
+        if passes.is_empty() {
+            drawn = self.draw(&self.program(COPY_SHADER), input, output);
+        }
+        let mut source = input;
+        for (index, pass) in passes.iter().enumerate() {
+            let target = if index + 1 == passes.len() { output } else { &self.targets[index % 2] };
+            drawn = self.run(&pass.effect, source, target);
+            if drawn.is_err() {
+                break;
+            }
+            source = color_texture(target);
+        }
+
+        self.vertex_array.unbind();
+        unsafe {
+            if depth_test {
+                self.gl.Enable(gl::DEPTH_TEST);
+            }
+            if blend {
+                self.gl.Enable(gl::BLEND);
+            }
+            self.gl.BlendFuncSeparate(blend_func[0] as GLenum, blend_func[1] as GLenum, blend_func[2] as GLenum, blend_func[3] as GLenum);
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, frame_buffer as GLuint);
+            self.gl.Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        drawn
+    }
+
+    fn insert(&mut self, res: &Resources, index: usize, name: &str, effect: Effect) -> Result<(), Error> {
+        if self.position(name).is_some() {
+            return Err(Error::DuplicatePass { name: name.into() });
+        }
+        for shader in effect.shaders() {
+            self.load_shader(res, shader)?;
+        }
+        self.passes.insert(index, Pass { name: name.into(), effect, enabled: true });
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.passes.iter().position(|pass| pass.name == name)
+    }
+
+    fn enabled_passes(&self) -> impl Iterator<Item = &Pass> {
+        self.passes.iter().filter(|pass| pass.enabled)
+    }
+
+    /// Borrows a built in program, which was loaded when its pass was added
+    fn program(&self, shader: &str) -> Ref<'_, ShaderProgram> {
+        self.programs[shader].borrow()
+    }
+
+    fn grow_bloom_levels(&mut self, levels: u32, width: u32, height: u32) {
+        for level in self.bloom_levels.len() as u32..levels {
+            let frame_buffer = FrameBuffer::builder(Rc::clone(&self.gl), (width >> (level + 1)).max(1), (height >> (level + 1)).max(1))
+                .color_attachments(&[TARGET_FORMAT])
+                .build()
+                .expect("Expected a half float color attachment to be a complete frame buffer");
+            self.bloom_levels.push(frame_buffer);
+        }
+    }
+
+    /// Draws `effect` with the result of the previous pass as `source`
+    fn run(&self, effect: &Effect, source: &Texture, target: &FrameBuffer) -> Result<(), shader::Error> {
+        match effect {
+            Effect::ToneMapping(tone_mapping) => {
+                let program = self.program(TONE_MAPPING_SHADER);
+                ignore_unknown_uniform(program.set_uniform("Exposure", &tone_mapping.exposure))?;
+                ignore_unknown_uniform(program.set_uniform("Operator", &(tone_mapping.operator as i32)))?;
+                self.draw(&program, source, target)
+            }
+            Effect::GammaCorrection(gamma) => {
+                let program = self.program(GAMMA_CORRECTION_SHADER);
+                ignore_unknown_uniform(program.set_uniform("Gamma", gamma))?;
+                self.draw(&program, source, target)
+            }
+            Effect::Fxaa => self.draw(&self.program(FXAA_SHADER), source, target),
+            Effect::Bloom(bloom) => self.bloom(bloom, source, target),
+            Effect::Vignette(vignette) => {
+                let program = self.program(VIGNETTE_SHADER);
+                ignore_unknown_uniform(program.set_uniform("Intensity", &vignette.intensity))?;
+                ignore_unknown_uniform(program.set_uniform("Radius", &vignette.radius))?;
+                ignore_unknown_uniform(program.set_uniform("Softness", &vignette.softness))?;
+                self.draw(&program, source, target)
+            }
+            Effect::ColorGrading(grading) => {
+                let program = self.program(COLOR_GRADING_SHADER);
+                ignore_unknown_uniform(program.bind_texture("Lut", grading.lut.as_ref()))?;
+                ignore_unknown_uniform(program.set_uniform("LutSize", &(grading.lut.get_size() as f32)))?;
+                ignore_unknown_uniform(program.set_uniform("Strength", &grading.strength))?;
+                self.draw(&program, source, target)
+            }
+            Effect::Shader(material) => {
+                ignore_unknown_uniform(material.apply())?;
+                self.draw(&material.program(), source, target)
+            }
+        }
+    }
+
+    /**
+    Downsamples `source` along the mip chain, keeping only bright light in the first level,
+    then adds every level onto the next larger one and composites the largest onto `source`
+    */
+    fn bloom(&self, bloom: &Bloom, source: &Texture, target: &FrameBuffer) -> Result<(), shader::Error> {
+        let levels = &self.bloom_levels[..(bloom.levels.max(1) as usize).min(self.bloom_levels.len())];
+
+        let downsample = self.program(BLOOM_DOWNSAMPLE_SHADER);
+        ignore_unknown_uniform(downsample.set_uniform("Threshold", &bloom.threshold))?;
+        ignore_unknown_uniform(downsample.set_uniform("Knee", &bloom.knee))?;
+        let mut input = source;
+        for (index, level) in levels.iter().enumerate() {
+            ignore_unknown_uniform(downsample.set_uniform("Prefilter", &((index == 0) as i32)))?;
+            self.draw(&downsample, input, level)?;
+            input = color_texture(level);
+        }
+
+        let upsample = self.program(BLOOM_UPSAMPLE_SHADER);
+        unsafe {
+            self.gl.Enable(gl::BLEND);
+            self.gl.BlendFunc(gl::ONE, gl::ONE);
+        }
+        let upsampled = levels.windows(2).rev().try_for_each(|pair| self.draw(&upsample, color_texture(&pair[1]), &pair[0]));
+        unsafe {
+            self.gl.Disable(gl::BLEND);
+        }
+        upsampled?;
+
+        let composite = self.program(BLOOM_COMPOSITE_SHADER);
+        ignore_unknown_uniform(composite.bind_texture("Bloom", color_texture(&levels[0])))?;
+        ignore_unknown_uniform(composite.set_uniform("Intensity", &bloom.intensity))?;
+        self.draw(&composite, source, target)
+    }
+
+    /// Draws the full screen triangle with `program` into `target`, sampling `input` as `Input`
+    fn draw(&self, program: &ShaderProgram, input: &Texture, target: &FrameBuffer) -> Result<(), shader::Error> {
+        let (width, height) = target.get_size();
+        let (input_width, input_height) = input.get_size();
+        target.bind();
+        program.bind();
+        ignore_unknown_uniform(program.bind_texture("Input", input))?;
+        ignore_unknown_uniform(program.set_uniform("TexelSize", &Vector2::new(1.0 / input_width as f32, 1.0 / input_height as f32)))?;
+        unsafe {
+            self.gl.Viewport(0, 0, width as GLsizei, height as GLsizei);
+            self.gl.DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        Ok(())
+    }
+}
+
+fn color_texture(frame_buffer: &FrameBuffer) -> &Texture {
+    frame_buffer
+        .get_texture()
+        .expect("Expected post processing targets to have a color texture")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use image::{ImageBuffer, Rgba, RgbaImage};
+    use nalgebra::Vector3;
+
+    use super::*;
+    use crate::renderer::test_support::{TempResources, FULLSCREEN_VERTEX_SOURCE};
+    use crate::renderer::{FilterMode, HeadlessContext, TextureDescriptor, TextureFormat};
+
+    fn assets() -> Resources {
+        Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"))
+    }
+
+    fn float_texture<F>(gl: &Rc<gl::Gl>, width: u32, height: u32, pixel: F) -> Texture
+    where F: Fn(u32, u32) -> [f32; 4], {
+        let image = ImageBuffer::from_fn(width, height, |x, y| Rgba(pixel(x, y)));
+        Texture::from_image_with_descriptor(Rc::clone(gl), &image, &TextureDescriptor::render_target(TextureFormat::Rgba16F, FilterMode::Nearest))
+    }
+
+    fn render(post_process: &mut PostProcess, input: &Texture) -> RgbaImage {
+        let (width, height) = input.get_size();
+        let output = FrameBuffer::builder(Rc::clone(&post_process.gl), width, height)
+            .color_attachments(&[ColorFormat::Rgba8])
+            .build()
+            .unwrap();
+        post_process.render(input, &output).unwrap();
+        output.read_rgba_image()
+    }
+
+    fn assert_close(actual: u8, expected: u8) {
+        assert!((actual as i32 - expected as i32).abs() <= 1, "{} differs from {}", actual, expected);
+    }
+
+    #[test]
+    fn skips_missing_uniforms_but_reports_mistyped_ones() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("post_process_uniforms");
+        temp.write(FULLSCREEN_SHADER, FULLSCREEN_VERTEX_SOURCE);
+        temp.write(format!("{}.frag", COPY_SHADER).as_str(), "#version 330 core\nin vec2 frag_uv;\nout vec4 out_color;\n\
+            uniform sampler2D Input;\nvoid main() { out_color = texture(Input, frag_uv); }\n");
+        // No `Exposure` and an `Operator` of the wrong type
+        temp.write(format!("{}.frag", TONE_MAPPING_SHADER).as_str(), "#version 330 core\nin vec2 frag_uv;\nout vec4 out_color;\n\
+            uniform sampler2D Input;\nuniform float Operator;\nvoid main() { out_color = texture(Input, frag_uv) * Operator; }\n");
+        let res = temp.resources();
+        let input = float_texture(&gl, 1, 1, |_, _| [1.0, 1.0, 1.0, 1.0]);
+        let mut post_process = PostProcess::new(Rc::clone(&gl), &res, 1, 1).unwrap();
+        assert_eq!(render(&mut post_process, &input).get_pixel(0, 0).0, [255, 255, 255, 255]);
+
+        post_process.add(&res, "tone_mapping", Effect::ToneMapping(ToneMapping::default())).unwrap();
+        let output = FrameBuffer::builder(Rc::clone(&gl), 1, 1).build().unwrap();
+        assert!(matches!(post_process.render(&input, &output),
+            Err(shader::Error::UniformTypeMismatch { ref name, .. }) if name == "Operator"));
+    }
+
+    #[test]
+    fn passes_run_in_order_and_can_be_disabled() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let res = assets();
+        let input = float_texture(&gl, 2, 2, |_, _| [1.0, 1.0, 1.0, 1.0]);
+        let mut post_process = PostProcess::new(Rc::clone(&gl), &res, 2, 2).unwrap();
+        assert_eq!(render(&mut post_process, &input).get_pixel(0, 0).0, [255; 4]);
+
+        let reinhard = ToneMapping { operator: ToneMapOperator::Reinhard, exposure: 1.0 };
+        post_process.add(&res, "tone_mapping", Effect::ToneMapping(reinhard)).unwrap();
+        assert_close(render(&mut post_process, &input).get_pixel(1, 1).0[0], 128);
+
+        // 0.5 ^ (1 / 2.2)
+        post_process.add(&res, "gamma", Effect::GammaCorrection(2.2)).unwrap();
+        assert_close(render(&mut post_process, &input).get_pixel(1, 1).0[0], 186);
+        post_process.set_enabled("tone_mapping", false);
+        assert_close(render(&mut post_process, &input).get_pixel(1, 1).0[0], 255);
+        assert!(!post_process.is_enabled("tone_mapping") && post_process.is_enabled("gamma"));
+
+        post_process.add_before(&res, "fxaa", "gamma", Effect::Fxaa).unwrap();
+        assert_eq!(post_process.pass_names().collect::<Vec<_>>(), ["tone_mapping", "fxaa", "gamma"]);
+        assert!(matches!(post_process.add(&res, "gamma", Effect::Fxaa), Err(Error::DuplicatePass { .. })));
+        assert!(matches!(post_process.add_before(&res, "aa", "missing", Effect::Fxaa), Err(Error::UnknownPass { .. })));
+        assert!(matches!(post_process.remove("gamma"), Some(Effect::GammaCorrection(_))));
+        assert!(post_process.effect("gamma").is_none() && !post_process.set_enabled("gamma", true));
+    }
+
+    #[test]
+    fn custom_shader_passes_read_the_previous_result() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("post_process");
+        temp.write(FULLSCREEN_SHADER, FULLSCREEN_VERTEX_SOURCE);
+        temp.write("invert.frag", "#version 330 core\nin vec2 frag_uv;\nout vec4 out_color;\n\
+            uniform sampler2D Input;\nuniform vec3 Tint;\nvoid main() {\n\
+            out_color = vec4((1.0 - texture(Input, frag_uv).rgb) * Tint, 1.0);\n}\n");
+        let custom = temp.resources();
+
+        let input = float_texture(&gl, 2, 2, |_, _| [0.25, 0.5, 1.0, 1.0]);
+        let mut post_process = PostProcess::new(Rc::clone(&gl), &assets(), 2, 2).unwrap();
+        post_process.add_shader(&custom, "invert", "invert").unwrap()
+            .set_uniform("Tint", Vector3::new(1.0, 1.0, 0.5))
+            .unwrap();
+        post_process.add(&assets(), "gamma", Effect::GammaCorrection(1.0)).unwrap();
+
+        let pixel = render(&mut post_process, &input).get_pixel(0, 0).0;
+        assert_close(pixel[0], 191);
+        assert_close(pixel[1], 128);
+        assert_eq!(pixel[2], 0);
+        // Loading the same shader again shares the program
+        let program = post_process.load_shader(&custom, "invert").unwrap();
+        assert!(matches!(post_process.effect("invert"), Some(Effect::Shader(material)) if Rc::ptr_eq(material.shared_program(), &program)));
+    }
+
+    #[test]
+    fn bloom_spreads_only_bright_light() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let res = assets();
+        let mut post_process = PostProcess::new(Rc::clone(&gl), &res, 16, 16).unwrap();
+        post_process.add(&res, "bloom", Effect::Bloom(Bloom { intensity: 1.0, ..Default::default() })).unwrap();
+
+        let bright = float_texture(&gl, 16, 16, |x, y| if (x, y) == (8, 8) { [64.0, 64.0, 64.0, 1.0] } else { [0.0, 0.0, 0.0, 1.0] });
+        let image = render(&mut post_process, &bright);
+        assert!(image.get_pixel(11, 7).0[0] > 0 && image.get_pixel(8, 11).0[0] > 0);
+        post_process.set_enabled("bloom", false);
+        assert_eq!(render(&mut post_process, &bright).get_pixel(11, 7).0[0], 0);
+
+        post_process.set_enabled("bloom", true);
+        let dim = float_texture(&gl, 16, 16, |_, _| [0.5, 0.5, 0.5, 1.0]);
+        for pixel in render(&mut post_process, &dim).pixels() {
+            assert_close(pixel.0[0], 128);
+        }
+    }
+
+    #[test]
+    fn grading_vignette_and_fxaa_change_colors_as_expected() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let res = assets();
+        let mut post_process = PostProcess::new(Rc::clone(&gl), &res, 8, 8).unwrap();
+        let input = float_texture(&gl, 8, 8, |_, _| [0.25, 0.5, 0.75, 1.0]);
+
+        let identity = Rc::new(ColorLut::identity(Rc::clone(&gl), 16));
+        post_process.add(&res, "grading", Effect::ColorGrading(ColorGrading::new(identity))).unwrap();
+        let pixel = render(&mut post_process, &input).get_pixel(3, 3).0;
+        for (channel, expected) in pixel.iter().zip([64, 128, 191].iter()) {
+            assert_close(*channel, *expected);
+        }
+        // Halfway to the inverted colors is grey
+        let invert = Rc::new(ColorLut::from_fn(Rc::clone(&gl), 16, |color| Vector3::repeat(1.0) - color));
+        if let Some(Effect::ColorGrading(grading)) = post_process.effect_mut("grading") {
+            *grading = ColorGrading { lut: invert, strength: 0.5 };
+        }
+        for channel in render(&mut post_process, &input).get_pixel(3, 3).0[..3].iter() {
+            assert_close(*channel, 128);
+        }
+        post_process.remove("grading");
+
+        post_process.add(&res, "vignette", Effect::Vignette(Vignette::default())).unwrap();
+        let white = float_texture(&gl, 8, 8, |_, _| [1.0; 4]);
+        let image = render(&mut post_process, &white);
+        assert_eq!(image.get_pixel(4, 4).0[0], 255);
+        assert!(image.get_pixel(0, 0).0[0] < 200);
+        post_process.remove("vignette");
+
+        // A hard diagonal edge gets blended with its neighbors along the edge
+        post_process.add(&res, "fxaa", Effect::Fxaa).unwrap();
+        let edge = float_texture(&gl, 8, 8, |x, y| if x > y { [1.0; 4] } else { [0.0, 0.0, 0.0, 1.0] });
+        let image = render(&mut post_process, &edge);
+        assert!(image.pixels().any(|pixel| pixel.0[0] > 0 && pixel.0[0] < 255));
+    }
+}
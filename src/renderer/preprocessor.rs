@@ -0,0 +1,187 @@
+#![allow(non_local_definitions)]
+
+use crate::resources;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Failed to load resource {}", name)]
+    ResourceLoad { name: String, #[cause] inner: resources::Error },
+    #[fail(display = "Include cycle: {}", chain)]
+    IncludeCycle { chain: String },
+    #[fail(display = "{}:{}: expected #include \"file\"", file, line)]
+    InvalidInclude { file: String, line: usize },
+}
+
+/// GLSL source with all includes resolved
+pub struct PreprocessedSource {
+    pub source: String,
+    /// All files the source was assembled from, indexed by their `#line` source string number
+    pub files: Vec<String>,
+}
+
+/**
+Resolves `#include "name"` directives of the shader source `name` and injects `defines`
+
+Included names are resource names, i.e. relative to the `Resources` root. `load` returns the
+text of a resource. Defines are inserted right after the `#version` directive of the root file.
+Every file gets its own source string number in the `#line` directives, so compiler messages
+can be mapped back with `map_info_log`. A file may be included several times, guard it with
+`#ifndef` if it defines functions.
+*/
+pub fn preprocess<F>(name: &str, defines: &[(String, String)], mut load: F) -> Result<PreprocessedSource, Error>
+where F: FnMut(&str) -> Result<String, resources::Error>, {
+    let mut output = PreprocessedSource { source: String::new(), files: Vec::new() };
+    let mut stack = Vec::new();
+    let text = load(name).map_err(|e| Error::ResourceLoad { name: name.into(), inner: e })?;
+    append_file(name, &text, Some(defines), &mut load, &mut stack, &mut output)?;
+    Ok(output)
+}
+
+fn append_file<F>(
+    name: &str,
+    text: &str,
+    defines: Option<&[(String, String)]>,
+    load: &mut F,
+    stack: &mut Vec<String>,
+    output: &mut PreprocessedSource,
+) -> Result<(), Error>
+where F: FnMut(&str) -> Result<String, resources::Error>, {
+    stack.push(name.into());
+    let file_index = output.files.len();
+    output.files.push(name.into());
+
+    let lines: Vec<&str> = text.lines().collect();
+    // Only the root file gets defines, after its #version which has to come first
+    let mut first_line = 0;
+    if let Some(defines) = defines {
+        if let Some(version) = lines.iter().position(|line| line.trim_start().starts_with("#version")) {
+            for line in &lines[..=version] {
+                output.source.push_str(line);
+                output.source.push('\n');
+            }
+            first_line = version + 1;
+        }
+        for (define, value) in defines {
+            output.source.push_str(&format!("#define {} {}\n", define, value));
+        }
+    }
+    output.source.push_str(&format!("#line {} {}\n", first_line + 1, file_index));
+
+    for (index, line) in lines.iter().enumerate().skip(first_line) {
+        let line_number = index + 1;
+        let directive = line.trim_start();
+        if !directive.starts_with("#include") {
+            output.source.push_str(line);
+            output.source.push('\n');
+            continue;
+        }
+
+        let include = parse_include(directive).ok_or_else(|| Error::InvalidInclude {
+            file: name.into(),
+            line: line_number,
+        })?;
+        if stack.iter().any(|file| file == include) {
+            stack.push(include.into());
+            return Err(Error::IncludeCycle { chain: stack.join(" -> ") });
+        }
+
+        let included = load(include).map_err(|e| Error::ResourceLoad { name: include.into(), inner: e })?;
+        append_file(include, &included, None, load, stack, output)?;
+        output.source.push_str(&format!("#line {} {}\n", line_number + 1, file_index));
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+/// Extracts the name of `#include "name"`, trailing `//` comments are allowed
+fn parse_include(directive: &str) -> Option<&str> {
+    let rest = directive["#include".len()..].trim_start().strip_prefix('"')?;
+    let end = rest.find('"')?;
+    let trailing = rest[end + 1..].trim();
+    if end == 0 || !(trailing.is_empty() || trailing.starts_with("//")) {
+        return None;
+    }
+    Some(&rest[..end])
+}
+
+/**
+Replaces source string numbers at the start of info log lines, e.g. "1:12(3): error"
+(Mesa) or "1(12) : error" (NVIDIA), with the name of the file
+*/
+pub fn map_info_log(log: &str, files: &[String]) -> String {
+    log.lines()
+        .map(|line| {
+            let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+            let file = line[..digits].parse::<usize>().ok().and_then(|index| files.get(index));
+            match (file, line[digits..].chars().next()) {
+                (Some(file), Some(':')) | (Some(file), Some('(')) => format!("{}{}", file, &line[digits..]),
+                _ => line.to_owned(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+
+    use super::*;
+
+    fn loader<'a>(files: &'a HashMap<&str, &str>) -> impl FnMut(&str) -> Result<String, resources::Error> + 'a {
+        move |name| {
+            files
+                .get(name)
+                .map(|text| text.to_string())
+                .ok_or_else(|| resources::Error::Io(io::Error::new(io::ErrorKind::NotFound, name.to_owned())))
+        }
+    }
+
+    #[test]
+    fn resolves_includes_with_line_directives_and_defines() {
+        let files: HashMap<&str, &str> = [
+            ("main.frag", "#version 330 core\n#include \"common/util.glsl\"\nvoid main() {}\n"),
+            ("common/util.glsl", "float twice(float x) { return 2.0 * x; }\n"),
+        ].iter().cloned().collect();
+        let defines = vec![("LIGHTS".to_owned(), "4".to_owned())];
+
+        let output = preprocess("main.frag", &defines, loader(&files)).unwrap();
+        assert_eq!(output.files, vec!["main.frag", "common/util.glsl"]);
+        assert_eq!(output.source, "#version 330 core\n#define LIGHTS 4\n#line 2 0\n\
+            #line 1 1\nfloat twice(float x) { return 2.0 * x; }\n#line 3 0\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn detects_include_cycles() {
+        let files: HashMap<&str, &str> = [
+            ("main.frag", "#version 330 core\n#include \"a.glsl\"\n"),
+            ("a.glsl", "#include \"b.glsl\"\n"),
+            ("b.glsl", "#include \"a.glsl\"\n"),
+        ].iter().cloned().collect();
+
+        match preprocess("main.frag", &[], loader(&files)) {
+            Err(Error::IncludeCycle { chain }) => assert_eq!(chain, "main.frag -> a.glsl -> b.glsl -> a.glsl"),
+            _ => panic!("Expected include cycle to be detected"),
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_includes() {
+        let files: HashMap<&str, &str> = [("main.frag", "\n#include <util.glsl>\n")].iter().cloned().collect();
+        match preprocess("main.frag", &[], loader(&files)) {
+            Err(Error::InvalidInclude { file, line }) => assert_eq!((file.as_str(), line), ("main.frag", 2)),
+            _ => panic!("Expected malformed include to be rejected"),
+        }
+    }
+
+    #[test]
+    fn maps_info_log_to_file_names() {
+        let files = vec!["main.frag".to_owned(), "common/util.glsl".to_owned()];
+        let log = "1:4(10): error: syntax error\n0(2) : error C0000: oops\nno location";
+        assert_eq!(
+            map_info_log(log, &files),
+            "common/util.glsl:4(10): error: syntax error\nmain.frag(2) : error C0000: oops\nno location");
+    }
+}
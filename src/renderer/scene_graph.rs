@@ -0,0 +1,274 @@
+use std::rc::Rc;
+
+use nalgebra::{Isometry3, Matrix4, Translation3, UnitQuaternion, Vector3};
+
+use super::data::VertexAttribPointers;
+use super::shader::{Error, ShaderProgram};
+use super::Mesh;
+
+/// Something the scene graph can draw with the currently bound program
+pub trait Drawable {
+    fn draw(&self);
+}
+
+impl<V> Drawable for Mesh<V>
+where V: VertexAttribPointers, {
+    fn draw(&self) {
+        Mesh::draw(self)
+    }
+}
+
+/// Handle to a node of a `SceneGraph`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/**
+A node with a transform relative to its parent and an optional drawable
+*/
+pub struct Node {
+    pub name: String,
+    translation: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+    scale: Vector3<f32>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    drawable: Option<Rc<dyn Drawable>>,
+    /// The local transform changed since the world transform was last computed
+    dirty: bool,
+    world_transform: Matrix4<f32>,
+}
+
+impl Node {
+    pub fn translation(&self) -> &Vector3<f32> {
+        &self.translation
+    }
+
+    pub fn rotation(&self) -> &UnitQuaternion<f32> {
+        &self.rotation
+    }
+
+    pub fn scale(&self) -> &Vector3<f32> {
+        &self.scale
+    }
+
+    pub fn set_translation(&mut self, translation: Vector3<f32>) {
+        self.translation = translation;
+        self.dirty = true;
+    }
+
+    pub fn set_rotation(&mut self, rotation: UnitQuaternion<f32>) {
+        self.rotation = rotation;
+        self.dirty = true;
+    }
+
+    pub fn set_scale(&mut self, scale: Vector3<f32>) {
+        self.scale = scale;
+        self.dirty = true;
+    }
+
+    pub fn set_drawable(&mut self, drawable: Option<Rc<dyn Drawable>>) {
+        self.drawable = drawable;
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    /// Translation * Rotation * Scale
+    pub fn local_transform(&self) -> Matrix4<f32> {
+        let isometry = Isometry3::from_parts(Translation3::from(self.translation), self.rotation);
+        isometry.to_homogeneous() * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+/**
+Hierarchy of transformed nodes
+
+World transforms are cached and only recomputed for nodes whose local transform, or the
+transform of one of their ancestors, changed since the last `update_world_transforms`.
+*/
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        SceneGraph::default()
+    }
+
+    /**
+    Adds a node with an identity transform as the last child of `parent`, or as a root
+    */
+    pub fn add_node(&mut self, name: &str, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name: name.into(),
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            parent: None,
+            children: Vec::new(),
+            drawable: None,
+            dirty: true,
+            world_transform: Matrix4::identity(),
+        });
+        self.roots.push(id);
+        self.set_parent(id, parent);
+        id
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0]
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /**
+    Moves `id` with its subtree below `parent`, or to the roots, keeping its local transform
+
+    Panics if `parent` is `id` or one of its descendants.
+    */
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let mut ancestor = parent;
+        while let Some(node) = ancestor {
+            assert!(node != id, "Expected new parent of node {} not to be part of its subtree", self.nodes[id.0].name);
+            ancestor = self.nodes[node.0].parent;
+        }
+
+        match self.nodes[id.0].parent {
+            Some(old_parent) => self.nodes[old_parent.0].children.retain(|&child| child != id),
+            None => self.roots.retain(|&root| root != id),
+        }
+        match parent {
+            Some(new_parent) => self.nodes[new_parent.0].children.push(id),
+            None => self.roots.push(id),
+        }
+
+        let node = &mut self.nodes[id.0];
+        node.parent = parent;
+        node.dirty = true;
+    }
+
+    /**
+    World transform of `id` as of the last `update_world_transforms`
+    */
+    pub fn world_transform(&self, id: NodeId) -> &Matrix4<f32> {
+        &self.nodes[id.0].world_transform
+    }
+
+    /**
+    Recomputes the cached world transforms of all dirty nodes and their descendants
+    */
+    pub fn update_world_transforms(&mut self) {
+        // (node, parent world transform if it changed this update)
+        let mut stack: Vec<(NodeId, Option<Matrix4<f32>>)> = self.roots.iter().map(|&root| (root, None)).collect();
+        while let Some((id, changed_parent)) = stack.pop() {
+            let parent_world = match (changed_parent, self.nodes[id.0].parent) {
+                (Some(parent_world), _) => Some(parent_world),
+                (None, Some(parent)) if self.nodes[id.0].dirty => Some(self.nodes[parent.0].world_transform),
+                (None, None) if self.nodes[id.0].dirty => Some(Matrix4::identity()),
+                _ => None,
+            };
+
+            let node = &mut self.nodes[id.0];
+            let changed = parent_world.map(|parent_world| {
+                node.world_transform = parent_world * node.local_transform();
+                node.dirty = false;
+                node.world_transform
+            });
+            stack.extend(node.children.iter().map(|&child| (child, changed)));
+        }
+    }
+
+    /**
+    Updates the world transforms and draws every node with a drawable, setting the
+    `Model` uniform of `program` to the node's world transform
+
+    `program` must be bound by the caller and have an active `mat4 Model` uniform.
+    */
+    pub fn render(&mut self, program: &ShaderProgram) -> Result<(), Error> {
+        self.update_world_transforms();
+        for node in &self.nodes {
+            if let Some(drawable) = &node.drawable {
+                program.set_uniform("Model", &node.world_transform)?;
+                drawable.draw();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin(graph: &SceneGraph, id: NodeId) -> Vector3<f32> {
+        graph.world_transform(id).transform_point(&nalgebra::Point3::origin()).coords
+    }
+
+    #[test]
+    fn world_transforms_follow_parents() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add_node("root", None);
+        let child = graph.add_node("child", Some(root));
+        let grandchild = graph.add_node("grandchild", Some(child));
+
+        graph.node_mut(root).set_translation(Vector3::new(1.0, 0.0, 0.0));
+        graph.node_mut(root).set_scale(Vector3::new(2.0, 2.0, 2.0));
+        graph.node_mut(child).set_rotation(UnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2));
+        graph.node_mut(grandchild).set_translation(Vector3::new(1.0, 0.0, 0.0));
+        graph.update_world_transforms();
+
+        // Rotated to +Y, scaled by 2, then moved by the root
+        assert!((origin(&graph, grandchild) - Vector3::new(1.0, 2.0, 0.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn only_dirty_subtrees_are_recomputed() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add_node("root", None);
+        let child = graph.add_node("child", Some(root));
+        let other = graph.add_node("other", None);
+        graph.update_world_transforms();
+        assert!(graph.nodes.iter().all(|node| !node.dirty));
+
+        // A stale cache that only survives if clean nodes are skipped
+        let stale = Matrix4::new_translation(&Vector3::new(0.0, 0.0, 7.0));
+        graph.nodes[other.0].world_transform = stale;
+
+        graph.node_mut(root).set_translation(Vector3::new(0.0, 3.0, 0.0));
+        // Not visible until the next update
+        assert_eq!(origin(&graph, child), Vector3::zeros());
+        graph.update_world_transforms();
+        assert_eq!(origin(&graph, child), Vector3::new(0.0, 3.0, 0.0));
+        assert_eq!(graph.world_transform(other), &stale);
+        // Children of the clean node read its cached transform when reparented
+        graph.nodes[other.0].world_transform = Matrix4::identity();
+
+        graph.set_parent(child, Some(other));
+        graph.update_world_transforms();
+        assert_eq!(origin(&graph, child), Vector3::zeros());
+        assert_eq!(graph.roots(), &[root, other]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_parent_cycles() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add_node("root", None);
+        let child = graph.add_node("child", Some(root));
+        graph.set_parent(root, Some(child));
+    }
+}
@@ -1,41 +1,169 @@
+#![allow(non_local_definitions)]
+
 use gl::types::*;
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::ptr::*;
 use std::rc::Rc;
+use std::time::SystemTime;
 
 use crate::resources;
 use crate::resources::Resources;
 use nalgebra::{Matrix4};
 
+use super::preprocessor;
+use super::texture::{SampledTexture, Sampler};
+use super::uniform::{self, TextureUnit, UniformInfo, UniformValue};
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Fail)]
 pub enum Error {
     #[fail(display = "Failed to load resource {}", name)]
     ResourceLoad { name: String, #[cause] inner: resources::Error },
+    #[fail(display = "Failed to preprocess shader {}", name)]
+    Preprocess { name: String, #[cause] inner: preprocessor::Error },
     #[fail(display = "Can not determine shader type for resource {}", name)]
     CanNotDetermineShaderTypeForResource { name: String },
     #[fail(display = "Failed to compile shader {}: {}", name, message)]
     CompileError { name: String, message: String },
     #[fail(display = "Failed to link program {}: {}", name, message)]
     LinkError { name: String, message: String },
+    #[fail(display = "Shader program {} has no vertex shader {}.vert", name, name)]
+    MissingVertexShader { name: String },
+    #[fail(display = "Program {} has no active uniform {}", program, name)]
+    UnknownUniform { program: String, name: String },
+    #[fail(display = "Uniform {} of program {} is a {}, which can not be set from {}", name, program, expected, found)]
+    UniformTypeMismatch { program: String, name: String, expected: &'static str, found: &'static str },
+    #[fail(display = "Program {} has no active uniform block {}", program, name)]
+    UnknownUniformBlock { program: String, name: String },
 }
 
+/// File extensions of shader stages in pipeline order
+const SHADER_STAGES: [(&str, GLenum); 6] = [
+    (".vert", gl::VERTEX_SHADER),
+    (".tesc", gl::TESS_CONTROL_SHADER),
+    (".tese", gl::TESS_EVALUATION_SHADER),
+    (".geom", gl::GEOMETRY_SHADER),
+    (".frag", gl::FRAGMENT_SHADER),
+    (".comp", gl::COMPUTE_SHADER),
+];
+
 pub struct ShaderProgram {
     gl: Rc<gl::Gl>,
     id: GLuint,
+    name: String,
+    /// Resource names of the shader stages
+    stages: Vec<String>,
+    /// Defines injected into every stage
+    defines: Vec<(String, String)>,
+    /// Stages and the files they include with their modification time when last compiled
+    sources: Vec<(String, Option<SystemTime>)>,
+    /// Active uniforms outside of uniform blocks, queried at link time
+    uniforms: HashMap<String, UniformInfo>,
+    /// First texture unit of every sampler uniform, assigned at link time
+    texture_units: HashMap<String, TextureUnit>,
 }
 
 impl ShaderProgram {
+    /**
+    Builds a graphics program from all stage files named `name` with a known extension,
+    e.g. shaders/test.vert, shaders/test.geom and shaders/test.frag for "shaders/test"
+
+    A vertex shader is required, all other graphics stages are optional.
+    Compute shaders are loaded through `ComputeProgram` instead.
+    */
     pub fn from_resource(gl: Rc<gl::Gl>, res: &Resources, name: &str) -> Result<Self, Error> {
-        const POSSIBLE_EXT: [&str; 2] = [".vert", ".frag"];
+        Self::from_resource_with_defines(gl, res, name, &[])
+    }
 
-        let shaders = POSSIBLE_EXT
+    /**
+    Like `from_resource`, with `#define NAME VALUE` lines for `defines` injected into every stage
+    */
+    pub fn from_resource_with_defines(gl: Rc<gl::Gl>, res: &Resources, name: &str, defines: &[(&str, &str)]) -> Result<Self, Error> {
+        let defines: Vec<(String, String)> = defines
             .iter()
-            .map(|file_extension| {
-                Shader::from_resource(Rc::clone(&gl), res, &format!("{}{}", name, file_extension))
-            })
+            .map(|&(define, value)| (define.to_owned(), value.to_owned()))
+            .collect();
+        let source_names: Vec<String> = SHADER_STAGES
+            .iter()
+            .filter(|&&(_, shader_type)| shader_type != gl::COMPUTE_SHADER)
+            .map(|(file_extension, _)| format!("{}{}", name, file_extension))
+            .filter(|source_name| res.exists(source_name))
+            .collect();
+
+        if !source_names.iter().any(|source_name| source_name.ends_with(".vert")) {
+            return Err(Error::MissingVertexShader { name: name.into() });
+        }
+
+        Self::from_stages(gl, res, name, source_names, defines)
+    }
+
+    /**
+    Builds a graphics program from explicitly named stage files, e.g. a shared vertex shader
+    with one of several fragment shaders, the stage of each file is taken from its extension
+
+    `name` is only used in error messages. A vertex shader is required.
+    */
+    pub fn from_stage_resources(gl: Rc<gl::Gl>, res: &Resources, name: &str, stages: &[&str]) -> Result<Self, Error> {
+        if !stages.iter().any(|source_name| source_name.ends_with(".vert")) {
+            return Err(Error::MissingVertexShader { name: name.into() });
+        }
+
+        let stages = stages.iter().map(|&source_name| source_name.to_owned()).collect();
+        Self::from_stages(gl, res, name, stages, Vec::new())
+    }
+
+    /**
+    Compiles and links the given stage resources, remembering them for `reload_if_changed`
+    */
+    fn from_stages(gl: Rc<gl::Gl>, res: &Resources, name: &str, stages: Vec<String>, defines: Vec<(String, String)>) -> Result<Self, Error> {
+        let shaders = stages
+            .iter()
+            .map(|source_name| Shader::from_resource(Rc::clone(&gl), res, source_name, &defines))
             .collect::<Result<Vec<Shader>, Error>>()?;
 
-        Self::from_shaders(Rc::clone(&gl), &shaders[..], name)
+        let mut sources: Vec<(String, Option<SystemTime>)> = Vec::new();
+        for file in shaders.iter().flat_map(|shader| shader.files.iter()) {
+            if !sources.iter().any(|(source_name, _)| source_name == file) {
+                sources.push((file.clone(), res.modified_time(file)));
+            }
+        }
+
+        let mut program = Self::from_shaders(Rc::clone(&gl), &shaders[..], name)?;
+        program.stages = stages;
+        program.defines = defines;
+        program.sources = sources;
+        Ok(program)
+    }
+
+    /**
+    Recompiles and relinks the program if any of its source files, including the files they
+    `#include`, changed since it was built
+
+    Returns true if the program was replaced. On compile or link errors the error is returned
+    and the last working program is kept, so uniform locations must be queried again only
+    after a successful reload.
+    */
+    pub fn reload_if_changed(&mut self, res: &Resources) -> Result<bool, Error> {
+        let changed = self
+            .sources
+            .iter()
+            .any(|(source_name, modified)| res.modified_time(source_name) != *modified);
+        if !changed {
+            return Ok(false);
+        }
+
+        // Remember the new times even on failure, to only retry after the next edit
+        for (source_name, modified) in self.sources.iter_mut() {
+            *modified = res.modified_time(source_name);
+        }
+
+        let mut program = Self::from_stages(Rc::clone(&self.gl), res, &self.name, self.stages.clone(), self.defines.clone())?;
+        std::mem::swap(&mut self.id, &mut program.id);
+        std::mem::swap(&mut self.sources, &mut program.sources);
+        std::mem::swap(&mut self.uniforms, &mut program.uniforms);
+        std::mem::swap(&mut self.texture_units, &mut program.texture_units);
+        Ok(true)
     }
 
     fn from_shaders(gl: Rc<gl::Gl>, shaders: &[Shader], name: &str) -> Result<Self, Error> {
@@ -60,12 +188,26 @@ impl ShaderProgram {
 
             let error = create_blank_cstring(len as usize);
 
-            unsafe { gl.GetProgramInfoLog(id, len, null_mut(), error.as_ptr() as *mut GLchar) }
+            unsafe {
+                gl.GetProgramInfoLog(id, len, null_mut(), error.as_ptr() as *mut GLchar);
+                gl.DeleteProgram(id);
+            }
 
             return Err(Error::LinkError{name: name.into(), message: error.to_string_lossy().into_owned()});
         }
 
-        Ok(Self { gl, id })
+        let uniforms = query_active_uniforms(&gl, id);
+        let texture_units = assign_texture_units(&gl, id, &uniforms);
+        Ok(Self {
+            gl,
+            id,
+            name: name.into(),
+            stages: Vec::new(),
+            defines: Vec::new(),
+            sources: Vec::new(),
+            uniforms,
+            texture_units,
+        })
     }
 
     pub fn bind(&self) {
@@ -73,6 +215,11 @@ impl ShaderProgram {
     }
 
     pub fn get_uniform_location(&self, name: &str) -> Option<GLint> {
+        if let Some(info) = self.uniforms.get(name) {
+            return Some(info.location);
+        }
+
+        // Individual array elements, e.g. "lights[2]", are not part of the reflection cache
         let cname = CString::new(name).expect("Expected uniform name to have no null bytes");
 
         let location = unsafe {
@@ -84,6 +231,135 @@ impl ShaderProgram {
         Some(location)
     }
 
+    pub fn get_uniform_info(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.get(name)
+    }
+
+    /// All active uniforms of the program by name
+    pub fn uniforms(&self) -> &HashMap<String, UniformInfo> {
+        &self.uniforms
+    }
+
+    /**
+    Sets the uniform `name` to `value`, the program does not need to be bound
+
+    Fails if the program has no such active uniform or if its GLSL type does not match `T`.
+    Arrays are set starting at their first element.
+    */
+    pub fn set_uniform<T>(&self, name: &str, value: &T) -> Result<(), Error>
+    where T: UniformValue, {
+        let info = self.check_uniform::<T>(name)?;
+        value.set(&self.gl, self.id, info.location);
+        Ok(())
+    }
+
+    /**
+    Checks that `set_uniform` can assign a `T` to the uniform `name`, without setting it
+    */
+    pub fn check_uniform<T>(&self, name: &str) -> Result<&UniformInfo, Error>
+    where T: UniformValue, {
+        let info = self.uniforms.get(name).ok_or_else(|| Error::UnknownUniform {
+            program: self.name.clone(),
+            name: name.into(),
+        })?;
+
+        if !T::GL_TYPES.contains(&info.gl_type) {
+            return Err(Error::UniformTypeMismatch {
+                program: self.name.clone(),
+                name: name.into(),
+                expected: uniform::glsl_type_name(info.gl_type),
+                found: std::any::type_name::<T>(),
+            });
+        }
+
+        Ok(info)
+    }
+
+    /**
+    The texture unit assigned to the sampler uniform `name` at link time
+
+    Samplers are numbered from unit 0 in order of their names, sampler arrays take one unit per
+    element. Explicit `layout(binding = n)` qualifiers in the shader are overridden.
+    */
+    pub fn texture_unit(&self, name: &str) -> Option<TextureUnit> {
+        self.texture_units.get(name).copied()
+    }
+
+    /**
+    Binds `texture` to the texture unit of the sampler uniform `name`, returning the unit
+
+    Fails like `check_texture` if the sampler can not sample `texture`.
+    */
+    pub fn bind_texture<T>(&self, name: &str, texture: &T) -> Result<TextureUnit, Error>
+    where T: SampledTexture + ?Sized, {
+        let unit = self.check_texture(name, texture)?;
+        texture.bind_to_unit(unit.0);
+        Ok(unit)
+    }
+
+    /**
+    Like `bind_texture`, sampling `texture` with the filters and wrap modes of `sampler`
+    instead of its own
+    */
+    pub fn bind_texture_with_sampler<T>(&self, name: &str, texture: &T, sampler: &Sampler) -> Result<TextureUnit, Error>
+    where T: SampledTexture + ?Sized, {
+        let unit = self.bind_texture(name, texture)?;
+        sampler.bind_to_unit(unit.0);
+        Ok(unit)
+    }
+
+    /**
+    The texture unit `bind_texture` would bind `texture` to, without binding it
+
+    Fails like `set_uniform` if there is no such uniform, or if it is not a sampler of the
+    texture's target, e.g. a `sampler2D` for a `TextureCube`.
+    */
+    pub fn check_texture<T>(&self, name: &str, texture: &T) -> Result<TextureUnit, Error>
+    where T: SampledTexture + ?Sized, {
+        let info = self.uniforms.get(name);
+        if let (Some(unit), Some(info)) = (self.texture_units.get(name), info) {
+            if uniform::sampler_target(info.gl_type) == Some(texture.target()) {
+                return Ok(*unit);
+            }
+        }
+
+        match info {
+            Some(info) => Err(Error::UniformTypeMismatch {
+                program: self.name.clone(),
+                name: name.into(),
+                expected: uniform::glsl_type_name(info.gl_type),
+                found: std::any::type_name::<T>(),
+            }),
+            None => Err(Error::UnknownUniform { program: self.name.clone(), name: name.into() }),
+        }
+    }
+
+    /**
+    Reads the uniform block `name` from the uniform buffer bound to `binding`,
+    see `VertexBuffer::bind_base`
+
+    see: glUniformBlockBinding
+    */
+    pub fn bind_uniform_block(&self, name: &str, binding: u32) -> Result<(), Error> {
+        let index = self
+            .uniform_block_index(name)
+            .ok_or_else(|| Error::UnknownUniformBlock { program: self.name.clone(), name: name.into() })?;
+
+        unsafe { self.gl.UniformBlockBinding(self.id, index, binding) }
+        Ok(())
+    }
+
+    /// Whether the program has an active uniform block `name`, e.g. to tell lit from unlit shaders
+    pub fn has_uniform_block(&self, name: &str) -> bool {
+        self.uniform_block_index(name).is_some()
+    }
+
+    fn uniform_block_index(&self, name: &str) -> Option<GLuint> {
+        let cname = CString::new(name).expect("Expected uniform block name to have no null bytes");
+        let index = unsafe { self.gl.GetUniformBlockIndex(self.id, cname.as_ptr()) };
+        Some(index).filter(|&index| index != gl::INVALID_INDEX)
+    }
+
     pub fn bind_uniform_mat4(&self, location: GLint, matrix: &Matrix4<f32>) {
         unsafe {
             self.gl.UniformMatrix4fv(location, 1, gl::FALSE, matrix.as_ptr());
@@ -97,30 +373,169 @@ impl Drop for ShaderProgram {
     }
 }
 
+/**
+A program with a single compute shader stage
+*/
+pub struct ComputeProgram {
+    program: ShaderProgram,
+    local_size: [u32; 3],
+}
+
+impl ComputeProgram {
+    /**
+    Builds a compute program from the resource `name`.comp
+    */
+    pub fn from_resource(gl: Rc<gl::Gl>, res: &Resources, name: &str) -> Result<Self, Error> {
+        Self::from_resource_with_defines(gl, res, name, &[])
+    }
+
+    /**
+    Like `from_resource`, with `#define NAME VALUE` lines for `defines` injected into the shader
+    */
+    pub fn from_resource_with_defines(gl: Rc<gl::Gl>, res: &Resources, name: &str, defines: &[(&str, &str)]) -> Result<Self, Error> {
+        let defines = defines
+            .iter()
+            .map(|&(define, value)| (define.to_owned(), value.to_owned()))
+            .collect();
+        let program = ShaderProgram::from_stages(gl, res, name, vec![format!("{}.comp", name)], defines)?;
+        let local_size = query_local_size(&program);
+        Ok(Self { program, local_size })
+    }
+
+    /**
+    Recompiles the compute shader if its source file changed, see `ShaderProgram::reload_if_changed`
+    */
+    pub fn reload_if_changed(&mut self, res: &Resources) -> Result<bool, Error> {
+        let reloaded = self.program.reload_if_changed(res)?;
+        if reloaded {
+            self.local_size = query_local_size(&self.program);
+        }
+        Ok(reloaded)
+    }
+
+    /**
+    Binds the program and launches `x` * `y` * `z` work groups
+
+    Results written to buffers or images are only guaranteed to be visible to later
+    commands after a matching `memory_barrier`.
+
+    see: glDispatchCompute
+    */
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        self.program.bind();
+        unsafe { self.program.gl.DispatchCompute(x, y, z) }
+    }
+
+    /// Work group size declared by the shader's `layout(local_size_x = ...) in;`
+    pub fn local_size(&self) -> [u32; 3] {
+        self.local_size
+    }
+
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    pub fn set_uniform<T>(&self, name: &str, value: &T) -> Result<(), Error>
+    where T: UniformValue, {
+        self.program.set_uniform(name, value)
+    }
+}
+
+fn query_local_size(program: &ShaderProgram) -> [u32; 3] {
+    let mut local_size: [GLint; 3] = [0; 3];
+    unsafe { program.gl.GetProgramiv(program.id, gl::COMPUTE_WORK_GROUP_SIZE, local_size.as_mut_ptr()) }
+    [local_size[0] as u32, local_size[1] as u32, local_size[2] as u32]
+}
+
+/**
+Kinds of incoherent memory accesses to make visible, combine with `|`
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryBarrier(pub GLbitfield);
+
+impl MemoryBarrier {
+    pub const VERTEX_ATTRIB_ARRAY: MemoryBarrier = MemoryBarrier(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    pub const ELEMENT_ARRAY: MemoryBarrier = MemoryBarrier(gl::ELEMENT_ARRAY_BARRIER_BIT);
+    pub const UNIFORM: MemoryBarrier = MemoryBarrier(gl::UNIFORM_BARRIER_BIT);
+    pub const TEXTURE_FETCH: MemoryBarrier = MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT);
+    pub const SHADER_IMAGE_ACCESS: MemoryBarrier = MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    pub const COMMAND: MemoryBarrier = MemoryBarrier(gl::COMMAND_BARRIER_BIT);
+    pub const PIXEL_BUFFER: MemoryBarrier = MemoryBarrier(gl::PIXEL_BUFFER_BARRIER_BIT);
+    pub const TEXTURE_UPDATE: MemoryBarrier = MemoryBarrier(gl::TEXTURE_UPDATE_BARRIER_BIT);
+    pub const BUFFER_UPDATE: MemoryBarrier = MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+    pub const FRAMEBUFFER: MemoryBarrier = MemoryBarrier(gl::FRAMEBUFFER_BARRIER_BIT);
+    pub const SHADER_STORAGE: MemoryBarrier = MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+    pub const ALL: MemoryBarrier = MemoryBarrier(gl::ALL_BARRIER_BITS);
+}
+
+impl std::ops::BitOr for MemoryBarrier {
+    type Output = MemoryBarrier;
+
+    fn bitor(self, other: MemoryBarrier) -> MemoryBarrier {
+        MemoryBarrier(self.0 | other.0)
+    }
+}
+
+/**
+Orders shader writes before later commands reading the memory in the ways given by `barriers`
+
+see: glMemoryBarrier
+*/
+pub fn memory_barrier(gl: &gl::Gl, barriers: MemoryBarrier) {
+    unsafe { gl.MemoryBarrier(barriers.0) }
+}
+
+/**
+Like `memory_barrier`, but only for reads by fragment shaders of the current draw call's
+framebuffer region
+
+see: glMemoryBarrierByRegion
+*/
+pub fn memory_barrier_by_region(gl: &gl::Gl, barriers: MemoryBarrier) {
+    unsafe { gl.MemoryBarrierByRegion(barriers.0) }
+}
+
+/**
+Treats `UnknownUniform` errors as success and passes on any other error
+
+Meant for setting uniforms while rendering, where a shader edit may remove a uniform while hot
+reloading, but a uniform of the wrong type or a missing uniform block is still a bug.
+*/
+pub fn ignore_unknown_uniform<T>(result: Result<T, Error>) -> Result<(), Error> {
+    match result {
+        Ok(_) | Err(Error::UnknownUniform { .. }) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
 struct Shader {
     gl: Rc<gl::Gl>,
     id: GLuint,
+    /// The shader source followed by all files it includes
+    files: Vec<String>,
 }
 
 impl Shader {
-    fn from_resource(gl: Rc<gl::Gl>, res: &Resources, name: &str) -> Result<Self, Error> {
-        const POSSIBLE_EXT: [(&str, gl::types::GLenum); 2] =
-            [(".vert", gl::VERTEX_SHADER), (".frag", gl::FRAGMENT_SHADER)];
-
-        let shader_type = POSSIBLE_EXT
+    fn from_resource(gl: Rc<gl::Gl>, res: &Resources, name: &str, defines: &[(String, String)]) -> Result<Self, Error> {
+        let shader_type = SHADER_STAGES
             .iter()
             .find(|&&(file_extension, _)| name.ends_with(file_extension))
             .map(|&(_, s_type)| s_type)
             .ok_or_else(|| Error::CanNotDetermineShaderTypeForResource{ name: name.into() })?;
 
-        let source = res
-            .load_cstring(name)
-            .map_err(|e| Error::ResourceLoad{name: name.into(), inner: e})?;
+        let preprocessed = preprocessor::preprocess(name, defines, |source_name| {
+            res.load_cstring(source_name).map(|source| source.to_string_lossy().into_owned())
+        })
+        .map_err(|e| match e {
+            preprocessor::Error::ResourceLoad { name, inner } => Error::ResourceLoad { name, inner },
+            e => Error::Preprocess { name: name.into(), inner: e },
+        })?;
+        let source = CString::new(preprocessed.source).expect("Expected shader source to have no null bytes");
 
-        Self::from_source(gl, &source, shader_type, name)
+        Self::from_source(gl, &source, shader_type, name, preprocessed.files)
     }
 
-    fn from_source(gl: Rc<gl::Gl>, src_code: &CString, shader_type: GLenum, name: &str) -> Result<Self, Error> {
+    fn from_source(gl: Rc<gl::Gl>, src_code: &CString, shader_type: GLenum, name: &str, files: Vec<String>) -> Result<Self, Error> {
         let id = unsafe { gl.CreateShader(shader_type) };
         unsafe {
             gl.ShaderSource(
@@ -144,11 +559,13 @@ impl Shader {
             let error = create_blank_cstring(len as usize);
             unsafe {
                 gl.GetShaderInfoLog(id, len, null_mut(), error.as_ptr() as *mut GLchar);
+                gl.DeleteShader(id);
             }
-            return Err(Error::CompileError{name: name.into(), message: error.to_string_lossy().into_owned()});
+            let message = preprocessor::map_info_log(&error.to_string_lossy(), &files);
+            return Err(Error::CompileError{name: name.into(), message});
         }
 
-        Ok(Self { gl, id, })
+        Ok(Self { gl, id, files })
     }
 }
 
@@ -160,8 +577,288 @@ impl Drop for Shader {
     }
 }
 
+/**
+Assigns consecutive texture units to the sampler uniforms of a linked program, sorted by name
+*/
+fn assign_texture_units(gl: &gl::Gl, program: GLuint, uniforms: &HashMap<String, UniformInfo>) -> HashMap<String, TextureUnit> {
+    let mut samplers: Vec<(&String, &UniformInfo)> = uniforms
+        .iter()
+        // Arrays are also registered as "name[0]", which is the same uniform
+        .filter(|(name, info)| uniform::is_sampler_type(info.gl_type) && !name.ends_with("[0]"))
+        .collect();
+    samplers.sort_by_key(|(name, _)| *name);
+
+    let mut texture_units = HashMap::new();
+    let mut next_unit: u32 = 0;
+    for (name, info) in samplers {
+        let units: Vec<GLint> = (next_unit..next_unit + info.size as u32).map(|unit| unit as GLint).collect();
+        unsafe { gl.ProgramUniform1iv(program, info.location, units.len() as GLsizei, units.as_ptr()) }
+        texture_units.insert(name.clone(), TextureUnit(next_unit));
+        let first_element = format!("{}[0]", name);
+        if uniforms.contains_key(&first_element) {
+            texture_units.insert(first_element, TextureUnit(next_unit));
+        }
+        next_unit += info.size as u32;
+    }
+
+    texture_units
+}
+
+/**
+Reflects all active uniforms of a linked program
+
+Arrays are registered both as "name[0]" and "name", uniforms in uniform blocks are skipped.
+*/
+fn query_active_uniforms(gl: &gl::Gl, program: GLuint) -> HashMap<String, UniformInfo> {
+    let mut count: GLint = 0;
+    let mut max_length: GLint = 0;
+    unsafe {
+        gl.GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+        gl.GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+    }
+
+    let mut uniforms = HashMap::new();
+    for index in 0..count as GLuint {
+        let mut name_buffer: Vec<u8> = vec![0; max_length.max(1) as usize];
+        let mut length: GLsizei = 0;
+        let mut size: GLint = 0;
+        let mut gl_type: GLenum = 0;
+        unsafe {
+            gl.GetActiveUniform(
+                program,
+                index,
+                name_buffer.len() as GLsizei,
+                &mut length,
+                &mut size,
+                &mut gl_type,
+                name_buffer.as_mut_ptr() as *mut GLchar,
+            );
+        }
+        name_buffer.truncate(length as usize);
+        let name = String::from_utf8_lossy(&name_buffer).into_owned();
+
+        let cname = CString::new(name.as_str()).expect("Expected uniform name to have no null bytes");
+        let location = unsafe { gl.GetUniformLocation(program, cname.as_ptr()) };
+        if location == -1 {
+            continue;
+        }
+
+        let info = UniformInfo { location, gl_type, size };
+        if let Some(array_name) = name.strip_suffix("[0]") {
+            uniforms.insert(array_name.to_owned(), info);
+        }
+        uniforms.insert(name, info);
+    }
+
+    uniforms
+}
+
 fn create_blank_cstring(len: usize) -> CString {
     let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
     buffer.extend([b' '].iter().cycle().take(len));
     unsafe { CString::from_vec_unchecked(buffer) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::test_support::{TempResources, FULLSCREEN_VERTEX_SOURCE};
+    use crate::renderer::{FrameBuffer, HeadlessContext, ShaderStorageBuffer, Texture, TextureDescriptor, TextureFormat, FilterMode};
+
+    const VERTEX_SOURCE: &str = "#version 330 core\nvoid main() { gl_Position = vec4(0.0); }\n";
+    const FRAGMENT_SOURCE: &str = "#version 330 core\nout vec4 color;\nvoid main() { color = vec4(1.0); }\n";
+
+    #[test]
+    fn ignores_only_unknown_uniforms() {
+        let unknown = Error::UnknownUniform { program: "lit".into(), name: "Removed".into() };
+        assert!(ignore_unknown_uniform::<()>(Err(unknown)).is_ok());
+        let block = Error::UnknownUniformBlock { program: "lit".into(), name: "Lights".into() };
+        assert!(matches!(ignore_unknown_uniform::<()>(Err(block)), Err(Error::UnknownUniformBlock { .. })));
+        assert!(ignore_unknown_uniform(Ok(TextureUnit(0))).is_ok());
+    }
+
+    #[test]
+    fn reload_keeps_last_good_program() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let temp = TempResources::new("shader_reload");
+        let res = temp.resources();
+        temp.write_aged("reload.vert", VERTEX_SOURCE, 60);
+        temp.write_aged("reload.frag", FRAGMENT_SOURCE, 60);
+
+        let mut program = ShaderProgram::from_resource(context.get_gl_handle(), &res, "reload").unwrap();
+        assert!(!program.reload_if_changed(&res).unwrap());
+
+        temp.write_aged("reload.frag", "#version 330 core\nvoid main() { oops }\n", 30);
+        let last_good = program.id;
+        assert!(matches!(program.reload_if_changed(&res), Err(Error::CompileError { .. })));
+        assert_eq!(program.id, last_good);
+        // Failed sources are only retried after the next edit
+        assert!(!program.reload_if_changed(&res).unwrap());
+
+        temp.write_aged("reload.frag", FRAGMENT_SOURCE, 0);
+        assert!(program.reload_if_changed(&res).unwrap());
+        assert_ne!(program.id, last_good);
+    }
+
+    #[test]
+    fn set_uniform_checks_name_and_type() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let temp = TempResources::new("shader_uniforms");
+        let res = temp.resources();
+        temp.write("uniforms.vert", "#version 330 core\nuniform mat4 Model;\nuniform float Scale[2];\n\
+            void main() { gl_Position = Model * vec4(Scale[0] + Scale[1]); }\n");
+        temp.write("uniforms.frag", "#version 330 core\nuniform sampler2D Image;\nout vec4 color;\n\
+            void main() { color = texture(Image, vec2(0.0)); }\n");
+
+        let program = ShaderProgram::from_resource(context.get_gl_handle(), &res, "uniforms").unwrap();
+        assert_eq!(program.get_uniform_info("Model").unwrap().gl_type, gl::FLOAT_MAT4);
+        assert_eq!(program.get_uniform_info("Scale").unwrap().size, 2);
+        assert_eq!(program.get_uniform_location("Scale[1]"), Some(program.get_uniform_location("Scale").unwrap() + 1));
+
+        program.set_uniform("Model", &Matrix4::identity()).unwrap();
+        program.set_uniform("Scale", &0.5f32).unwrap();
+        program.set_uniform("Image", &TextureUnit(3)).unwrap();
+
+        let mut unit: GLint = 0;
+        unsafe { program.gl.GetUniformiv(program.id, program.get_uniform_location("Image").unwrap(), &mut unit) }
+        assert_eq!(unit, 3);
+
+        match program.set_uniform("Missing", &1.0f32) {
+            Err(Error::UnknownUniform { name, .. }) => assert_eq!(name, "Missing"),
+            _ => panic!("Expected unknown uniform to be rejected"),
+        }
+        match program.set_uniform("Model", &1.0f32) {
+            Err(Error::UniformTypeMismatch { expected, .. }) => assert_eq!(expected, "mat4"),
+            _ => panic!("Expected type mismatch to be rejected"),
+        }
+    }
+
+    #[test]
+    fn binds_textures_to_assigned_units() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("shader_textures");
+        let res = temp.resources();
+        temp.write("textures.vert", FULLSCREEN_VERTEX_SOURCE);
+        temp.write("textures.frag", "#version 330 core\nuniform sampler2D Overlay[2];\n\
+            uniform sampler2D Base;\nuniform float Scale;\nout vec4 color;\nvoid main() {\n\
+            color = vec4(texture(Base, vec2(0.5)).r, texture(Overlay[1], vec2(0.5)).g, texture(Overlay[0], vec2(0.5)).b, Scale);\n}\n");
+
+        let program = ShaderProgram::from_resource(Rc::clone(&gl), &res, "textures").unwrap();
+        assert_eq!(program.texture_unit("Base"), Some(TextureUnit(0)));
+        assert_eq!(program.texture_unit("Overlay"), Some(TextureUnit(1)));
+        assert_eq!(program.texture_unit("Scale"), None);
+        assert!(matches!(program.bind_texture("Missing", &Texture::empty(Rc::clone(&gl), 1, 1)), Err(Error::UnknownUniform { .. })));
+        assert!(matches!(program.bind_texture("Scale", &Texture::empty(Rc::clone(&gl), 1, 1)), Err(Error::UniformTypeMismatch { .. })));
+
+        let linear = TextureDescriptor::render_target(TextureFormat::Rgba8, FilterMode::Linear);
+        let texture = |pixels: Vec<u8>| {
+            let width = pixels.len() as u32 / 4;
+            Texture::from_image_with_descriptor(Rc::clone(&gl), &image::RgbaImage::from_raw(width, 1, pixels).unwrap(), &linear)
+        };
+        let base = texture(vec![0, 0, 0, 255, 255, 255, 255, 255]);
+        let blue = texture(vec![0, 0, 255, 255]);
+        let green = texture(vec![0, 255, 0, 255]);
+        let nearest = Sampler::new(Rc::clone(&gl), &TextureDescriptor::render_target(TextureFormat::Rgba8, FilterMode::Nearest));
+
+        let frame_buffer = FrameBuffer::new(Rc::clone(&gl), 1, 1);
+        let mut vertex_array: GLuint = 0;
+        let mut draw = || {
+            frame_buffer.bind();
+            unsafe {
+                gl.Viewport(0, 0, 1, 1);
+                gl.GenVertexArrays(1, &mut vertex_array);
+                gl.BindVertexArray(vertex_array);
+                gl.DrawArrays(gl::TRIANGLES, 0, 3);
+                gl.DeleteVertexArrays(1, &vertex_array);
+            }
+            frame_buffer.unbind();
+            frame_buffer.read_rgb_image().get_pixel(0, 0).0
+        };
+
+        program.bind();
+        program.set_uniform("Scale", &1.0f32).unwrap();
+        program.bind_texture("Base", &base).unwrap();
+        program.bind_texture("Overlay[0]", &blue).unwrap();
+        green.bind_to_unit(2);
+        let [red, green_channel, blue_channel] = draw();
+        // Halfway between the black and the white texel
+        assert!((red as i32 - 128).abs() <= 1);
+        assert_eq!([green_channel, blue_channel], [255, 255]);
+
+        program.bind_texture_with_sampler("Base", &base, &nearest).unwrap();
+        assert_eq!(draw(), [255, 255, 255]);
+        nearest.unbind_unit(0);
+    }
+
+    #[test]
+    fn discovers_optional_graphics_stages() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let temp = TempResources::new("shader_stages");
+        let res = temp.resources();
+        temp.write("stages.vert", VERTEX_SOURCE);
+        temp.write("stages.geom", "#version 330 core\nlayout(points) in;\n\
+            layout(points, max_vertices = 1) out;\nvoid main() { gl_Position = gl_in[0].gl_Position; EmitVertex(); }\n");
+        temp.write("stages.frag", FRAGMENT_SOURCE);
+        temp.write("fragment_only.frag", FRAGMENT_SOURCE);
+
+        let program = ShaderProgram::from_resource(context.get_gl_handle(), &res, "stages").unwrap();
+        assert_eq!(program.stages, vec!["stages.vert", "stages.geom", "stages.frag"]);
+
+        match ShaderProgram::from_resource(context.get_gl_handle(), &res, "fragment_only") {
+            Err(Error::MissingVertexShader { name }) => assert_eq!(name, "fragment_only"),
+            _ => panic!("Expected program without vertex shader to be rejected"),
+        }
+    }
+
+    #[test]
+    fn compute_program_writes_storage_buffer() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("shader_compute");
+        let res = temp.resources();
+        temp.write("double.comp", "#version 430 core\nlayout(local_size_x = 4) in;\n\
+            layout(std430, binding = 0) buffer Values { float values[]; };\nuniform float Factor;\n\
+            void main() { values[gl_GlobalInvocationID.x] *= Factor; }\n");
+
+        let program = ComputeProgram::from_resource(Rc::clone(&gl), &res, "double").unwrap();
+        assert_eq!(program.local_size(), [4, 1, 1]);
+
+        let buffer = ShaderStorageBuffer::new(Rc::clone(&gl));
+        buffer.bind();
+        buffer.buffer_static_draw(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        buffer.unbind();
+        buffer.bind_base(0);
+
+        program.set_uniform("Factor", &2.0f32).unwrap();
+        program.dispatch(2, 1, 1);
+        memory_barrier(&gl, MemoryBarrier::SHADER_STORAGE | MemoryBarrier::BUFFER_UPDATE);
+
+        let mut values = [0.0f32; 8];
+        buffer.read_data(&mut values);
+        assert_eq!(values, [2.0, 4.0, 6.0, 8.0, 10.0, 12.0, 14.0, 16.0]);
+    }
+
+    #[test]
+    fn includes_map_errors_and_trigger_reload() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let temp = TempResources::new("shader_include");
+        let res = temp.resources();
+        temp.write_aged("include.vert", VERTEX_SOURCE, 60);
+        temp.write_aged("include.frag", "#version 330 core\n#include \"common/color.glsl\"\n\
+            out vec4 color;\nvoid main() { color = base_color(); }\n", 60);
+        temp.write_aged("common/color.glsl", "vec4 base_color() { return vec4(BRIGHTNESS); }\n", 60);
+
+        let mut program = ShaderProgram::from_resource_with_defines(
+            context.get_gl_handle(), &res, "include", &[("BRIGHTNESS", "0.5")]).unwrap();
+        assert!(program.sources.iter().any(|(source_name, _)| source_name == "common/color.glsl"));
+
+        match ShaderProgram::from_resource(context.get_gl_handle(), &res, "include") {
+            Err(Error::CompileError { message, .. }) => assert!(message.contains("common/color.glsl:1"), "{}", message),
+            _ => panic!("Expected missing define to fail in the included file"),
+        }
+
+        temp.write_aged("common/color.glsl", "vec4 base_color() { return vec4(vec3(BRIGHTNESS), 1.0); }\n", 0);
+        assert!(program.reload_if_changed(&res).unwrap());
+    }
+}
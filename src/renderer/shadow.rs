@@ -0,0 +1,490 @@
+#![allow(non_local_definitions)]
+
+use std::rc::Rc;
+
+use gl::types::*;
+use nalgebra::{Matrix4, Orthographic3, Perspective3, Point3, Unit, Vector3};
+
+use crate::resources::Resources;
+
+use super::camera::Camera;
+use super::gl_buffers::{DepthFormat, FrameBuffer, FrameBufferError, UniformBuffer};
+use super::lighting::{Light, SpotLight, MAX_LIGHTS};
+use super::shader::{self, ShaderProgram};
+use super::texture::Texture;
+
+/// Depth only pass rendering shadow casters
+const SHADOW_SHADER: &str = "shaders/shadow";
+
+/// Shadow maps the atlas holds, must match `MAX_SHADOW_VIEWS` in shaders/common/shadow.glsl
+pub const MAX_SHADOW_VIEWS: usize = 8;
+
+/// Uniform buffer binding point `ShadowMaps::bind` binds the `Shadows` block to
+pub const SHADOWS_BINDING: u32 = 1;
+
+/// Tiles per row of the atlas
+const ATLAS_COLUMNS: u32 = 4;
+
+/// Tiles per column of the atlas
+const ATLAS_ROWS: u32 = MAX_SHADOW_VIEWS as u32 / ATLAS_COLUMNS;
+
+/// Near plane of spot light shadows as a fraction of the light's range
+const SPOT_NEAR_RATIO: f32 = 0.01;
+
+/// Widest field of view of spot light shadows, perspective projections degenerate at 180°
+const MAX_SPOT_FOV: f32 = 170.0 * std::f32::consts::PI / 180.0;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "Failed to build the shadow shader")]
+    Shader { #[cause] inner: shader::Error },
+    #[fail(display = "Failed to create the shadow atlas")]
+    Atlas { #[cause] inner: FrameBufferError },
+}
+
+/// Resolution, cascades, bias and filtering of `ShadowMaps`
+#[derive(Copy, Clone, Debug)]
+pub struct ShadowSettings {
+    /// Width and height in texels of the shadow map of each view
+    pub tile_size: u32,
+    /// Number of cascades of directional lights, each one takes a view
+    pub cascades: u32,
+    /// Blends cascade splits between uniform (0) and logarithmic (1) spacing
+    pub split_lambda: f32,
+    /// View depth beyond which directional lights cast no shadows, if it is before the far plane
+    pub max_distance: f32,
+    /// How far towards a directional light objects outside the camera frustum still cast shadows
+    pub caster_distance: f32,
+    /// Subtracted from the depth of shaded points, in shadow map depth from 0 to 1
+    pub depth_bias: f32,
+    /// Offset of shaded points along their normal, in texels of their shadow map
+    pub normal_bias: f32,
+    /// Percentage closer filtering averages (2 * radius + 1)² texels, 0 for hard shadows
+    pub pcf_radius: u32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            tile_size: 1024,
+            cascades: 3,
+            split_lambda: 0.75,
+            max_distance: 50.0,
+            caster_distance: 50.0,
+            depth_bias: 0.0005,
+            normal_bias: 1.5,
+            pcf_radius: 1,
+        }
+    }
+}
+
+/// One shadow map, rendered into a tile of the atlas
+#[derive(Copy, Clone, Debug)]
+pub struct ShadowView {
+    /// Index of the light in the lights passed to `ShadowMaps::render`
+    pub light: usize,
+    /// World space to the light's clip space
+    pub view_projection: Matrix4<f32>,
+    /// View depth of the camera up to which this cascade is used, infinite for spot lights
+    pub split: f32,
+    /**
+    World size of a shadow map texel, for spot lights at a distance of 1 along the light's axis
+    since it grows with the distance
+    */
+    pub texel_size: f32,
+}
+
+/// The `Shadows` uniform block in std140 layout
+#[derive(Default)]
+#[repr(C)]
+struct ShadowsBlock {
+    /// Column major
+    matrices: [[f32; 16]; MAX_SHADOW_VIEWS],
+    tiles: [[f32; 4]; MAX_SHADOW_VIEWS],
+    splits: [[f32; 4]; MAX_SHADOW_VIEWS],
+    light_shadows: [[i32; 4]; MAX_LIGHTS],
+    params: [f32; 4],
+}
+
+/**
+Shadow maps of directional and spot lights, rendered into tiles of one depth texture atlas and
+sampled with percentage closer filtering by shaders/common/shadow.glsl
+
+Directional lights get one cascade per slice of the camera frustum, spot lights one
+perspective view along their cone. Point lights cast no shadows.
+*/
+pub struct ShadowMaps {
+    gl: Rc<gl::Gl>,
+    program: ShaderProgram,
+    /// Holds the atlas in its depth texture
+    atlas: FrameBuffer,
+    buffer: UniformBuffer,
+    views: Vec<ShadowView>,
+    pub settings: ShadowSettings,
+}
+
+impl ShadowMaps {
+    /// Creates the atlas for `settings`, the shaders are read from `res`
+    pub fn new(gl: Rc<gl::Gl>, res: &Resources, settings: ShadowSettings) -> Result<Self, Error> {
+        let program = ShaderProgram::from_resource(Rc::clone(&gl), res, SHADOW_SHADER)
+            .map_err(|inner| Error::Shader { inner })?;
+        let (width, height) = atlas_size(&settings);
+        let atlas = FrameBuffer::builder(Rc::clone(&gl), width, height)
+            .without_color()
+            .depth_texture(DepthFormat::Depth32F)
+            .build()
+            .map_err(|inner| Error::Atlas { inner })?;
+        let buffer = UniformBuffer::new(Rc::clone(&gl));
+
+        let shadow_maps = ShadowMaps { gl, program, atlas, buffer, views: Vec::new(), settings };
+        shadow_maps.upload();
+        Ok(shadow_maps)
+    }
+
+    /// The views rendered by the last `render`, in the order of their tiles
+    pub fn views(&self) -> &[ShadowView] {
+        &self.views
+    }
+
+    /// The depth texture holding every view's tile
+    pub fn atlas(&self) -> &Texture {
+        self.atlas
+            .get_depth_texture()
+            .expect("Expected the shadow atlas to have a depth texture")
+    }
+
+    /// See `ShaderProgram::reload_if_changed`
+    pub fn reload_if_changed(&mut self, res: &Resources) -> Result<bool, Error> {
+        self.program.reload_if_changed(res).map_err(|inner| Error::Shader { inner })
+    }
+
+    /**
+    Renders the shadow maps of `lights[i]` for every `i` in `casters`, with cascades fit to
+    `camera`, and uploads the `Shadows` block for shading `lights`
+
+    `draw` is called once per view with the bound depth only program and should set its `Model`
+    uniform and draw every shadow caster. Casters beyond `MAX_LIGHTS`, or beyond the views
+    left in the atlas, cast no shadows. The bound frame buffer and viewport are kept, also when
+    setting a uniform or `draw` fails.
+    */
+    pub fn render<F>(&mut self, camera: &Camera, lights: &[Light], casters: &[usize], mut draw: F) -> Result<(), shader::Error>
+    where F: FnMut(&ShaderProgram) -> Result<(), shader::Error>, {
+        self.views = plan_views(&self.settings, camera, lights, casters);
+        let (width, height) = atlas_size(&self.settings);
+        if self.atlas.get_size() != (width, height) {
+            self.atlas.resize(width, height);
+        }
+
+        let mut frame_buffer: GLint = 0;
+        let mut viewport = [0 as GLint; 4];
+        let depth_test = unsafe {
+            self.gl.GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut frame_buffer);
+            self.gl.GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            self.gl.IsEnabled(gl::DEPTH_TEST) == gl::TRUE
+        };
+
+        self.atlas.bind();
+        self.atlas.clear([0.0; 4]);
+        self.program.bind();
+        unsafe {
+            self.gl.Enable(gl::DEPTH_TEST);
+        }
+        let tile_size = self.settings.tile_size as GLsizei;
+        let mut drawn = Ok(());
+        for (index, view) in self.views.iter().enumerate() {
+            let (column, row) = tile_position(index);
+            unsafe {
+                self.gl.Viewport(column as GLsizei * tile_size, row as GLsizei * tile_size, tile_size, tile_size);
+            }
+            drawn = shader::ignore_unknown_uniform(self.program.set_uniform("LightViewProjection", &view.view_projection))
+                .and_then(|_| draw(&self.program));
+            if drawn.is_err() {
+                break;
+            }
+        }
+
+        unsafe {
+            if !depth_test {
+                self.gl.Disable(gl::DEPTH_TEST);
+            }
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, frame_buffer as GLuint);
+            self.gl.Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        self.upload();
+        drawn
+    }
+
+    /**
+    Binds the atlas as `ShadowMap` and the `Shadows` block of `program` to `SHADOWS_BINDING`
+    and enables them with `UseShadows`
+    */
+    pub fn bind(&self, program: &ShaderProgram) -> Result<(), shader::Error> {
+        self.buffer.bind_base(SHADOWS_BINDING);
+        program.bind_uniform_block("Shadows", SHADOWS_BINDING)?;
+        program.bind_texture("ShadowMap", self.atlas())?;
+        program.set_uniform("UseShadows", &1)
+    }
+
+    fn upload(&self) {
+        let mut block = ShadowsBlock::default();
+        let settings = &self.settings;
+        block.params = [settings.depth_bias, settings.normal_bias, settings.pcf_radius as f32, 0.0];
+        for (index, view) in self.views.iter().enumerate() {
+            block.matrices[index].copy_from_slice((clip_to_texture() * view.view_projection).as_slice());
+            let (column, row) = tile_position(index);
+            let (width, height) = (1.0 / ATLAS_COLUMNS as f32, 1.0 / ATLAS_ROWS as f32);
+            block.tiles[index] = [column as f32 * width, row as f32 * height, width, height];
+            block.splits[index] = [view.split, view.texel_size, 0.0, 0.0];
+            // The views of a light are consecutive
+            let shadow = &mut block.light_shadows[view.light];
+            if shadow[1] == 0 {
+                shadow[0] = index as i32;
+            }
+            shadow[1] += 1;
+        }
+
+        self.buffer.bind();
+        self.buffer.buffer_dynamic_draw(std::slice::from_ref(&block));
+        self.buffer.unbind();
+    }
+}
+
+/**
+`count + 1` view depths from `near` to `far` bounding the cascades, blending between uniform
+(`lambda` 0) and logarithmic (`lambda` 1) spacing
+*/
+fn cascade_splits(near: f32, far: f32, count: u32, lambda: f32) -> Vec<f32> {
+    (0..=count)
+        .map(|i| {
+            let fraction = i as f32 / count as f32;
+            let logarithmic = near * (far / near).powf(fraction);
+            let uniform = near + (far - near) * fraction;
+            lambda * logarithmic + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+fn atlas_size(settings: &ShadowSettings) -> (u32, u32) {
+    (settings.tile_size * ATLAS_COLUMNS, settings.tile_size * ATLAS_ROWS)
+}
+
+/// Column and row of the atlas tile of view `index`
+fn tile_position(index: usize) -> (u32, u32) {
+    (index as u32 % ATLAS_COLUMNS, index as u32 / ATLAS_COLUMNS)
+}
+
+/// Maps clip space to shadow map coordinates from 0 to 1
+fn clip_to_texture() -> Matrix4<f32> {
+    Matrix4::new_translation(&Vector3::repeat(0.5)) * Matrix4::new_scaling(0.5)
+}
+
+/// An up vector for looking along `direction`
+fn up_vector(direction: &Vector3<f32>) -> Vector3<f32> {
+    if direction.y.abs() > 0.99 {
+        Vector3::z()
+    } else {
+        Vector3::y()
+    }
+}
+
+/// The views of the casters that fit into the atlas
+fn plan_views(settings: &ShadowSettings, camera: &Camera, lights: &[Light], casters: &[usize]) -> Vec<ShadowView> {
+    let far = camera.far_plane().min(settings.max_distance);
+    let splits = cascade_splits(camera.near_plane(), far, settings.cascades.max(1), settings.split_lambda);
+    let cascades = splits.len() - 1;
+
+    let mut views = Vec::new();
+    for &light in casters.iter().filter(|&&light| light < lights.len().min(MAX_LIGHTS)) {
+        match &lights[light] {
+            Light::Directional(directional) if views.len() + cascades <= MAX_SHADOW_VIEWS => {
+                for slice in splits.windows(2) {
+                    let corners = camera.frustum_corners(slice[0], slice[1]);
+                    let (view_projection, texel_size) = cascade_view_projection(&directional.direction, &corners, settings);
+                    views.push(ShadowView { light, view_projection, split: slice[1], texel_size });
+                }
+            },
+            Light::Spot(spot) if views.len() < MAX_SHADOW_VIEWS => {
+                let (view_projection, texel_size) = spot_view_projection(spot, settings);
+                views.push(ShadowView { light, view_projection, split: f32::INFINITY, texel_size });
+            },
+            _ => (),
+        }
+    }
+    views
+}
+
+/**
+Orthographic projection along `direction` of the bounding sphere of a frustum slice, so that
+its size does not change while the camera turns, and the world size of its texels
+*/
+fn cascade_view_projection(direction: &Unit<Vector3<f32>>, corners: &[Point3<f32>; 8], settings: &ShadowSettings) -> (Matrix4<f32>, f32) {
+    let center = corners.iter().fold(Vector3::zeros(), |sum, corner| sum + corner.coords) / corners.len() as f32;
+    let radius = corners.iter().map(|corner| (corner.coords - center).norm()).fold(0.0, f32::max);
+    // Rounding keeps the radius from jittering with floating point error
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    // Moving the center in whole texels keeps shadow edges from shimmering while the camera moves
+    let up = up_vector(direction);
+    let rotation = Matrix4::look_at_rh(&Point3::origin(), &Point3::from(direction.into_inner()), &up);
+    let texel = 2.0 * radius / settings.tile_size as f32;
+    let mut center = rotation.transform_point(&Point3::from(center));
+    center.x = (center.x / texel).floor() * texel;
+    center.y = (center.y / texel).floor() * texel;
+    let center = rotation.transpose().transform_point(&center);
+
+    let view = Matrix4::look_at_rh(&center, &(center + direction.into_inner()), &up);
+    let projection = Orthographic3::new(-radius, radius, -radius, radius, -settings.caster_distance, radius);
+    (projection.to_homogeneous() * view, texel)
+}
+
+/**
+Perspective projection covering the cone of `light` up to its range, and the world size of its
+texels at a distance of 1
+*/
+fn spot_view_projection(light: &SpotLight, settings: &ShadowSettings) -> (Matrix4<f32>, f32) {
+    let direction = light.direction.into_inner();
+    let view = Matrix4::look_at_rh(&light.position, &(light.position + direction), &up_vector(&direction));
+    let fov = (2.0 * light.outer_angle).min(MAX_SPOT_FOV);
+    let projection = Perspective3::new(1.0, fov, light.range * SPOT_NEAR_RATIO, light.range);
+    let texel_size = 2.0 * (fov / 2.0).tan() / settings.tile_size as f32;
+    (projection.to_homogeneous() * view, texel_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::renderer::{DirectionalLight, HeadlessContext, LightBuffer, Mesh, PointLight, PosNormalUV, Topology};
+    use crate::renderer::LIGHTS_BINDING;
+
+    #[test]
+    fn cascade_splits_cover_the_depth_range() {
+        let uniform = cascade_splits(1.0, 10.0, 3, 0.0);
+        for (split, expected) in uniform.iter().zip([1.0, 4.0, 7.0, 10.0].iter()) {
+            assert!((split - expected).abs() < 1e-5, "{:?}", uniform);
+        }
+        let logarithmic = cascade_splits(0.1, 100.0, 3, 1.0);
+        for (split, expected) in logarithmic.iter().zip([0.1, 1.0, 10.0, 100.0].iter()) {
+            assert!((split - expected).abs() < 1e-3, "{:?}", logarithmic);
+        }
+    }
+
+    #[test]
+    fn cascades_contain_their_frustum_slice() {
+        let camera = Camera::new(Vector3::new(0.0, 2.0, 5.0), Vector3::new(0.4, -0.3, 0.0), 1.5, 1.0, 0.1, 1000.0, true);
+        let sun = Light::Directional(DirectionalLight {
+            direction: Unit::new_normalize(Vector3::new(-1.0, -2.0, -0.5)),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+        });
+        let lamp = Light::Point(PointLight { position: Point3::origin(), color: Vector3::zeros(), intensity: 1.0, range: 1.0 });
+        let settings = ShadowSettings { cascades: 3, max_distance: 30.0, ..Default::default() };
+
+        let views = plan_views(&settings, &camera, &[lamp, sun], &[0, 1]);
+        assert_eq!(views.len(), 3);
+        assert!((views[2].split - 30.0).abs() < 1e-4);
+        let splits = cascade_splits(0.1, 30.0, 3, settings.split_lambda);
+        for (view, slice) in views.iter().zip(splits.windows(2)) {
+            assert_eq!(view.light, 1);
+            for corner in camera.frustum_corners(slice[0], slice[1]).iter() {
+                let ndc = view.view_projection.transform_point(corner);
+                assert!(ndc.coords.iter().all(|c| c.abs() <= 1.0), "{:?} outside of the cascade", ndc);
+            }
+        }
+
+        // The atlas fits two directional lights with three cascades, but not a third
+        assert_eq!(plan_views(&settings, &camera, &[sun, sun, sun], &[0, 1, 2]).len(), 6);
+    }
+
+    #[test]
+    fn shadows_block_matches_std140_layout() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let assets = Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        let program = ShaderProgram::from_resource(Rc::clone(&gl), &assets, "shaders/lit").unwrap();
+
+        // ShaderProgram keeps its id private, read it back from the current binding
+        program.bind();
+        let name = std::ffi::CString::new("Shadows").unwrap();
+        let mut id: GLint = 0;
+        let mut size: GLint = 0;
+        unsafe {
+            gl.GetIntegerv(gl::CURRENT_PROGRAM, &mut id);
+            let index = gl.GetUniformBlockIndex(id as GLuint, name.as_ptr());
+            gl.GetActiveUniformBlockiv(id as GLuint, index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut size);
+        }
+        assert_eq!(size as usize, std::mem::size_of::<ShadowsBlock>());
+    }
+
+    #[test]
+    fn occluders_shadow_the_ground() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let assets = Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        let program = ShaderProgram::from_resource(Rc::clone(&gl), &assets, "shaders/pbr").unwrap();
+
+        // A ground quad at y = 0 and a smaller occluder above its center, both facing up
+        let quad = |size: f32, height: f32| {
+            let vertices = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].map(|(x, z)| PosNormalUV {
+                position: (x * size, height, z * size).into(),
+                normal: (0.0, 1.0, 0.0).into(),
+                uv: (0.0, 0.0).into(),
+            });
+            Mesh::new(Rc::clone(&gl), &vertices, &[0u8, 2, 1, 1, 2, 3], Topology::Triangles)
+        };
+        let ground = quad(4.0, 0.0);
+        let occluder = quad(1.0, 1.0);
+
+        let sun = Light::Directional(DirectionalLight {
+            direction: -Vector3::y_axis(),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            intensity: 2.0,
+        });
+        let lights = LightBuffer::new(Rc::clone(&gl), Vector3::zeros());
+        lights.upload(&[sun]);
+        lights.bind_base(LIGHTS_BINDING);
+        program.bind_uniform_block("Lights", LIGHTS_BINDING).unwrap();
+
+        let settings = ShadowSettings { tile_size: 64, cascades: 1, ..Default::default() };
+        let mut shadow_maps = ShadowMaps::new(Rc::clone(&gl), &assets, settings).unwrap();
+        let frame_buffer = FrameBuffer::new(Rc::clone(&gl), 1, 1);
+
+        // Looking down at a point of the ground from above the occluder
+        let render = |shadow_maps: &mut ShadowMaps, target: Vector3<f32>, casters: &[usize]| {
+            let position = target + Vector3::new(0.0, 3.0, 0.01);
+            let view = nalgebra::Isometry3::look_at_rh(&Point3::from(position), &Point3::from(target), &Vector3::z());
+            let camera = Camera::from_isometry(&view.inverse(), 1.0, 0.1, 0.1, 20.0, true);
+            shadow_maps.render(&camera, &[sun], casters, |program| {
+                program.set_uniform("Model", &Matrix4::identity())?;
+                ground.draw();
+                occluder.draw();
+                Ok(())
+            }).unwrap();
+            shadow_maps.bind(&program).unwrap();
+            program.set_uniform("View", &camera.get_view_matrix()).unwrap();
+            program.set_uniform("Perspective", &camera.get_projection_matrix()).unwrap();
+            program.set_uniform("Model", &Matrix4::identity()).unwrap();
+            program.set_uniform("CameraPosition", &camera.get_position()).unwrap();
+
+            frame_buffer.bind();
+            frame_buffer.clear([0.0, 0.0, 0.0, 1.0]);
+            unsafe { gl.Enable(gl::DEPTH_TEST) };
+            program.bind();
+            // Only the ground is drawn, the occluder just casts its shadow
+            ground.draw();
+            frame_buffer.unbind();
+            context.finish();
+            frame_buffer.read_rgba_image().get_pixel(0, 0).0[0]
+        };
+
+        let under_occluder = Vector3::new(0.3, 0.0, 0.0);
+        let in_the_open = Vector3::new(2.5, 0.0, 0.0);
+        let shadowed = render(&mut shadow_maps, under_occluder, &[0]);
+        let lit = render(&mut shadow_maps, in_the_open, &[0]);
+        let without_casters = render(&mut shadow_maps, under_occluder, &[]);
+        assert_eq!(shadow_maps.views().len(), 0);
+        assert_eq!(shadowed, 0, "only the light's direct contribution is shadowed");
+        assert!(lit > 0 && (lit as i32 - without_casters as i32).abs() <= 1, "{} {}", lit, without_casters);
+    }
+}
@@ -0,0 +1,71 @@
+use std::rc::Rc;
+
+use nalgebra::Matrix4;
+
+use crate::resources::Resources;
+
+use super::shader::{self, ShaderProgram};
+use super::{Camera, TextureCube, VertexArray};
+
+/**
+Draws a cube map around the camera, behind everything already in the depth buffer
+
+Render it after the opaque scene geometry: it is drawn on the far plane with depth writes
+disabled, so only pixels no geometry was drawn to are covered.
+*/
+pub struct Skybox {
+    gl: Rc<gl::Gl>,
+    program: ShaderProgram,
+    cube: Rc<TextureCube>,
+    /// Empty, the vertex shader derives its full screen triangle from `gl_VertexID`
+    vertex_array: VertexArray,
+}
+
+impl Skybox {
+    /// Loads the skybox shader "shaders/skybox" from `res`
+    pub fn new(gl: Rc<gl::Gl>, res: &Resources, cube: Rc<TextureCube>) -> Result<Self, shader::Error> {
+        let program = ShaderProgram::from_resource(Rc::clone(&gl), res, "shaders/skybox")?;
+        let vertex_array = VertexArray::new(Rc::clone(&gl));
+        unsafe {
+            gl.Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+        }
+
+        Ok(Skybox { gl, program, cube, vertex_array })
+    }
+
+    pub fn cube(&self) -> &Rc<TextureCube> {
+        &self.cube
+    }
+
+    pub fn set_cube(&mut self, cube: Rc<TextureCube>) {
+        self.cube = cube;
+    }
+
+    /// See `ShaderProgram::reload_if_changed`
+    pub fn reload_if_changed(&mut self, res: &Resources) -> Result<bool, shader::Error> {
+        self.program.reload_if_changed(res)
+    }
+
+    /**
+    Draws the sky as seen from `camera`, only its rotation is used so the sky stays at infinity
+    */
+    pub fn render(&self, camera: &Camera) -> Result<(), shader::Error> {
+        let view_projection = camera.get_projection_matrix() * camera.get_view_rotation_matrix();
+        let inverse_view_projection = view_projection.try_inverse().unwrap_or_else(Matrix4::identity);
+
+        self.program.bind();
+        shader::ignore_unknown_uniform(self.program.set_uniform("InverseViewProjection", &inverse_view_projection))?;
+        shader::ignore_unknown_uniform(self.program.bind_texture("Sky", self.cube.as_ref()))?;
+        self.vertex_array.bind();
+        unsafe {
+            // The far plane has depth 1, which only passes where the depth buffer was cleared
+            self.gl.DepthFunc(gl::LEQUAL);
+            self.gl.DepthMask(gl::FALSE);
+            self.gl.DrawArrays(gl::TRIANGLES, 0, 3);
+            self.gl.DepthMask(gl::TRUE);
+            self.gl.DepthFunc(gl::LESS);
+        }
+        self.vertex_array.unbind();
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+/*
+* Components and systems of the playground scene
+*
+* Expected world resources: `EventState`, `DeltaTime`, `FixedStepAlpha`, `MouseDrag`, `SceneGraph`
+* and `ActiveCamera`, optionally a `Skybox`, the `LightBuffer` filled by `lighting_system`, the
+* `ShadowMaps` rendered by `shadow_system` and the `ImageBasedLighting` of shaders/pbr.
+*/
+
+use std::rc::Rc;
+
+use nalgebra::{Matrix4, Unit, UnitQuaternion, Vector2, Vector3};
+
+use crate::ecs::{Entity, World};
+
+use super::shader::{self, ignore_unknown_uniform};
+use super::{Camera, Drawable, EventState, ImageBasedLighting, KeyCode, Light, LightBuffer, Material, NodeId, SceneGraph};
+use super::{ShadowMaps, Skybox, LIGHTS_BINDING};
+
+/// Seconds since the previous update
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DeltaTime(pub f32);
+
+/// Fraction of a fixed step elapsed since the last fixed update, see `FrameClock::alpha`
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FixedStepAlpha(pub f32);
+
+/// Mouse movement in pixels while dragging with the left button, since the last update
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MouseDrag(pub Vector2<f32>);
+
+/// The camera entity the scene is rendered from
+#[derive(Copy, Clone, Debug)]
+pub struct ActiveCamera(pub Entity);
+
+/// Places an entity at the world transform of a node in the `SceneGraph` resource
+#[derive(Copy, Clone, Debug)]
+pub struct SceneNode(pub NodeId);
+
+/**
+Draws a mesh with a `Material` at the entity's `SceneNode`, or at the origin without one
+
+Renderers can share materials and materials can share programs.
+*/
+pub struct MeshRenderer {
+    pub mesh: Rc<dyn Drawable>,
+    pub material: Rc<Material>,
+}
+
+/// Marks a directional or spot `Light` as casting shadows into the `ShadowMaps` resource
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ShadowCaster;
+
+/// Moves a `Camera` with WASD and rotates it by mouse drags
+#[derive(Copy, Clone, Debug)]
+pub struct CameraController {
+    /// Units per second
+    pub speed: f32,
+    /// Radians per dragged pixel
+    pub sensitivity: f32,
+}
+
+/**
+Rotates the entity's `SceneNode` around `axis`, replacing its rotation
+
+The angle advances in `spin_system` and is shown interpolated between the last two fixed
+steps by `spin_interpolation_system`.
+*/
+#[derive(Copy, Clone, Debug)]
+pub struct Spin {
+    pub axis: Unit<Vector3<f32>>,
+    /// Radians per second
+    pub speed: f32,
+    /// Angles in radians before and after the last fixed step
+    angles: (f32, f32),
+}
+
+impl Spin {
+    pub fn new(axis: Unit<Vector3<f32>>, speed: f32) -> Self {
+        Spin { axis, speed, angles: (0.0, 0.0) }
+    }
+}
+
+pub fn camera_controller_system(world: &mut World) {
+    let events = world.resource::<EventState>();
+    let dt = world.resource::<DeltaTime>().0;
+    let drag = std::mem::take(&mut world.resource_mut::<MouseDrag>().0);
+    let controllers = world.storage::<CameraController>();
+    let mut cameras = world.storage_mut::<Camera>();
+
+    const DIRECTIONS: [(KeyCode, [f32; 3]); 4] = [
+        (KeyCode::A, [-1.0, 0.0, 0.0]),
+        (KeyCode::D, [1.0, 0.0, 0.0]),
+        (KeyCode::W, [0.0, 0.0, -1.0]),
+        (KeyCode::S, [0.0, 0.0, 1.0]),
+    ];
+
+    for (entity, controller) in controllers.iter() {
+        if let Some(camera) = cameras.get_mut(entity) {
+            for (key, [x, y, z]) in DIRECTIONS.iter() {
+                if events.is_pressed(key) {
+                    camera.move_camera(Vector3::new(*x, *y, *z), controller.speed * dt);
+                }
+            }
+            if drag != Vector2::zeros() {
+                let rotation = drag * controller.sensitivity;
+                camera.rotate_camera(Vector3::new(rotation.x, rotation.y, 0.));
+            }
+        }
+    }
+}
+
+/// Advances every `Spin` by `DeltaTime`, meant to run once per fixed step
+pub fn spin_system(world: &mut World) {
+    let dt = world.resource::<DeltaTime>().0;
+    for (_, spin) in world.storage_mut::<Spin>().iter_mut() {
+        spin.angles = (spin.angles.1, spin.angles.1 + spin.speed * dt);
+    }
+}
+
+/// Rotates the nodes of every `Spin` to its angle interpolated by `FixedStepAlpha`
+pub fn spin_interpolation_system(world: &mut World) {
+    let alpha = world.resource::<FixedStepAlpha>().0;
+    let nodes = world.storage::<SceneNode>();
+    let mut graph = world.resource_mut::<SceneGraph>();
+
+    for (entity, spin) in world.storage::<Spin>().iter() {
+        if let Some(SceneNode(node)) = nodes.get(entity) {
+            let (previous, current) = spin.angles;
+            let angle = previous + (current - previous) * alpha;
+            graph.node_mut(*node).set_rotation(UnitQuaternion::from_axis_angle(&spin.axis, angle));
+        }
+    }
+}
+
+/**
+Draws every `MeshRenderer` from the `ActiveCamera`, applying its material and setting the
+program's `View`, `Perspective`, `CameraPosition` and `Model` uniforms. Binds the `Lights`
+block if there is a `LightBuffer`, and the `ShadowMaps` and `ImageBasedLighting` resources
+if there are any.
+*/
+pub fn mesh_renderer_system(world: &mut World) {
+    let ActiveCamera(camera) = *world.resource::<ActiveCamera>();
+    let camera = world.get::<Camera>(camera);
+    let nodes = world.storage::<SceneNode>();
+    let mut graph = world.resource_mut::<SceneGraph>();
+    graph.update_world_transforms();
+    let has_lights = world.has_resource::<LightBuffer>();
+    let shadow_maps = if world.has_resource::<ShadowMaps>() {
+        Some(world.resource::<ShadowMaps>())
+    } else {
+        None
+    };
+    let ibl = if world.has_resource::<ImageBasedLighting>() {
+        Some(world.resource::<ImageBasedLighting>())
+    } else {
+        None
+    };
+
+    for (entity, renderer) in world.storage::<MeshRenderer>().iter() {
+        let model = nodes
+            .get(entity)
+            .map(|SceneNode(node)| *graph.world_transform(*node))
+            .unwrap_or_else(Matrix4::identity);
+        let bound = bind_mesh_renderer(renderer, camera.as_deref(), has_lights, shadow_maps.as_deref(), ibl.as_deref(), &model);
+        match bound {
+            Ok(()) => renderer.mesh.draw(),
+            Err(e) => eprintln!("Failed to draw the mesh of entity {:?}: {}", entity, e),
+        }
+    }
+}
+
+/// Applies the material of `renderer` and sets the uniforms `mesh_renderer_system` provides
+fn bind_mesh_renderer(renderer: &MeshRenderer, camera: Option<&Camera>, has_lights: bool, shadow_maps: Option<&ShadowMaps>,
+    ibl: Option<&ImageBasedLighting>, model: &Matrix4<f32>) -> Result<(), shader::Error> {
+    ignore_unknown_uniform(renderer.material.apply())?;
+    let program = renderer.material.program();
+    if let Some(camera) = camera {
+        ignore_unknown_uniform(program.set_uniform("View", &camera.get_view_matrix()))?;
+        ignore_unknown_uniform(program.set_uniform("Perspective", &camera.get_projection_matrix()))?;
+        ignore_unknown_uniform(program.set_uniform("CameraPosition", &camera.get_position()))?;
+    }
+    // Unlit shaders have no lights block, no shadows and no environment
+    if has_lights && program.has_uniform_block("Lights") {
+        program.bind_uniform_block("Lights", LIGHTS_BINDING)?;
+    }
+    if let Some(shadow_maps) = shadow_maps.filter(|_| program.has_uniform_block("Shadows")) {
+        ignore_unknown_uniform(shadow_maps.bind(&program))?;
+    }
+    if let Some(ibl) = ibl {
+        ignore_unknown_uniform(ibl.bind(&program))?;
+    }
+    ignore_unknown_uniform(program.set_uniform("Model", model))
+}
+
+/**
+Uploads every `Light`, placed by the entity's `SceneNode`, into the `LightBuffer` resource and
+binds it to `LIGHTS_BINDING`, if there is a `LightBuffer`
+*/
+pub fn lighting_system(world: &mut World) {
+    if !world.has_resource::<LightBuffer>() {
+        return;
+    }
+    let lights: Vec<Light> = placed_lights(world).into_iter().map(|(_, light)| light).collect();
+
+    let light_buffer = world.resource::<LightBuffer>();
+    light_buffer.upload(&lights);
+    light_buffer.bind_base(LIGHTS_BINDING);
+}
+
+/**
+Renders the `ShadowMaps` resource, if any, for every `Light` with a `ShadowCaster` and cascades
+fit to the `ActiveCamera`, drawing every `MeshRenderer` as a shadow caster
+*/
+pub fn shadow_system(world: &mut World) {
+    if !world.has_resource::<ShadowMaps>() {
+        return;
+    }
+    let ActiveCamera(camera) = *world.resource::<ActiveCamera>();
+    let camera = match world.get::<Camera>(camera) {
+        Some(camera) => camera,
+        None => return,
+    };
+    let shadow_casters = world.storage::<ShadowCaster>();
+    let placed = placed_lights(world);
+    // Indices into `lights`, which has the order of the `LightBuffer`
+    let casters: Vec<usize> = placed
+        .iter()
+        .enumerate()
+        .filter(|(_, (entity, _))| shadow_casters.contains(*entity))
+        .map(|(index, _)| index)
+        .collect();
+    let lights: Vec<Light> = placed.into_iter().map(|(_, light)| light).collect();
+
+    let nodes = world.storage::<SceneNode>();
+    let graph = world.resource::<SceneGraph>();
+    let renderers = world.storage::<MeshRenderer>();
+    let rendered = world.resource_mut::<ShadowMaps>().render(&camera, &lights, &casters, |program| {
+        for (entity, renderer) in renderers.iter() {
+            let model = nodes
+                .get(entity)
+                .map(|SceneNode(node)| *graph.world_transform(*node))
+                .unwrap_or_else(Matrix4::identity);
+            ignore_unknown_uniform(program.set_uniform("Model", &model))?;
+            renderer.mesh.draw();
+        }
+        Ok(())
+    });
+    if let Err(e) = rendered {
+        eprintln!("Failed to render shadow maps: {}", e);
+    }
+}
+
+/// Every `Light` placed by the entity's `SceneNode`, in the order of the `Light` storage
+fn placed_lights(world: &World) -> Vec<(Entity, Light)> {
+    let nodes = world.storage::<SceneNode>();
+    let mut graph = world.resource_mut::<SceneGraph>();
+    graph.update_world_transforms();
+
+    world
+        .storage::<Light>()
+        .iter()
+        .map(|(entity, light)| match nodes.get(entity) {
+            Some(SceneNode(node)) => (entity, light.transformed(graph.world_transform(*node))),
+            None => (entity, *light),
+        })
+        .collect()
+}
+
+/// Draws the `Skybox` resource, if any, from the `ActiveCamera` behind the rendered meshes
+pub fn skybox_system(world: &mut World) {
+    if !world.has_resource::<Skybox>() {
+        return;
+    }
+    let ActiveCamera(camera) = *world.resource::<ActiveCamera>();
+    if let Some(camera) = world.get::<Camera>(camera) {
+        if let Err(e) = world.resource::<Skybox>().render(&camera) {
+            eprintln!("Failed to render the skybox: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glutin::event::ElementState;
+
+    use super::*;
+
+    #[test]
+    fn controller_and_spin_systems_use_delta_time() {
+        let mut world = World::new();
+        let mut graph = SceneGraph::new();
+        let mut events = EventState::new();
+        events.process_event(KeyCode::W, ElementState::Pressed);
+
+        let camera = world.spawn();
+        world.insert(camera, Camera::new(Vector3::zeros(), Vector3::zeros(), 1.0, 1.0, 0.1, 100.0, true));
+        world.insert(camera, CameraController { speed: 4.0, sensitivity: 0.01 });
+        let spinning = world.spawn();
+        let node = graph.add_node("spinning", None);
+        world.insert(spinning, SceneNode(node));
+        world.insert(spinning, Spin::new(Vector3::y_axis(), 2.0));
+
+        world.insert_resource(events);
+        world.insert_resource(DeltaTime(0.5));
+        world.insert_resource(FixedStepAlpha(1.0));
+        world.insert_resource(MouseDrag::default());
+        world.insert_resource(graph);
+
+        camera_controller_system(&mut world);
+        spin_system(&mut world);
+        spin_interpolation_system(&mut world);
+
+        // Moved 2 units forward, i.e. down -Z
+        let view_origin = world.get::<Camera>(camera).unwrap().get_view_matrix() * nalgebra::Vector4::new(0., 0., -2., 1.);
+        assert!((view_origin - nalgebra::Vector4::new(0., 0., 0., 1.)).norm() < 1e-5);
+        let angle = world.resource::<SceneGraph>().node(node).rotation().angle();
+        assert!((angle - 1.0).abs() < 1e-5);
+
+        // Halfway between the angles after the first and second step
+        spin_system(&mut world);
+        world.resource_mut::<FixedStepAlpha>().0 = 0.5;
+        spin_interpolation_system(&mut world);
+        let angle = world.resource::<SceneGraph>().node(node).rotation().angle();
+        assert!((angle - 1.5).abs() < 1e-5);
+    }
+}
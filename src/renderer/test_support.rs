@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::resources::Resources;
+
+/// Vertex shader of a triangle covering the whole viewport, without any vertex buffers
+pub const FULLSCREEN_VERTEX_SOURCE: &str = include_str!("../../assets/shaders/post/fullscreen.vert");
+
+/**
+A directory of resources written by one test, removed on drop so that it is cleaned up even
+when an assertion fails
+*/
+pub struct TempResources {
+    root: PathBuf,
+}
+
+impl TempResources {
+    /// Creates an empty directory for `name`, unique to this process
+    pub fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("playground_{}_{}", name, std::process::id()));
+        // Left behind by a test run that was killed
+        fs::remove_dir_all(&root).ok();
+        fs::create_dir_all(&root).unwrap();
+        TempResources { root }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn resources(&self) -> Resources {
+        Resources::from_path(&self.root)
+    }
+
+    /// Writes `contents` to the resource `name`, creating its directories
+    pub fn write<C: AsRef<[u8]>>(&self, name: &str, contents: C) -> PathBuf {
+        let path = self.root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /**
+    Like `write`, with a modification time `age` seconds in the past
+
+    Explicit times, file system timestamps can be too coarse to see quick edits.
+    */
+    pub fn write_aged<C: AsRef<[u8]>>(&self, name: &str, contents: C, age: u64) -> PathBuf {
+        let path = self.write(name, contents);
+        let modified = SystemTime::now() - Duration::from_secs(age);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        path
+    }
+}
+
+impl Drop for TempResources {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.root).ok();
+    }
+}
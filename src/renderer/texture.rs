@@ -1,63 +1,357 @@
 use std::{ffi::c_void, rc::Rc};
 use gl::types::*;
+use image::{ImageBuffer, Luma, Rgb, RgbImage, Rgba};
 
-use crate::resources::{Resources, Error};
+use crate::resources::{HdrImage, Resources, Error};
+
+/**
+Internal format of a texture, see: glTexImage2D
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8,
+    Rg8,
+    Rgb8,
+    Rgba8,
+    /// sRGB encoded color, converted to linear when sampled
+    Srgb8,
+    Srgb8Alpha8,
+    Rgba16,
+    R16F,
+    Rg16F,
+    Rgba16F,
+    R32F,
+    Rg32F,
+    Rgba32F,
+    R11fG11fB10f,
+    Depth16,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+    Depth32FStencil8,
+}
+
+impl TextureFormat {
+    /// Internal format, pixel format and pixel type
+    pub fn gl_formats(self) -> (GLenum, GLenum, GLenum) {
+        match self {
+            TextureFormat::R8 => (gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+            TextureFormat::Rg8 => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+            TextureFormat::Rgb8 => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE),
+            TextureFormat::Rgba8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::Srgb8 => (gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE),
+            TextureFormat::Srgb8Alpha8 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::Rgba16 => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT),
+            TextureFormat::R16F => (gl::R16F, gl::RED, gl::FLOAT),
+            TextureFormat::Rg16F => (gl::RG16F, gl::RG, gl::FLOAT),
+            TextureFormat::Rgba16F => (gl::RGBA16F, gl::RGBA, gl::FLOAT),
+            TextureFormat::R32F => (gl::R32F, gl::RED, gl::FLOAT),
+            TextureFormat::Rg32F => (gl::RG32F, gl::RG, gl::FLOAT),
+            TextureFormat::Rgba32F => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+            TextureFormat::R11fG11fB10f => (gl::R11F_G11F_B10F, gl::RGB, gl::FLOAT),
+            TextureFormat::Depth16 => (gl::DEPTH_COMPONENT16, gl::DEPTH_COMPONENT, gl::UNSIGNED_SHORT),
+            TextureFormat::Depth24 => (gl::DEPTH_COMPONENT24, gl::DEPTH_COMPONENT, gl::UNSIGNED_INT),
+            TextureFormat::Depth32F => (gl::DEPTH_COMPONENT32F, gl::DEPTH_COMPONENT, gl::FLOAT),
+            TextureFormat::Depth24Stencil8 => (gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8),
+            TextureFormat::Depth32FStencil8 => (gl::DEPTH32F_STENCIL8, gl::DEPTH_STENCIL, gl::FLOAT_32_UNSIGNED_INT_24_8_REV),
+        }
+    }
+
+    pub fn is_depth(self) -> bool {
+        matches!(self.gl_formats().1, gl::DEPTH_COMPONENT | gl::DEPTH_STENCIL)
+    }
+
+    /// Half and single precision float formats, which hold values outside of [0, 1]
+    pub fn is_float(self) -> bool {
+        !self.is_depth() && self.gl_formats().2 == gl::FLOAT
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FilterMode {
+    Nearest,
+    Linear,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    /// Samples outside of [0, 1] return `TextureDescriptor::border_color`
+    ClampToBorder,
+}
+
+impl WrapMode {
+    pub(super) fn gl_enum(self) -> GLenum {
+        match self {
+            WrapMode::Repeat => gl::REPEAT,
+            WrapMode::MirroredRepeat => gl::MIRRORED_REPEAT,
+            WrapMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+            WrapMode::ClampToBorder => gl::CLAMP_TO_BORDER,
+        }
+    }
+}
+
+/**
+Storage format and sampling state of a texture
+
+The default is a mipmapped RGBA8 texture with trilinear filtering that repeats in both directions.
+*/
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextureDescriptor {
+    pub format: TextureFormat,
+    pub min_filter: FilterMode,
+    pub mag_filter: FilterMode,
+    /// Filter between mipmap levels, `None` disables mipmaps
+    pub mipmap_filter: Option<FilterMode>,
+    /// Wrap mode of the s and t coordinates
+    pub wrap: (WrapMode, WrapMode),
+    /// Maximum anisotropic filtering samples, clamped to what the driver supports, 1 disables it
+    pub anisotropy: f32,
+    pub border_color: [f32; 4],
+}
+
+impl Default for TextureDescriptor {
+    fn default() -> Self {
+        TextureDescriptor {
+            format: TextureFormat::Rgba8,
+            min_filter: FilterMode::Linear,
+            mag_filter: FilterMode::Linear,
+            mipmap_filter: Some(FilterMode::Linear),
+            wrap: (WrapMode::Repeat, WrapMode::Repeat),
+            anisotropy: 1.0,
+            border_color: [0.0; 4],
+        }
+    }
+}
+
+impl TextureDescriptor {
+    /**
+    A single level texture of `format` sampled with `filter`, clamped to its edges, as used
+    for render targets
+    */
+    pub fn render_target(format: TextureFormat, filter: FilterMode) -> Self {
+        TextureDescriptor {
+            format,
+            min_filter: filter,
+            mag_filter: filter,
+            mipmap_filter: None,
+            wrap: (WrapMode::ClampToEdge, WrapMode::ClampToEdge),
+            ..Default::default()
+        }
+    }
+
+    fn gl_min_filter(&self) -> GLenum {
+        match (self.min_filter, self.mipmap_filter) {
+            (FilterMode::Nearest, None) => gl::NEAREST,
+            (FilterMode::Linear, None) => gl::LINEAR,
+            (FilterMode::Nearest, Some(FilterMode::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (FilterMode::Nearest, Some(FilterMode::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (FilterMode::Linear, Some(FilterMode::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (FilterMode::Linear, Some(FilterMode::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+
+    fn gl_mag_filter(&self) -> GLenum {
+        match self.mag_filter {
+            FilterMode::Nearest => gl::NEAREST,
+            FilterMode::Linear => gl::LINEAR,
+        }
+    }
+}
+
+/**
+Images that can be uploaded to a texture, with their pixel format and pixel type
+*/
+pub trait TextureImage {
+    const PIXEL_FORMAT: GLenum;
+    const PIXEL_TYPE: GLenum;
+
+    fn dimensions(&self) -> (u32, u32);
+
+    fn data_ptr(&self) -> *const c_void;
+}
+
+macro_rules! impl_texture_image {
+    ($pixel:ident<$subpixel:ty>, $format:expr, $pixel_type:expr) => {
+        impl TextureImage for ImageBuffer<$pixel<$subpixel>, Vec<$subpixel>> {
+            const PIXEL_FORMAT: GLenum = $format;
+            const PIXEL_TYPE: GLenum = $pixel_type;
+
+            fn dimensions(&self) -> (u32, u32) {
+                ImageBuffer::dimensions(self)
+            }
+
+            fn data_ptr(&self) -> *const c_void {
+                self.as_ptr() as _
+            }
+        }
+    };
+}
+
+impl_texture_image!(Luma<u8>, gl::RED, gl::UNSIGNED_BYTE);
+impl_texture_image!(Rgb<u8>, gl::RGB, gl::UNSIGNED_BYTE);
+impl_texture_image!(Rgba<u8>, gl::RGBA, gl::UNSIGNED_BYTE);
+impl_texture_image!(Luma<u16>, gl::RED, gl::UNSIGNED_SHORT);
+impl_texture_image!(Rgba<u16>, gl::RGBA, gl::UNSIGNED_SHORT);
+impl_texture_image!(Rgb<f32>, gl::RGB, gl::FLOAT);
+impl_texture_image!(Rgba<f32>, gl::RGBA, gl::FLOAT);
+
+/**
+Textures that shaders can sample, see `ShaderProgram::bind_texture`
+*/
+pub trait SampledTexture {
+    /**
+    Binds the texture to texture unit `unit` without changing the active unit
+
+    see: glBindTextureUnit
+    */
+    fn bind_to_unit(&self, unit: u32);
+
+    /// Texture target, e.g. `TEXTURE_2D`, which decides the sampler types that can sample it
+    fn target(&self) -> GLenum;
+}
+
+/**
+An image decoded by the loader matching a `TextureFormat`, see `Texture::from_resource_with_descriptor`
+*/
+pub(super) enum LoadedImage {
+    /// 16 bit luminance, uploaded as depth values
+    Depth16(ImageBuffer<Luma<u16>, Vec<u16>>),
+    Rgba8(ImageBuffer<Rgba<u8>, Vec<u8>>),
+    Rgba16(ImageBuffer<Rgba<u16>, Vec<u16>>),
+    Hdr(HdrImage),
+}
+
+impl LoadedImage {
+    pub(super) fn load(res: &Resources, name: &str, format: TextureFormat) -> Result<Self, Error> {
+        Ok(if format.is_depth() {
+            LoadedImage::Depth16(res.load_luma16_image(name)?)
+        } else if format.is_float() && name.to_lowercase().ends_with(".hdr") {
+            LoadedImage::Hdr(res.load_hdr_image(name)?)
+        } else if format.is_float() || format == TextureFormat::Rgba16 {
+            LoadedImage::Rgba16(res.load_rgba16_image(name)?)
+        } else {
+            LoadedImage::Rgba8(res.load_rgba_image(name)?)
+        })
+    }
+
+    pub(super) fn dimensions(&self) -> (u32, u32) {
+        match self {
+            LoadedImage::Depth16(image) => image.dimensions(),
+            LoadedImage::Rgba8(image) => image.dimensions(),
+            LoadedImage::Rgba16(image) => image.dimensions(),
+            LoadedImage::Hdr(image) => image.dimensions(),
+        }
+    }
+
+    /// Pixel format, pixel type and a pointer to the pixels
+    pub(super) fn pixels(&self) -> (GLenum, GLenum, *const c_void) {
+        fn pixels<I: TextureImage>(image: &I) -> (GLenum, GLenum, *const c_void) {
+            (I::PIXEL_FORMAT, I::PIXEL_TYPE, image.data_ptr())
+        }
+
+        match self {
+            LoadedImage::Depth16(image) => (gl::DEPTH_COMPONENT, gl::UNSIGNED_SHORT, image.data_ptr()),
+            LoadedImage::Rgba8(image) => pixels(image),
+            LoadedImage::Rgba16(image) => pixels(image),
+            LoadedImage::Hdr(image) => pixels(image),
+        }
+    }
+}
+
+/// Sets the filters, wrap modes, anisotropy and border color of `descriptor` on the texture `id`
+pub(super) fn apply_sampling(gl: &gl::Gl, id: GLuint, descriptor: &TextureDescriptor) {
+    unsafe {
+        gl.TextureParameteri(id, gl::TEXTURE_MIN_FILTER, descriptor.gl_min_filter() as GLint);
+        gl.TextureParameteri(id, gl::TEXTURE_MAG_FILTER, descriptor.gl_mag_filter() as GLint);
+        gl.TextureParameteri(id, gl::TEXTURE_WRAP_S, descriptor.wrap.0.gl_enum() as GLint);
+        gl.TextureParameteri(id, gl::TEXTURE_WRAP_T, descriptor.wrap.1.gl_enum() as GLint);
+        gl.TextureParameterfv(id, gl::TEXTURE_BORDER_COLOR, descriptor.border_color.as_ptr());
+        if descriptor.anisotropy > 1.0 {
+            let mut max_anisotropy: GLfloat = 1.0;
+            gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+            gl.TextureParameterf(id, gl::TEXTURE_MAX_ANISOTROPY, descriptor.anisotropy.min(max_anisotropy));
+        }
+    }
+}
 
 pub struct Texture {
     gl: Rc<gl::Gl>,
     id: GLuint,
     width: u32,
     height: u32,
-    img_ptr: *const c_void, 
+    descriptor: TextureDescriptor,
 }
 
 impl Texture {
     pub fn empty(gl: Rc<gl::Gl>, width: u32, height: u32) -> Self {
-        let mut id: GLuint = 0;
-        let img_ptr: *const c_void = std::ptr::null();
-        unsafe {
-            gl.GenTextures(1, &mut id);
-            gl.BindTexture(gl::TEXTURE_2D, id);
+        Self::new(gl, width, height, &TextureDescriptor::render_target(TextureFormat::Rgb8, FilterMode::Linear))
+    }
 
-            gl.TexImage2D(gl::TEXTURE_2D, 0, gl::RGB8 as GLint, width as i32, height as i32, 0, gl::RGB, gl::UNSIGNED_BYTE, img_ptr);
-    
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32); 
-    
-            gl.BindTexture(gl::TEXTURE_2D, 0);
-        }
+    /**
+    Allocates an uninitialized texture, e.g. a `TextureFormat::Depth24` render target
 
-        Texture {
-            gl,
-            id, 
-            width, 
-            height,
-            img_ptr,
-        }
+    see: glTexImage2D
+    */
+    pub fn new(gl: Rc<gl::Gl>, width: u32, height: u32, descriptor: &TextureDescriptor) -> Self {
+        let (_, format, pixel_type) = descriptor.format.gl_formats();
+        Self::from_raw(gl, width, height, descriptor, format, pixel_type, std::ptr::null())
+    }
+
+    /**
+    Loads an image with the loader matching `format`: 8 bit RGBA for normalized 8 bit formats,
+    16 bit RGBA for `Rgba16`, 32 bit float RGB from .hdr files (16 bit RGBA from other files) for
+    float formats and the 16 bit luminance as depth values for depth formats
+    */
+    pub fn from_resource_with_descriptor(gl: Rc<gl::Gl>, res: &Resources, name: &str, descriptor: &TextureDescriptor) -> Result<Self, Error> {
+        let image = LoadedImage::load(res, name, descriptor.format)?;
+        let (width, height) = image.dimensions();
+        let (format, pixel_type, data) = image.pixels();
+        Ok(Self::from_raw(gl, width, height, descriptor, format, pixel_type, data))
     }
 
+    /// Loads an image with alpha as a mipmapped RGBA8 texture
     pub fn from_resource(gl: Rc<gl::Gl>, res: &Resources, name: &str) -> Result<Self, Error> {
+        Self::from_resource_with_descriptor(gl, res, name, &TextureDescriptor::default())
+    }
+
+    pub fn from_image(gl: Rc<gl::Gl>, img: &RgbImage) -> Self {
+        Self::from_image_with_descriptor(gl, img, &TextureDescriptor { format: TextureFormat::Rgb8, ..Default::default() })
+    }
+
+    /**
+    Uploads `img`, converting it to `descriptor.format`, and generates mipmaps if the
+    descriptor has a mipmap filter
+    */
+    pub fn from_image_with_descriptor<I>(gl: Rc<gl::Gl>, img: &I, descriptor: &TextureDescriptor) -> Self
+    where I: TextureImage, {
+        let (width, height) = img.dimensions();
+        Self::from_raw(gl, width, height, descriptor, I::PIXEL_FORMAT, I::PIXEL_TYPE, img.data_ptr())
+    }
+
+    fn from_raw(gl: Rc<gl::Gl>, width: u32, height: u32, descriptor: &TextureDescriptor,
+        format: GLenum, pixel_type: GLenum, data: *const c_void) -> Self {
         let mut id: GLuint = 0;
         unsafe { gl.GenTextures(1, &mut id); };
-        let img = res.load_rgb_image(name)?;
-        
-        let texture = Texture{ gl: Rc::clone(&gl), id, width: img.width(), height: img.height(), img_ptr: img.as_ptr() as _, };
-        
+
+        let texture = Texture { gl: Rc::clone(&gl), id, width, height, descriptor: *descriptor };
+
         texture.bind();
         unsafe {
-            gl.TexImage2D(
-                gl::TEXTURE_2D, 0, gl::RGB8 as GLint,
-                img.width() as i32,
-                img.height() as i32,
-                0,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
-                img.as_ptr() as _,
-            );
-            gl.GenerateMipmap(gl::TEXTURE_2D);
-        }      
+            let (internal_format, _, _) = descriptor.format.gl_formats();
+            // Rows of single channel and RGB images are not always 4 byte aligned
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl.TexImage2D(gl::TEXTURE_2D, 0, internal_format as GLint, width as i32, height as i32, 0, format, pixel_type, data);
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            if descriptor.mipmap_filter.is_some() {
+                gl.GenerateMipmap(gl::TEXTURE_2D);
+            }
+        }
         texture.unbind();
-        Ok(texture)
+        apply_sampling(&gl, id, descriptor);
+        texture
     }
 
     pub fn bind(&self) {
@@ -72,12 +366,24 @@ impl Texture {
         }
     }
 
-    pub fn resize(&self,  width: u32, height: u32) {
+    /**
+    Reallocates the texture at `width` x `height`, discarding its contents
+
+    The texture keeps its id, so frame buffers it is attached to stay valid. Mipmapped textures
+    get a new, undefined mipmap chain.
+    */
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
         self.bind();
         unsafe {
-            self.gl.TexImage2D(gl::TEXTURE_2D, 0, gl::RGB8 as GLint, width as i32, height as i32, 0, gl::RGB, gl::UNSIGNED_BYTE, self.img_ptr);
+            let (internal_format, format, pixel_type) = self.descriptor.format.gl_formats();
+            self.gl.TexImage2D(gl::TEXTURE_2D, 0, internal_format as GLint, width as i32, height as i32, 0, format, pixel_type, std::ptr::null());
+            if self.descriptor.mipmap_filter.is_some() {
+                self.gl.GenerateMipmap(gl::TEXTURE_2D);
+            }
         }
-        self.bind();
+        self.unbind();
     }
 
     pub fn get_id(&self) -> GLuint {
@@ -87,6 +393,22 @@ impl Texture {
     pub fn get_size(&self) -> (u32, u32) {
         (self.width, self.height)
     }
+
+    pub fn descriptor(&self) -> &TextureDescriptor {
+        &self.descriptor
+    }
+}
+
+impl SampledTexture for Texture {
+    fn bind_to_unit(&self, unit: u32) {
+        unsafe {
+            self.gl.BindTextureUnit(unit, self.id);
+        }
+    }
+
+    fn target(&self) -> GLenum {
+        gl::TEXTURE_2D
+    }
 }
 
 impl Drop for Texture {
@@ -95,4 +417,189 @@ impl Drop for Texture {
             self.gl.DeleteTextures(1, &self.id);
         }
     }
-}
\ No newline at end of file
+}
+
+/**
+Sampling state that overrides the state of any texture bound to the same texture unit,
+e.g. to sample one texture both with and without filtering
+*/
+pub struct Sampler {
+    gl: Rc<gl::Gl>,
+    id: GLuint,
+}
+
+impl Sampler {
+    /// A sampler with the filters, wrap modes, anisotropy and border color of `descriptor`, its format is ignored
+    pub fn new(gl: Rc<gl::Gl>, descriptor: &TextureDescriptor) -> Self {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl.CreateSamplers(1, &mut id);
+            gl.SamplerParameteri(id, gl::TEXTURE_MIN_FILTER, descriptor.gl_min_filter() as GLint);
+            gl.SamplerParameteri(id, gl::TEXTURE_MAG_FILTER, descriptor.gl_mag_filter() as GLint);
+            gl.SamplerParameteri(id, gl::TEXTURE_WRAP_S, descriptor.wrap.0.gl_enum() as GLint);
+            gl.SamplerParameteri(id, gl::TEXTURE_WRAP_T, descriptor.wrap.1.gl_enum() as GLint);
+            gl.SamplerParameterfv(id, gl::TEXTURE_BORDER_COLOR, descriptor.border_color.as_ptr());
+            if descriptor.anisotropy > 1.0 {
+                let mut max_anisotropy: GLfloat = 1.0;
+                gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+                gl.SamplerParameterf(id, gl::TEXTURE_MAX_ANISOTROPY, descriptor.anisotropy.min(max_anisotropy));
+            }
+        }
+
+        Sampler { gl, id }
+    }
+
+    /// see: glBindSampler
+    pub fn bind_to_unit(&self, unit: u32) {
+        unsafe {
+            self.gl.BindSampler(unit, self.id);
+        }
+    }
+
+    /// Restores sampling with the texture's own state on `unit`
+    pub fn unbind_unit(&self, unit: u32) {
+        unsafe {
+            self.gl.BindSampler(unit, 0);
+        }
+    }
+
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteSamplers(1, &self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::renderer::test_support::TempResources;
+    use crate::renderer::HeadlessContext;
+
+    fn read_rgba_floats(gl: &gl::Gl, texture: &Texture) -> Vec<f32> {
+        let (width, height) = texture.get_size();
+        let mut pixels = vec![0.0f32; (width * height * 4) as usize];
+        unsafe {
+            gl.GetTextureImage(texture.get_id(), 0, gl::RGBA, gl::FLOAT,
+                (pixels.len() * std::mem::size_of::<f32>()) as GLsizei, pixels.as_mut_ptr() as *mut c_void);
+        }
+        pixels
+    }
+
+    fn texture_parameter(gl: &gl::Gl, texture: &Texture, parameter: GLenum) -> GLint {
+        let mut value: GLint = 0;
+        unsafe { gl.GetTextureParameteriv(texture.get_id(), parameter, &mut value) };
+        value
+    }
+
+    #[test]
+    fn loads_images_for_descriptor_formats() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("texture_formats");
+        let res = temp.resources();
+
+        let hdr_pixels = [Rgb([4.0f32, 0.5, 0.0]), Rgb([0.0, 1.0, 16.0])];
+        image::codecs::hdr::HdrEncoder::new(fs::File::create(temp.path().join("sky.hdr")).unwrap())
+            .encode(&hdr_pixels, 2, 1)
+            .unwrap();
+        image::RgbaImage::from_raw(2, 1, vec![255, 0, 0, 128, 0, 255, 0, 255]).unwrap()
+            .save(temp.path().join("translucent.png"))
+            .unwrap();
+
+        let hdr = Texture::from_resource_with_descriptor(Rc::clone(&gl), &res, "sky.hdr",
+            &TextureDescriptor { format: TextureFormat::Rgba32F, ..Default::default() }).unwrap();
+        assert_eq!(read_rgba_floats(&gl, &hdr), [4.0, 0.5, 0.0, 1.0, 0.0, 1.0, 16.0, 1.0]);
+
+        let translucent = Texture::from_resource(Rc::clone(&gl), &res, "translucent.png").unwrap();
+        assert_eq!(translucent.get_size(), (2, 1));
+        assert!((read_rgba_floats(&gl, &translucent)[3] - 128.0 / 255.0).abs() < 1e-6);
+
+        let half_float = Texture::from_resource_with_descriptor(Rc::clone(&gl), &res, "translucent.png",
+            &TextureDescriptor { format: TextureFormat::Rgba16F, ..Default::default() }).unwrap();
+        // Drivers may round when converting normalized integers to half floats
+        assert!((read_rgba_floats(&gl, &half_float)[3] - 128.0 / 255.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn loads_images_as_depth_values_for_depth_formats() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let temp = TempResources::new("texture_depth");
+        image::ImageBuffer::<Luma<u16>, Vec<u16>>::from_raw(2, 1, vec![0, 32768]).unwrap()
+            .save(temp.path().join("depth.png"))
+            .unwrap();
+
+        let descriptor = TextureDescriptor::render_target(TextureFormat::Depth16, FilterMode::Nearest);
+        let depth = Texture::from_resource_with_descriptor(Rc::clone(&gl), &temp.resources(), "depth.png", &descriptor).unwrap();
+        let mut pixels = [1.0f32; 2];
+        unsafe {
+            assert_eq!(gl.GetError(), gl::NO_ERROR);
+            gl.GetTextureImage(depth.get_id(), 0, gl::DEPTH_COMPONENT, gl::FLOAT,
+                (pixels.len() * std::mem::size_of::<f32>()) as GLsizei, pixels.as_mut_ptr() as *mut c_void);
+        }
+        assert_eq!(pixels[0], 0.0);
+        assert!((pixels[1] - 32768.0 / 65535.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn resize_reallocates_image_textures() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let mut texture = {
+            let image = image::RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255]));
+            Texture::from_image_with_descriptor(Rc::clone(&gl), &image, &TextureDescriptor::default())
+        };
+
+        texture.resize(16, 8);
+        assert_eq!(texture.get_size(), (16, 8));
+        let (mut width, mut height, mut levels) = (0, 0, 0);
+        unsafe {
+            gl.GetTextureLevelParameteriv(texture.get_id(), 0, gl::TEXTURE_WIDTH, &mut width);
+            gl.GetTextureLevelParameteriv(texture.get_id(), 0, gl::TEXTURE_HEIGHT, &mut height);
+            gl.GetTextureLevelParameteriv(texture.get_id(), 4, gl::TEXTURE_WIDTH, &mut levels);
+        }
+        assert_eq!((width, height), (16, 8));
+        // The mipmap chain follows the new size down to 1x1
+        assert_eq!(levels, 1);
+        assert_eq!(read_rgba_floats(&gl, &texture).len(), 16 * 8 * 4);
+    }
+
+    #[test]
+    fn applies_sampling_state() {
+        let context = HeadlessContext::from_size(1, 1).unwrap();
+        let gl = context.get_gl_handle();
+        let descriptor = TextureDescriptor {
+            format: TextureFormat::Srgb8Alpha8,
+            min_filter: FilterMode::Nearest,
+            mag_filter: FilterMode::Nearest,
+            mipmap_filter: Some(FilterMode::Linear),
+            wrap: (WrapMode::ClampToBorder, WrapMode::MirroredRepeat),
+            anisotropy: 4.0,
+            border_color: [1.0, 0.0, 0.0, 1.0],
+        };
+        let texture = Texture::new(Rc::clone(&gl), 4, 4, &descriptor);
+
+        assert_eq!(texture_parameter(&gl, &texture, gl::TEXTURE_MIN_FILTER) as GLenum, gl::NEAREST_MIPMAP_LINEAR);
+        assert_eq!(texture_parameter(&gl, &texture, gl::TEXTURE_MAG_FILTER) as GLenum, gl::NEAREST);
+        assert_eq!(texture_parameter(&gl, &texture, gl::TEXTURE_WRAP_S) as GLenum, gl::CLAMP_TO_BORDER);
+        assert_eq!(texture_parameter(&gl, &texture, gl::TEXTURE_WRAP_T) as GLenum, gl::MIRRORED_REPEAT);
+        assert!(texture_parameter(&gl, &texture, gl::TEXTURE_MAX_ANISOTROPY) > 1);
+        let mut border_color = [0.0f32; 4];
+        let mut internal_format: GLint = 0;
+        unsafe {
+            gl.GetTextureParameterfv(texture.get_id(), gl::TEXTURE_BORDER_COLOR, border_color.as_mut_ptr());
+            gl.GetTextureLevelParameteriv(texture.get_id(), 0, gl::TEXTURE_INTERNAL_FORMAT, &mut internal_format);
+        }
+        assert_eq!(border_color, descriptor.border_color);
+        assert_eq!(internal_format as GLenum, gl::SRGB8_ALPHA8);
+    }
+}
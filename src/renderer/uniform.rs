@@ -0,0 +1,166 @@
+use gl::types::*;
+use nalgebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4};
+
+/// An active uniform of a linked program, see glGetActiveUniform
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UniformInfo {
+    pub location: GLint,
+    pub gl_type: GLenum,
+    /// Number of elements for arrays, 1 otherwise
+    pub size: GLint,
+}
+
+/// Texture unit index to assign to sampler uniforms
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureUnit(pub u32);
+
+/**
+A Rust value that can be assigned to a uniform of one of `GL_TYPES`
+*/
+pub trait UniformValue {
+    const GL_TYPES: &'static [GLenum];
+
+    /**
+    Assigns the value to `location` of `program`
+
+    see: glProgramUniform
+    */
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint);
+}
+
+impl UniformValue for f32 {
+    const GL_TYPES: &'static [GLenum] = &[gl::FLOAT];
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1f(program, location, *self) }
+    }
+}
+
+impl UniformValue for i32 {
+    const GL_TYPES: &'static [GLenum] = &[gl::INT, gl::BOOL];
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1i(program, location, *self) }
+    }
+}
+
+impl UniformValue for Vector2<f32> {
+    const GL_TYPES: &'static [GLenum] = &[gl::FLOAT_VEC2];
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform2fv(program, location, 1, self.as_ptr()) }
+    }
+}
+
+impl UniformValue for Vector3<f32> {
+    const GL_TYPES: &'static [GLenum] = &[gl::FLOAT_VEC3];
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform3fv(program, location, 1, self.as_ptr()) }
+    }
+}
+
+impl UniformValue for Vector4<f32> {
+    const GL_TYPES: &'static [GLenum] = &[gl::FLOAT_VEC4];
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform4fv(program, location, 1, self.as_ptr()) }
+    }
+}
+
+impl UniformValue for Matrix3<f32> {
+    const GL_TYPES: &'static [GLenum] = &[gl::FLOAT_MAT3];
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniformMatrix3fv(program, location, 1, gl::FALSE, self.as_ptr()) }
+    }
+}
+
+impl UniformValue for Matrix4<f32> {
+    const GL_TYPES: &'static [GLenum] = &[gl::FLOAT_MAT4];
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniformMatrix4fv(program, location, 1, gl::FALSE, self.as_ptr()) }
+    }
+}
+
+impl UniformValue for TextureUnit {
+    const GL_TYPES: &'static [GLenum] = &[
+        gl::SAMPLER_1D,
+        gl::SAMPLER_2D,
+        gl::SAMPLER_3D,
+        gl::SAMPLER_CUBE,
+        gl::SAMPLER_1D_SHADOW,
+        gl::SAMPLER_2D_SHADOW,
+        gl::SAMPLER_1D_ARRAY,
+        gl::SAMPLER_2D_ARRAY,
+        gl::SAMPLER_2D_ARRAY_SHADOW,
+        gl::SAMPLER_CUBE_SHADOW,
+        gl::SAMPLER_2D_MULTISAMPLE,
+        gl::SAMPLER_BUFFER,
+        gl::INT_SAMPLER_2D,
+        gl::INT_SAMPLER_3D,
+        gl::UNSIGNED_INT_SAMPLER_2D,
+        gl::UNSIGNED_INT_SAMPLER_3D,
+    ];
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1i(program, location, self.0 as GLint) }
+    }
+}
+
+/// Whether uniforms of `gl_type` are samplers, which are assigned texture units
+pub fn is_sampler_type(gl_type: GLenum) -> bool {
+    TextureUnit::GL_TYPES.contains(&gl_type)
+}
+
+/// Texture target sampled by uniforms of the sampler type `gl_type`, e.g. `TEXTURE_2D` for `sampler2D`
+pub fn sampler_target(gl_type: GLenum) -> Option<GLenum> {
+    match gl_type {
+        gl::SAMPLER_1D | gl::SAMPLER_1D_SHADOW => Some(gl::TEXTURE_1D),
+        gl::SAMPLER_2D | gl::SAMPLER_2D_SHADOW | gl::INT_SAMPLER_2D | gl::UNSIGNED_INT_SAMPLER_2D => Some(gl::TEXTURE_2D),
+        gl::SAMPLER_3D | gl::INT_SAMPLER_3D | gl::UNSIGNED_INT_SAMPLER_3D => Some(gl::TEXTURE_3D),
+        gl::SAMPLER_CUBE | gl::SAMPLER_CUBE_SHADOW => Some(gl::TEXTURE_CUBE_MAP),
+        gl::SAMPLER_1D_ARRAY => Some(gl::TEXTURE_1D_ARRAY),
+        gl::SAMPLER_2D_ARRAY | gl::SAMPLER_2D_ARRAY_SHADOW => Some(gl::TEXTURE_2D_ARRAY),
+        gl::SAMPLER_2D_MULTISAMPLE => Some(gl::TEXTURE_2D_MULTISAMPLE),
+        gl::SAMPLER_BUFFER => Some(gl::TEXTURE_BUFFER),
+        _ => None,
+    }
+}
+
+/// GLSL name of a uniform type for error messages
+pub fn glsl_type_name(gl_type: GLenum) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::BOOL => "bool",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_1D_SHADOW => "sampler1DShadow",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_1D_ARRAY => "sampler1DArray",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        gl::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        gl::SAMPLER_2D_MULTISAMPLE => "sampler2DMS",
+        gl::SAMPLER_BUFFER => "samplerBuffer",
+        gl::INT_SAMPLER_2D => "isampler2D",
+        gl::INT_SAMPLER_3D => "isampler3D",
+        gl::UNSIGNED_INT_SAMPLER_2D => "usampler2D",
+        gl::UNSIGNED_INT_SAMPLER_3D => "usampler3D",
+        _ => "unknown",
+    }
+}
@@ -6,6 +6,7 @@ use glutin::event_loop::{EventLoop};
 use glutin::window::WindowBuilder;
 use nalgebra::{Vector3};
 
+#[allow(unused_imports)]
 use gl::types::*;
 
 pub struct Window {
@@ -0,0 +1,341 @@
+use std::path::Path;
+
+use gltf::camera::Projection;
+use gltf::image::Format;
+use gltf::mesh::Mode;
+use image::{DynamicImage, ImageBuffer, RgbImage};
+use nalgebra::{Isometry3, Matrix3, Matrix4, Rotation3, Translation3, UnitQuaternion, Vector3, Vector4};
+
+use crate::renderer::Camera;
+
+use super::{Error, MeshData, MeshVertex};
+
+/// Far plane used for glTF cameras with an infinite projection
+const DEFAULT_FAR_PLANE: f32 = 1000.0;
+
+/**
+A glTF 2.0 scene with all buffers and images decoded
+
+Indices between the lists follow the glTF document, e.g. `GltfNode::mesh` indexes `meshes`
+and `GltfMaterial::base_color_texture` indexes `images`, except for `GltfNode::camera`, which
+indexes `cameras`.
+*/
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub images: Vec<RgbImage>,
+    pub nodes: Vec<GltfNode>,
+    /// Root nodes of the default scene (or the first scene)
+    pub roots: Vec<usize>,
+    /// Cameras of the nodes in the scene placed at their world transform, in node order
+    pub cameras: Vec<Camera>,
+}
+
+pub struct GltfNode {
+    pub name: Option<String>,
+    pub mesh: Option<usize>,
+    /// Index into `GltfScene::cameras`, `None` for nodes outside of the scene
+    pub camera: Option<usize>,
+    pub children: Vec<usize>,
+    pub local_transform: Matrix4<f32>,
+    /// Product of all parent transforms and the local transform
+    pub world_transform: Matrix4<f32>,
+}
+
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+pub struct GltfPrimitive {
+    pub data: MeshData,
+    pub material: Option<usize>,
+}
+
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: Vector4<f32>,
+    pub base_color_texture: Option<usize>,
+}
+
+/**
+Imports a .gltf (with external or embedded buffers) or .glb file
+
+Only triangle list primitives are imported. Missing normals and uvs are filled with zeroes.
+*/
+pub fn import_gltf(path: &Path) -> Result<GltfScene, Error> {
+    let (document, buffers, images) = gltf::import(path)?;
+
+    let meshes = document
+        .meshes()
+        .map(|mesh| {
+            let primitives = mesh
+                .primitives()
+                .filter(|primitive| primitive.mode() == Mode::Triangles)
+                .map(|primitive| {
+                    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+                    let positions: Vec<[f32; 3]> = reader
+                        .read_positions()
+                        .ok_or(Error::InvalidGltf { message: "primitive has no positions".into() })?
+                        .collect();
+                    let mut normals = reader.read_normals().map(|normals| normals.collect::<Vec<_>>()).unwrap_or_default();
+                    normals.resize(positions.len(), [0.0; 3]);
+                    let mut uvs = reader.read_tex_coords(0).map(|uvs| uvs.into_f32().collect::<Vec<_>>()).unwrap_or_default();
+                    uvs.resize(positions.len(), [0.0; 2]);
+
+                    let vertices = positions
+                        .iter()
+                        .zip(normals.iter())
+                        .zip(uvs.iter())
+                        .map(|((&[x, y, z], &[nx, ny, nz]), &[u, v])| MeshVertex {
+                            position: (x, y, z).into(),
+                            normal: (nx, ny, nz).into(),
+                            uv: (u, v).into(),
+                        })
+                        .collect();
+                    let indices = match reader.read_indices() {
+                        Some(indices) => indices.into_u32().collect(),
+                        None => (0..positions.len() as u32).collect(),
+                    };
+
+                    Ok(GltfPrimitive {
+                        data: MeshData { vertices, indices },
+                        material: primitive.material().index(),
+                    })
+                })
+                .collect::<Result<Vec<GltfPrimitive>, Error>>()?;
+
+            Ok(GltfMesh {
+                name: mesh.name().map(String::from),
+                primitives,
+            })
+        })
+        .collect::<Result<Vec<GltfMesh>, Error>>()?;
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            GltfMaterial {
+                name: material.name().map(String::from),
+                base_color_factor: pbr.base_color_factor().into(),
+                base_color_texture: pbr.base_color_texture().map(|info| info.texture().source().index()),
+            }
+        })
+        .collect();
+
+    let images = images
+        .into_iter()
+        .map(image_to_rgb)
+        .collect::<Result<Vec<RgbImage>, Error>>()?;
+
+    let mut nodes: Vec<GltfNode> = document
+        .nodes()
+        .map(|node| {
+            let local_transform: Matrix4<f32> = node.transform().matrix().into();
+            GltfNode {
+                name: node.name().map(String::from),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                camera: None,
+                children: node.children().map(|child| child.index()).collect(),
+                local_transform,
+                world_transform: local_transform,
+            }
+        })
+        .collect();
+
+    let roots: Vec<usize> = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .map(|scene| scene.nodes().map(|node| node.index()).collect())
+        .unwrap_or_default();
+
+    // Propagate transforms from the roots down, a node only has a single parent in glTF
+    let mut in_scene = vec![false; nodes.len()];
+    let mut stack: Vec<usize> = Vec::new();
+    let mut visit = |index: usize, stack: &mut Vec<usize>| {
+        if in_scene[index] {
+            return Err(Error::InvalidGltf { message: format!("node {} is part of a cycle or has more than one parent", index) });
+        }
+        in_scene[index] = true;
+        stack.push(index);
+        Ok(())
+    };
+    for &root in &roots {
+        visit(root, &mut stack)?;
+    }
+    while let Some(index) = stack.pop() {
+        let world_transform = nodes[index].world_transform;
+        for child in nodes[index].children.clone() {
+            nodes[child].world_transform = world_transform * nodes[child].local_transform;
+            visit(child, &mut stack)?;
+        }
+    }
+
+    let mut cameras = Vec::new();
+    for node in document.nodes().filter(|node| in_scene[node.index()]) {
+        if let Some(camera) = node.camera() {
+            nodes[node.index()].camera = Some(cameras.len());
+            let isometry = isometry_from_matrix(&nodes[node.index()].world_transform);
+            cameras.push(match camera.projection() {
+                Projection::Perspective(perspective) => Camera::from_isometry(
+                    &isometry,
+                    perspective.aspect_ratio().unwrap_or(1.0),
+                    perspective.yfov(),
+                    perspective.znear(),
+                    perspective.zfar().unwrap_or(DEFAULT_FAR_PLANE),
+                    true,
+                ),
+                // Camera uses fixed orthographic extents, only the aspect ratio is kept
+                Projection::Orthographic(orthographic) => Camera::from_isometry(
+                    &isometry,
+                    orthographic.xmag() / orthographic.ymag(),
+                    0.0,
+                    orthographic.znear(),
+                    orthographic.zfar(),
+                    false,
+                ),
+            });
+        }
+    }
+
+    Ok(GltfScene {
+        meshes,
+        materials,
+        images,
+        nodes,
+        roots,
+        cameras,
+    })
+}
+
+/// Splits off the translation and rotation of a transform, ignoring scale
+fn isometry_from_matrix(matrix: &Matrix4<f32>) -> Isometry3<f32> {
+    let translation = Translation3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)]);
+    let linear = Matrix3::from_fn(|row, column| matrix[(row, column)]);
+    let x: Vector3<f32> = linear.column(0).normalize();
+    let y: Vector3<f32> = linear.column(1).normalize();
+    let z: Vector3<f32> = linear.column(2).normalize();
+    let rotation = Rotation3::from_matrix_unchecked(Matrix3::from_columns(&[x, y, z]));
+    Isometry3::from_parts(translation, UnitQuaternion::from_rotation_matrix(&rotation))
+}
+
+fn image_to_rgb(data: gltf::image::Data) -> Result<RgbImage, Error> {
+    let (width, height) = (data.width, data.height);
+    let invalid = || Error::InvalidGltf { message: "image data does not match its size".into() };
+    let wide = |pixels: Vec<u8>| -> Vec<u16> {
+        pixels.chunks_exact(2).map(|pair| u16::from_ne_bytes([pair[0], pair[1]])).collect()
+    };
+
+    let image = match data.format {
+        Format::R8 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, data.pixels).ok_or_else(invalid)?),
+        Format::R8G8 => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(width, height, data.pixels).ok_or_else(invalid)?),
+        Format::R8G8B8 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, data.pixels).ok_or_else(invalid)?),
+        Format::R8G8B8A8 => DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, data.pixels).ok_or_else(invalid)?),
+        Format::B8G8R8 => DynamicImage::ImageBgr8(ImageBuffer::from_raw(width, height, data.pixels).ok_or_else(invalid)?),
+        Format::B8G8R8A8 => DynamicImage::ImageBgra8(ImageBuffer::from_raw(width, height, data.pixels).ok_or_else(invalid)?),
+        Format::R16 => DynamicImage::ImageLuma16(ImageBuffer::from_raw(width, height, wide(data.pixels)).ok_or_else(invalid)?),
+        Format::R16G16 => DynamicImage::ImageLumaA16(ImageBuffer::from_raw(width, height, wide(data.pixels)).ok_or_else(invalid)?),
+        Format::R16G16B16 => DynamicImage::ImageRgb16(ImageBuffer::from_raw(width, height, wide(data.pixels)).ok_or_else(invalid)?),
+        Format::R16G16B16A16 => DynamicImage::ImageRgba16(ImageBuffer::from_raw(width, height, wide(data.pixels)).ok_or_else(invalid)?),
+    };
+
+    Ok(image.into_rgb8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::test_support::TempResources;
+    use crate::resources::Resources;
+
+    fn assert_triangle_scene(scene: &GltfScene) {
+        assert_eq!(scene.roots, vec![0]);
+        assert_eq!(scene.nodes[0].children, vec![1, 2]);
+
+        let triangle = &scene.nodes[1];
+        assert_eq!(triangle.name.as_deref(), Some("Triangle"));
+        assert_eq!(triangle.mesh, Some(0));
+        let origin = triangle.world_transform * Vector4::new(0., 0., 0., 1.);
+        assert_eq!(origin, Vector4::new(1., 2., 0., 1.));
+
+        let primitive = &scene.meshes[0].primitives[0];
+        assert_eq!(primitive.data.vertices.len(), 3);
+        assert_eq!(primitive.data.indices, vec![0, 1, 2]);
+        let uv = primitive.data.vertices[1].uv;
+        assert_eq!((uv.x, uv.y), (1., 0.));
+        assert_eq!(primitive.material, Some(0));
+
+        let material = &scene.materials[0];
+        assert_eq!(material.base_color_factor, Vector4::new(1., 0.5, 0.25, 1.));
+        assert_eq!(material.base_color_texture, Some(0));
+        assert_eq!(scene.images[0].dimensions(), (2, 2));
+        assert_eq!(scene.images[0].get_pixel(1, 0).0, [0, 255, 0]);
+
+        assert_eq!(scene.cameras.len(), 1);
+        let view_origin = scene.cameras[0].get_view_matrix() * Vector4::new(1., 0., 5., 1.);
+        assert!((view_origin - Vector4::new(0., 0., 0., 1.)).norm() < 1e-5);
+    }
+
+    fn resources() -> Resources {
+        Resources::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"))
+    }
+
+    #[test]
+    fn imports_gltf_with_external_buffer() {
+        assert_triangle_scene(&resources().load_gltf("models/triangle.gltf").unwrap());
+    }
+
+    #[test]
+    fn imports_glb() {
+        assert_triangle_scene(&resources().load_gltf("models/triangle.glb").unwrap());
+    }
+
+    #[test]
+    fn node_cameras_index_scene_cameras() {
+        let temp = TempResources::new("gltf_cameras");
+        temp.write("cameras.gltf", r#"{
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [1, 2] }],
+            "nodes": [
+                { "name": "Outside", "camera": 1, "translation": [0, 0, -9] },
+                { "name": "Back", "camera": 1, "translation": [0, 0, 5] },
+                { "name": "Front", "camera": 0, "translation": [3, 0, 0] }
+            ],
+            "cameras": [
+                { "type": "perspective", "perspective": { "yfov": 1.0, "znear": 0.1 } },
+                { "type": "perspective", "perspective": { "yfov": 0.5, "znear": 0.1, "zfar": 10 } }
+            ]
+        }"#);
+        let scene = temp.resources().load_gltf("cameras.gltf").unwrap();
+
+        assert_eq!(scene.cameras.len(), 2);
+        let cameras: Vec<_> = scene.nodes.iter().map(|node| node.camera).collect();
+        assert_eq!(cameras, vec![None, Some(0), Some(1)]);
+        for node in &scene.nodes[1..] {
+            let camera = &scene.cameras[node.camera.unwrap()];
+            let view_origin = camera.get_view_matrix() * node.world_transform * Vector4::new(0., 0., 0., 1.);
+            assert!((view_origin - Vector4::new(0., 0., 0., 1.)).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn rejects_node_cycles() {
+        let temp = TempResources::new("gltf_cycles");
+        temp.write("cycle.gltf", r#"{
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "children": [1] }, { "children": [0] }]
+        }"#);
+        temp.write("own_child.gltf", r#"{
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "children": [0] }]
+        }"#);
+        let res = temp.resources();
+
+        assert!(matches!(res.load_gltf("cycle.gltf"), Err(Error::InvalidGltf { .. })));
+        assert!(matches!(res.load_gltf("own_child.gltf"), Err(Error::InvalidGltf { .. })));
+    }
+}
@@ -0,0 +1,14 @@
+use crate::renderer::PosNormalUV;
+
+/// Vertex layout produced by the mesh loaders
+pub type MeshVertex = PosNormalUV;
+
+/**
+A triangulated mesh ready to be uploaded with `ArrayBuffer` and `ElementArrayBuffer`
+
+`indices` describe triangles and are meant to be drawn with `gl::UNSIGNED_INT`.
+*/
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
@@ -1,10 +1,23 @@
+#![allow(non_local_definitions)]
+
 use std::ffi;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use image;
 
+mod mesh;
+mod obj;
+mod gltf_import;
+
+pub use mesh::{MeshData, MeshVertex};
+pub use gltf_import::{GltfScene, GltfNode, GltfMesh, GltfPrimitive, GltfMaterial};
+
+/// High dynamic range image with linear 32 bit float channels
+pub type HdrImage = image::ImageBuffer<image::Rgb<f32>, Vec<f32>>;
+
 #[derive(Debug, Fail)]
 pub enum Error {
     #[fail(display = "I/O error")]
@@ -14,7 +27,15 @@ pub enum Error {
     #[fail(display = "Failed get executable path")]
     FailedToGetExePath,
     #[fail(display = "Failed to load image")]
-    FailedToLoadImage(#[cause] image::ImageError    )
+    FailedToLoadImage(#[cause] image::ImageError    ),
+    #[fail(display = "Invalid OBJ file, line {}: {}", line, message)]
+    InvalidObj { line: usize, message: String },
+    #[fail(display = "OBJ file is not valid UTF-8")]
+    ObjNotUtf8,
+    #[fail(display = "Failed to load glTF file")]
+    FailedToLoadGltf(#[cause] gltf::Error),
+    #[fail(display = "Invalid glTF file: {}", message)]
+    InvalidGltf { message: String },
 }
 
 impl From<io::Error> for Error {
@@ -29,6 +50,12 @@ impl From<image::ImageError> for Error {
     }
 }
 
+impl From<gltf::Error> for Error {
+    fn from(other: gltf::Error) -> Self {
+        Error::FailedToLoadGltf(other)
+    }
+}
+
 pub struct Resources {
     root_path: PathBuf,
 }
@@ -42,6 +69,26 @@ impl Resources {
         })
     }
 
+    pub fn from_path(root_path: &Path) -> Resources {
+        Resources {
+            root_path: root_path.into(),
+        }
+    }
+
+    /**
+    Returns when the resource was last modified, None if the file or its metadata is unavailable
+    */
+    pub fn modified_time(&self, resource_name: &str) -> Option<SystemTime> {
+        fs::metadata(resource_name_to_path(&self.root_path, resource_name))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    pub fn exists(&self, resource_name: &str) -> bool {
+        resource_name_to_path(&self.root_path, resource_name).is_file()
+    }
+
+    #[allow(clippy::manual_contains)]
     pub fn load_cstring(&self, resource_name: &str) -> Result<ffi::CString, Error> {
         let mut file = fs::File::open(resource_name_to_path(&self.root_path, resource_name))?;
 
@@ -61,6 +108,56 @@ impl Resources {
         let img = image::open(resource_name_to_path(&self.root_path, resource_name))?;
         Ok(img.into_rgb8())
     }
+
+    /// Loads an image, adding an opaque alpha channel to images without one
+    pub fn load_rgba_image(&self, resource_name: &str) -> Result<image::RgbaImage, Error> {
+        let img = image::open(resource_name_to_path(&self.root_path, resource_name))?;
+        Ok(img.into_rgba8())
+    }
+
+    /// Loads an image with 16 bits per channel, 8 bit images are scaled up
+    pub fn load_rgba16_image(&self, resource_name: &str) -> Result<image::ImageBuffer<image::Rgba<u16>, Vec<u16>>, Error> {
+        let img = image::open(resource_name_to_path(&self.root_path, resource_name))?;
+        Ok(img.into_rgba16())
+    }
+
+    /// Loads the luminance of an image with 16 bits per pixel, e.g. a height map
+    pub fn load_luma16_image(&self, resource_name: &str) -> Result<image::ImageBuffer<image::Luma<u16>, Vec<u16>>, Error> {
+        let img = image::open(resource_name_to_path(&self.root_path, resource_name))?;
+        Ok(img.into_luma16())
+    }
+
+    /**
+    Loads a Radiance .hdr image without clamping or tone mapping its colors
+    */
+    pub fn load_hdr_image(&self, resource_name: &str) -> Result<HdrImage, Error> {
+        let file = fs::File::open(resource_name_to_path(&self.root_path, resource_name))?;
+        let decoder = image::codecs::hdr::HdrDecoder::new(BufReader::new(file))?;
+        let metadata = decoder.metadata();
+        let pixels: Vec<f32> = decoder
+            .read_image_hdr()?
+            .into_iter()
+            .flat_map(|pixel| pixel.0.to_vec())
+            .collect();
+        Ok(HdrImage::from_raw(metadata.width, metadata.height, pixels)
+            .expect("Expected decoded HDR pixels to match the image size"))
+    }
+
+    /**
+    Loads a Wavefront OBJ file as a triangulated, indexed mesh
+    */
+    pub fn load_obj(&self, resource_name: &str) -> Result<MeshData, Error> {
+        let buffer = fs::read(resource_name_to_path(&self.root_path, resource_name))?;
+        let source = String::from_utf8(buffer).map_err(|_| Error::ObjNotUtf8)?;
+        obj::parse_obj(&source)
+    }
+
+    /**
+    Loads a glTF 2.0 scene from a .gltf (with its buffers and images) or .glb file
+    */
+    pub fn load_gltf(&self, resource_name: &str) -> Result<GltfScene, Error> {
+        gltf_import::import_gltf(&resource_name_to_path(&self.root_path, resource_name))
+    }
 }
 
 fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
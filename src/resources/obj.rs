@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use super::{Error, MeshData, MeshVertex};
+
+/// Indices into the position, uv and normal lists of one face corner
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+/**
+Parses the geometry of a Wavefront OBJ file
+
+Supports `v`, `vt`, `vn` and `f` statements, including negative (relative) indices.
+Every unique position/uv/normal combination becomes one vertex and polygons are
+triangulated as fans, corners without uv or normal get zeroes.
+All other statements (groups, materials, smoothing) are ignored.
+*/
+pub fn parse_obj(source: &str) -> Result<MeshData, Error> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+
+    let mut vertices: Vec<MeshVertex> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    let mut vertex_lookup: HashMap<VertexKey, u32> = HashMap::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => positions.push(parse_floats(tokens, line_number)?),
+            Some("vt") => uvs.push(parse_floats(tokens, line_number)?),
+            Some("vn") => normals.push(parse_floats(tokens, line_number)?),
+            Some("f") => {
+                let corners = tokens
+                    .map(|corner| parse_face_corner(corner, positions.len(), uvs.len(), normals.len(), line_number))
+                    .collect::<Result<Vec<VertexKey>, Error>>()?;
+                if corners.len() < 3 {
+                    return Err(invalid_obj(line_number, "face needs at least three vertices"));
+                }
+
+                let corner_indices: Vec<u32> = corners
+                    .iter()
+                    .map(|key| {
+                        *vertex_lookup.entry(*key).or_insert_with(|| {
+                            let (position, uv, normal) = *key;
+                            let [x, y, z] = positions[position];
+                            let [u, v] = uv.map(|uv| uvs[uv]).unwrap_or([0.0, 0.0]);
+                            let [nx, ny, nz] = normal.map(|normal| normals[normal]).unwrap_or([0.0, 0.0, 0.0]);
+                            vertices.push(MeshVertex {
+                                position: (x, y, z).into(),
+                                normal: (nx, ny, nz).into(),
+                                uv: (u, v).into(),
+                            });
+                            (vertices.len() - 1) as u32
+                        })
+                    })
+                    .collect();
+
+                for i in 1..corner_indices.len() - 1 {
+                    indices.extend_from_slice(&[corner_indices[0], corner_indices[i], corner_indices[i + 1]]);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    Ok(MeshData { vertices, indices })
+}
+
+/// Parses the first N floats of a statement, extra components (e.g. `w`) are ignored
+fn parse_floats<'a, const N: usize>(tokens: impl Iterator<Item = &'a str>, line: usize) -> Result<[f32; N], Error> {
+    let mut values = [0.0; N];
+    let mut count = 0;
+    for token in tokens.take(N) {
+        values[count] = token
+            .parse()
+            .map_err(|_| invalid_obj(line, &format!("invalid number '{}'", token)))?;
+        count += 1;
+    }
+
+    if count < N {
+        return Err(invalid_obj(line, &format!("expected {} components, found {}", N, count)));
+    }
+
+    Ok(values)
+}
+
+/// Parses a `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner into zero based indices
+fn parse_face_corner(corner: &str, positions: usize, uvs: usize, normals: usize, line: usize) -> Result<VertexKey, Error> {
+    let mut parts = corner.split('/');
+    let position = parts
+        .next()
+        .filter(|part| !part.is_empty())
+        .ok_or_else(|| invalid_obj(line, &format!("face corner '{}' has no position", corner)))
+        .and_then(|part| resolve_index(part, positions, line))?;
+    let uv = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, uvs, line)?),
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, normals, line)?),
+        _ => None,
+    };
+
+    Ok((position, uv, normal))
+}
+
+/// Converts a one based (or negative, relative to the end) OBJ index to a zero based index
+fn resolve_index(token: &str, count: usize, line: usize) -> Result<usize, Error> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| invalid_obj(line, &format!("invalid index '{}'", token)))?;
+
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(invalid_obj(line, &format!("index {} out of range", index)));
+    }
+
+    Ok(resolved as usize)
+}
+
+fn invalid_obj(line: usize, message: &str) -> Error {
+    Error::InvalidObj { line, message: message.into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::Resources;
+
+    #[test]
+    fn triangulates_polygons_as_fans() {
+        let mesh = parse_obj("
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            f 1 2 3 4
+        ").unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn deduplicates_index_triples() {
+        let mesh = parse_obj("
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            vt 0 0
+            vt 1 1
+            vn 0 0 1
+            f 1/1/1 2/1/1 3/1/1
+            f 1/1/1 3/1/1 2/2/1
+        ").unwrap();
+
+        // 2/2/1 differs from 2/1/1 by its uv, everything else is shared
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        let uv = mesh.vertices[3].uv;
+        assert_eq!((uv.x, uv.y), (1.0, 1.0));
+        let normal = mesh.vertices[0].normal;
+        assert_eq!((normal.x, normal.y, normal.z), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn resolves_negative_indices_and_missing_uvs() {
+        let mesh = parse_obj("
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            vn 0 0 1
+            f -3//-1 -2//-1 -1//-1
+        ").unwrap();
+
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        let position = mesh.vertices[2].position;
+        assert_eq!((position.x, position.y, position.z), (0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn loads_cube_through_resources() {
+        let res = Resources::from_path(&std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        let mesh = res.load_obj("models/cube.obj").unwrap();
+
+        // 6 quads with unique normals, 4 corners each
+        assert_eq!(mesh.vertices.len(), 24);
+        assert_eq!(mesh.indices.len(), 36);
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        match parse_obj("v 0 0 0\nv 1 0 0\nf 1 2 3") {
+            Err(Error::InvalidObj { line, .. }) => assert_eq!(line, 3),
+            _ => panic!("Expected out of range index to be rejected"),
+        }
+    }
+}